@@ -0,0 +1,373 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str;
+use std::string::String;
+use std::sync::{Arc, Mutex};
+use std::vec::Vec;
+
+use lazy_static::lazy_static;
+use regex::bytes::Regex;
+
+use crate::compression::Encoder;
+use crate::file_system::{self, WriteOptions};
+use crate::word::Word;
+
+lazy_static! {
+    static ref UUID: Regex =
+        Regex::new(r"^\{?[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}\}?$").unwrap();
+    /// A scheme like http:// or a host starting with www.
+    static ref URL: Regex = Regex::new(r"^(?i:[a-z][a-z0-9+.\-]*://[^\s]+|www\.[^\s.]+\.[^\s]+)$").unwrap();
+    static ref EMAIL: Regex = Regex::new(r"^[A-Za-z0-9._%+\-]+@[A-Za-z0-9.\-]+\.[A-Za-z]{2,}$").unwrap();
+    /// A tag like <b>, </div> or <!--, or an entity like &amp; or &#39;.
+    static ref MARKUP: Regex =
+        Regex::new(r"<\s*/?[A-Za-z!][^<>]*>|<!--|&(?:[A-Za-z]+|#[0-9]+|#x[0-9A-Fa-f]+);").unwrap();
+    /// A crypt hash like $1$salt$hash or $2b$10$...
+    static ref CRYPT: Regex = Regex::new(r"^\$[0-9a-z]{1,6}\$[./0-9A-Za-z$=,]{8,}$").unwrap();
+    static ref BASE64: Regex = Regex::new(r"^[A-Za-z0-9+/]+={0,2}$").unwrap();
+    /// The characters of a login name, like j.smith or admin1, with at least one letter.
+    static ref COMBO_USER: Regex = Regex::new(r"^[A-Za-z0-9._\-+]*[A-Za-z][A-Za-z0-9._\-+]*$").unwrap();
+}
+
+/// The lengths of the hex digests of common hashes, from half an MD5 up to SHA-512.
+const HEX_HASH_LENGTHS: &[usize] = &[16, 32, 40, 48, 56, 64, 96, 128];
+
+/// The shortest base64 that's taken for a hash rather than a word, the length of an encoded MD5.
+const MIN_BASE64_LENGTH: usize = 24;
+
+/// What a line from a list looks like, for telling passwords apart from the junk that ends up in lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    Email,
+    Url,
+    /// HTML, XML or other markup, like <br> or &amp;.
+    Markup,
+    /// A hex or base64 digest, or a crypt hash like $1$salt$hash.
+    Hash,
+    /// An IPv4 or IPv6 address, optionally with a port.
+    Ip,
+    Uuid,
+    /// A user and password, like admin1:hunter2 or admin@example.com;hunter2.
+    Combo,
+    /// Anything else, which is likely a password.
+    Plain,
+}
+
+/// Every class, in the order they're checked.
+pub const CLASSES: &[Class] = &[
+    Class::Uuid,
+    Class::Ip,
+    Class::Url,
+    Class::Email,
+    Class::Combo,
+    Class::Markup,
+    Class::Hash,
+    Class::Plain,
+];
+
+impl Class {
+    /// Returns the class with the given name, like email or combo.
+    pub fn from_name(name: &str) -> Option<Class> {
+        CLASSES.iter().copied().find(|class| class.to_string() == name)
+    }
+}
+
+impl fmt::Display for Class {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Class::Email => write!(f, "email"),
+            Class::Url => write!(f, "url"),
+            Class::Markup => write!(f, "markup"),
+            Class::Hash => write!(f, "hash"),
+            Class::Ip => write!(f, "ip"),
+            Class::Uuid => write!(f, "uuid"),
+            Class::Combo => write!(f, "combo"),
+            Class::Plain => write!(f, "plain"),
+        }
+    }
+}
+
+/// Parses a list of class names, for the stages that take them.
+/// Returns a human readable message if a name isn't a class.
+pub fn parse_classes(names: &[&str]) -> Result<Vec<Class>, String> {
+    names
+        .iter()
+        .map(|name| {
+            Class::from_name(name).ok_or_else(|| {
+                format!(
+                    "unknown class \"{}\", expected email, url, markup, hash, ip, uuid, combo or plain",
+                    name
+                )
+            })
+        })
+        .collect()
+}
+
+fn is_ip(word: &[u8]) -> bool {
+    match str::from_utf8(word) {
+        Err(_) => false,
+        Ok(text) => text.parse::<IpAddr>().is_ok() || text.parse::<SocketAddr>().is_ok(),
+    }
+}
+
+/// Returns true for a hex digest of a common hash, which needs a letter so long numbers aren't taken for one.
+fn is_hex_hash(word: &[u8]) -> bool {
+    HEX_HASH_LENGTHS.contains(&word.len())
+        && word.iter().all(u8::is_ascii_hexdigit)
+        && word.iter().any(u8::is_ascii_alphabetic)
+}
+
+/// Returns true for a login name like j.smith or admin1. Names of only letters, like hello in hello:world, could
+/// just as well be part of a password.
+fn is_login(user: &[u8]) -> bool {
+    COMBO_USER.is_match(user) && user.iter().any(|c| c.is_ascii_digit() || b"._-+".contains(c))
+}
+
+/// Returns true for an email, a login name or a hash (as in a potfile), followed by a : or ; and something after
+/// it.
+fn is_combo(word: &[u8]) -> bool {
+    let split = match word.iter().position(|&c| c == b':' || c == b';') {
+        None => return false,
+        Some(split) => split,
+    };
+    let (user, password) = (&word[..split], &word[split + 1..]);
+
+    !password.is_empty() && user.len() <= 64 && (EMAIL.is_match(user) || is_login(user) || is_hex_hash(user))
+}
+
+fn is_hash(word: &[u8]) -> bool {
+    if is_hex_hash(word) || CRYPT.is_match(word) {
+        return true;
+    }
+
+    // Base64 words need every kind of character and something only base64 has, a + or / or = padding, so long
+    // words and numbers aren't taken for hashes.
+    word.len() >= MIN_BASE64_LENGTH
+        && word.len().is_multiple_of(4)
+        && BASE64.is_match(word)
+        && word.iter().any(|c| b"+/=".contains(c))
+        && word.iter().any(u8::is_ascii_lowercase)
+        && word.iter().any(u8::is_ascii_uppercase)
+        && word.iter().any(u8::is_ascii_digit)
+}
+
+/// Returns what the word looks like, checking for each class in the order of CLASSES.
+///
+/// # Arguments
+///
+/// * `word` - The word to classify.
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(classify(&Word::from("admin@example.com")), Class::Email);
+/// assert_eq!(classify(&Word::from("admin@example.com:hunter2")), Class::Combo);
+/// assert_eq!(classify(&Word::from("hunter2")), Class::Plain);
+/// ```
+pub fn classify(word: &Word) -> Class {
+    let bytes = word.as_bytes();
+
+    if UUID.is_match(bytes) {
+        Class::Uuid
+    } else if is_ip(bytes) {
+        Class::Ip
+    } else if URL.is_match(bytes) {
+        Class::Url
+    } else if EMAIL.is_match(bytes) {
+        Class::Email
+    } else if is_combo(bytes) {
+        Class::Combo
+    } else if MARKUP.is_match(bytes) {
+        Class::Markup
+    } else if is_hash(bytes) {
+        Class::Hash
+    } else {
+        Class::Plain
+    }
+}
+
+/// The file a route writes to. It's created when the first word is routed, and finished when the last copy of the
+/// route is dropped.
+struct RouteFile {
+    path: PathBuf,
+    options: WriteOptions,
+    file: Option<Encoder<io::BufWriter<fs::File>>>,
+}
+
+impl Drop for RouteFile {
+    fn drop(&mut self) {
+        if let Some(file) = self.file.take() {
+            if let Err(why) = file.finish() {
+                eprintln!("couldn't write to {}: {}", self.path.display(), why);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for RouteFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RouteFile({})", self.path.display())
+    }
+}
+
+/// Takes words of some classes out of a stream and writes them to their own file, the same way the outputs are
+/// written. Routes write in the order words reach them, so they don't run on several threads at once.
+#[derive(Debug, Clone)]
+pub struct ClassRoute {
+    /// The file the words are written to.
+    pub path: PathBuf,
+    pub classes: Vec<Class>,
+    file: Arc<Mutex<RouteFile>>,
+}
+
+impl ClassRoute {
+    /// Makes a route for words of the classes. Its file isn't touched until a word is routed to it, and is then
+    /// replaced if it exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file to write to.
+    /// * `classes` - The classes of words that are written to it.
+    pub fn new(path: &Path, classes: Vec<Class>) -> ClassRoute {
+        ClassRoute {
+            path: path.to_path_buf(),
+            classes,
+            file: Arc::new(Mutex::new(RouteFile {
+                path: path.to_path_buf(),
+                options: WriteOptions::default(),
+                file: None,
+            })),
+        }
+    }
+
+    /// Sets how the route's file is written, like the outputs are with --hex and --compress.
+    pub fn set_write_options(&self, options: &WriteOptions) {
+        self.file.lock().unwrap().options = options.clone();
+    }
+
+    /// Writes the word to the route's file if it's one of the route's classes, otherwise hands it back.
+    pub fn route(&self, word: Word) -> Option<Word> {
+        if !self.classes.contains(&classify(&word)) {
+            return Some(word);
+        }
+
+        let mut route_file = self.file.lock().unwrap();
+        let RouteFile { path, options, file } = &mut *route_file;
+        let file = file.get_or_insert_with(|| file_system::create(path, options.compress));
+        if let Err(why) = file_system::write_word(file, &word, options) {
+            panic!("couldn't write to {}: {}", path.display(), why);
+        }
+        None
+    }
+}
+
+/// Routes are the same if they write the same classes to the same file.
+impl PartialEq for ClassRoute {
+    fn eq(&self, other: &ClassRoute) -> bool {
+        self.path == other.path && self.classes == other.classes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let cases = [
+            ("hunter2", Class::Plain),
+            ("correct horse battery staple", Class::Plain),
+            ("p@ssw0rd", Class::Plain),
+            ("12:30", Class::Plain),
+            ("admin@example.com", Class::Email),
+            ("j.smith+lists@mail.example.co.uk", Class::Email),
+            ("https://example.com/login?next=/", Class::Url),
+            ("www.example.com", Class::Url),
+            ("<br>", Class::Markup),
+            ("</div>hunter2", Class::Markup),
+            ("tom&amp;jerry", Class::Markup),
+            ("5f4dcc3b5aa765d61d8327deb882cf99", Class::Hash),
+            ("$1$saltsalt$qjXMvbEw8oaL.CzflDugX/", Class::Hash),
+            ("X03MO1qnZdYdgyfeuILPmQ==", Class::Hash),
+            ("ThisIsALongPassword1234", Class::Plain),
+            ("ThisIsALongPassword12345", Class::Plain),
+            ("1234567890123456", Class::Plain),
+            ("12345678901234567890123456789012", Class::Plain),
+            ("2fVqYJ7k/m9xQpLr0tZa+BcD", Class::Hash),
+            ("192.168.0.1", Class::Ip),
+            ("10.0.0.1:8080", Class::Ip),
+            ("fe80::1", Class::Ip),
+            ("123e4567-e89b-12d3-a456-426614174000", Class::Uuid),
+            ("admin1:hunter2", Class::Combo),
+            ("j.smith:hunter2", Class::Combo),
+            ("hello:world", Class::Plain),
+            ("admin:hunter2", Class::Plain),
+            ("admin@example.com;pass:word", Class::Combo),
+            ("5f4dcc3b5aa765d61d8327deb882cf99:password", Class::Combo),
+        ];
+
+        for (word, class) in cases.iter() {
+            assert_eq!(classify(&Word::from(*word)), *class, "{}", word);
+        }
+        assert_eq!(classify(&Word::new(b"caf\xe9".to_vec())), Class::Plain);
+    }
+
+    #[test]
+    fn test_parse_classes() {
+        assert_eq!(parse_classes(&["email", "url"]), Ok(vec![Class::Email, Class::Url]));
+        for class in CLASSES {
+            assert_eq!(Class::from_name(&class.to_string()), Some(*class));
+        }
+        assert_eq!(
+            parse_classes(&["email", "junk"]).unwrap_err(),
+            "unknown class \"junk\", expected email, url, markup, hash, ip, uuid, combo or plain"
+        );
+    }
+
+    #[test]
+    fn test_route() {
+        let path = PathBuf::from("test_classify_route.txt");
+        let route = ClassRoute::new(&path, vec![Class::Email, Class::Url]);
+        let untouched = path.exists();
+
+        let kept: Vec<Option<Word>> = ["hunter2", "admin@example.com", "http://example.com", "letmein"]
+            .iter()
+            .map(|w| route.route(Word::from(*w)))
+            .collect();
+        drop(route);
+        let written = fs::read_to_string(&path);
+
+        if let Err(why) = fs::remove_file(&path) {
+            eprintln!("couldn't remove testing file {}: {}", path.display(), why)
+        }
+
+        assert!(!untouched);
+        assert_eq!(
+            kept,
+            vec![Some(Word::from("hunter2")), None, None, Some(Word::from("letmein"))]
+        );
+        assert_eq!(written.unwrap(), "admin@example.com\nhttp://example.com\n");
+    }
+
+    #[test]
+    fn test_route_write_options() {
+        let path = PathBuf::from("test_classify_route_hex.txt");
+        let route = ClassRoute::new(&path, vec![Class::Plain]);
+        route.set_write_options(&WriteOptions {
+            hex: true,
+            ..WriteOptions::default()
+        });
+
+        route.route(Word::new(b"caf\xe9".to_vec()));
+        drop(route);
+        let written = fs::read_to_string(&path);
+
+        if let Err(why) = fs::remove_file(&path) {
+            eprintln!("couldn't remove testing file {}: {}", path.display(), why)
+        }
+
+        assert_eq!(written.unwrap(), "$HEX[636166e9]\n");
+    }
+}
@@ -0,0 +1,191 @@
+use std::vec::Vec;
+
+use crate::word::Word;
+
+/// Joins each word from one list with every word of the other lists, in order, with separators between them.
+/// Combinations outside the length limits are skipped before they're built, along with every longer combination
+/// that starts the same way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Combinator {
+    /// Every list after the first, each held in memory.
+    rest: Vec<Vec<Word>>,
+    /// What goes before each word of the rest of the lists.
+    separators: Vec<Vec<u8>>,
+    min_length: usize,
+    max_length: usize,
+    /// The shortest and longest the separators and words after each list can add, starting with the first list.
+    min_after: Vec<usize>,
+    max_after: Vec<usize>,
+}
+
+impl Combinator {
+    /// Sets up a combinator for the lists after the first one.
+    ///
+    /// # Arguments
+    ///
+    /// * `rest` - Every list after the first.
+    /// * `separators` - What goes between the words of each list and the next, one for each list in `rest`.
+    /// * `min_length` - The shortest a combination may be, in bytes.
+    /// * `max_length` - The longest a combination may be, in bytes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let years = vec![Word::from("2023"), Word::from("2024")];
+    /// let combinator = Combinator::new(vec![years], vec![b"-".to_vec()], 0, 50);
+    ///
+    /// assert!(combinator.combine(Word::from("summer")).collect::<Vec<Word>>() == vec!["summer-2023", "summer-2024"]);
+    /// ```
+    pub fn new(rest: Vec<Vec<Word>>, separators: Vec<Vec<u8>>, min_length: usize, max_length: usize) -> Combinator {
+        assert_eq!(rest.len(), separators.len(), "every list after the first needs a separator");
+
+        let mut min_after = vec![0; rest.len() + 1];
+        let mut max_after = vec![0; rest.len() + 1];
+        for i in (0..rest.len()).rev() {
+            let shortest = rest[i].iter().map(Word::len).min().unwrap_or(0);
+            let longest = rest[i].iter().map(Word::len).max().unwrap_or(0);
+            min_after[i] = min_after[i + 1] + separators[i].len() + shortest;
+            max_after[i] = max_after[i + 1] + separators[i].len() + longest;
+        }
+
+        Combinator {
+            rest,
+            separators,
+            min_length,
+            max_length,
+            min_after,
+            max_after,
+        }
+    }
+
+    /// Returns every combination starting with the word, in the order of the lists with the last one changing fastest.
+    ///
+    /// # Arguments
+    ///
+    /// * `first` - The word from the first list.
+    pub fn combine(&self, first: Word) -> Combinations<'_> {
+        Combinations {
+            combinator: self,
+            first,
+            buffer: vec![],
+            starts: vec![0; self.rest.len() + 1],
+            indexes: vec![0; self.rest.len() + 1],
+            depth: 0,
+            done: false,
+        }
+    }
+}
+
+/// The combinations starting with one word, built one at a time.
+pub struct Combinations<'a> {
+    combinator: &'a Combinator,
+    first: Word,
+    /// The combination being built, up to the current list.
+    buffer: Vec<u8>,
+    /// Where each list's separator and word start in the buffer.
+    starts: Vec<usize>,
+    /// Which word of each list is next.
+    indexes: Vec<usize>,
+    /// The list a word is being picked from, where 0 is the first word.
+    depth: usize,
+    done: bool,
+}
+
+impl<'a> Iterator for Combinations<'a> {
+    type Item = Word;
+
+    fn next(&mut self) -> Option<Word> {
+        let combinator = self.combinator;
+        let last = combinator.rest.len();
+
+        while !self.done {
+            let depth = self.depth;
+            let word = match depth {
+                0 if self.indexes[0] == 0 => Some(&self.first),
+                0 => None,
+                _ => combinator.rest[depth - 1].get(self.indexes[depth]),
+            };
+
+            let word = match word {
+                Some(word) => word,
+                None if depth == 0 => {
+                    self.done = true;
+                    break;
+                }
+                None => {
+                    self.depth -= 1;
+                    continue;
+                }
+            };
+            self.indexes[depth] += 1;
+
+            self.buffer.truncate(self.starts[depth]);
+            if depth > 0 {
+                self.buffer.extend_from_slice(&combinator.separators[depth - 1]);
+            }
+            let length = self.buffer.len() + word.len();
+            if length + combinator.min_after[depth] > combinator.max_length
+                || length + combinator.max_after[depth] < combinator.min_length
+            {
+                continue;
+            }
+            self.buffer.extend_from_slice(word.as_bytes());
+
+            if depth == last {
+                return Some(Word::from(self.buffer.clone()));
+            }
+            self.depth += 1;
+            self.starts[self.depth] = self.buffer.len();
+            self.indexes[self.depth] = 0;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(words: &[&str]) -> Vec<Word> {
+        words.iter().map(|w| Word::from(*w)).collect()
+    }
+
+    #[test]
+    fn test_combine() {
+        let combinator = Combinator::new(
+            vec![words(&["a", "bb"]), words(&["1", "22", "333"])],
+            vec![b" ".to_vec(), vec![]],
+            0,
+            usize::MAX,
+        );
+
+        assert_eq!(
+            combinator.combine(Word::from("x")).collect::<Vec<Word>>(),
+            vec!["x a1", "x a22", "x a333", "x bb1", "x bb22", "x bb333"]
+        );
+
+        let empty = Combinator::new(vec![words(&["a"]), vec![]], vec![vec![], vec![]], 0, 10);
+        assert_eq!(empty.combine(Word::from("x")).count(), 0);
+
+        let none = Combinator::new(vec![], vec![], 0, 10);
+        assert_eq!(none.combine(Word::from("x")).collect::<Vec<Word>>(), vec!["x"]);
+    }
+
+    #[test]
+    fn test_length_limits() {
+        let combinator = Combinator::new(
+            vec![words(&["a", "bb", "ccc"]), words(&["1", "22"])],
+            vec![vec![], b"-".to_vec()],
+            6,
+            7,
+        );
+
+        assert_eq!(
+            combinator.combine(Word::from("xy")).collect::<Vec<Word>>(),
+            vec!["xya-22", "xybb-1", "xybb-22", "xyccc-1"]
+        );
+        assert_eq!(combinator.combine(Word::from("toolong")).count(), 0);
+        assert_eq!(combinator.combine(Word::from("")).collect::<Vec<Word>>(), vec!["ccc-22"]);
+    }
+}
@@ -0,0 +1,117 @@
+use std::fmt;
+use std::vec::Vec;
+
+use crate::word::Word;
+
+/// The delimiters combo lists are split on, in the order they're preferred when lines have as many of each.
+pub const DELIMITERS: &[u8] = b":;|\t,";
+
+/// How many lines from the start of a file are looked at to work out its delimiter.
+pub const SAMPLE_LINES: usize = 1000;
+
+/// The delimiter of a combo list, displayed the way it'd be written on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Delimiter(pub u8);
+
+impl fmt::Display for Delimiter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            b'\t' => write!(f, "\\t"),
+            c => write!(f, "{}", c as char),
+        }
+    }
+}
+
+/// Works out which delimiter a combo list uses from some of its lines. The delimiter is the one found in the most
+/// lines, and it has to be in at least half of them.
+/// Returns None when no delimiter is in enough of the lines to be sure.
+///
+/// # Arguments
+///
+/// * `lines` - Lines from the start of the list.
+///
+/// # Example
+///
+/// ```
+/// let lines = vec![Word::from("admin@example.com:hunter2"), Word::from("root:pass;word")];
+///
+/// assert_eq!(detect_delimiter(&lines), Some(Delimiter(b':')));
+/// ```
+pub fn detect_delimiter(lines: &[Word]) -> Option<Delimiter> {
+    let counts: Vec<usize> = DELIMITERS
+        .iter()
+        .map(|delimiter| lines.iter().filter(|line| line.as_bytes().contains(delimiter)).count())
+        .collect();
+
+    let (best, count) = counts
+        .iter()
+        .enumerate()
+        .fold((0, 0), |(best, most), (i, &count)| if count > most { (i, count) } else { (best, most) });
+
+    if count == 0 || count * 2 < lines.len() {
+        None
+    } else {
+        Some(Delimiter(DELIMITERS[best]))
+    }
+}
+
+/// Splits a line of a combo list into its user and password at the first delimiter, so passwords can contain the
+/// delimiter themselves.
+/// Returns None if the line doesn't have the delimiter.
+///
+/// # Arguments
+///
+/// * `line` - The line to split.
+/// * `delimiter` - The delimiter between the user and password.
+///
+/// # Example
+///
+/// ```
+/// let split = split_combo(&Word::from("admin@example.com:pass:word"), Delimiter(b':'));
+///
+/// assert_eq!(split, Some((Word::from("admin@example.com"), Word::from("pass:word"))));
+/// ```
+pub fn split_combo(line: &Word, delimiter: Delimiter) -> Option<(Word, Word)> {
+    let bytes = line.as_bytes();
+    bytes
+        .iter()
+        .position(|&c| c == delimiter.0)
+        .map(|i| (Word::from(&bytes[..i]), Word::from(&bytes[i + 1..])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(words: &[&str]) -> Vec<Word> {
+        words.iter().map(|w| Word::from(*w)).collect()
+    }
+
+    #[test]
+    fn test_detect_delimiter() {
+        assert_eq!(
+            detect_delimiter(&words(&["a@example.com;hunter2", "b@example.com;pass:word", "junk"])),
+            Some(Delimiter(b';'))
+        );
+        assert_eq!(detect_delimiter(&words(&["a|b", "c:d"])), Some(Delimiter(b':')));
+        assert_eq!(detect_delimiter(&words(&["user\tpass"])), Some(Delimiter(b'\t')));
+        assert_eq!(detect_delimiter(&words(&["hunter2", "letmein", "pass:word"])), None);
+        assert_eq!(detect_delimiter(&[]), None);
+
+        assert_eq!(Delimiter(b'\t').to_string(), "\\t");
+        assert_eq!(Delimiter(b'|').to_string(), "|");
+    }
+
+    #[test]
+    fn test_split_combo() {
+        assert_eq!(
+            split_combo(&Word::from("admin:pass:word"), Delimiter(b':')),
+            Some((Word::from("admin"), Word::from("pass:word")))
+        );
+        assert_eq!(
+            split_combo(&Word::from("admin;"), Delimiter(b';')),
+            Some((Word::from("admin"), Word::from("")))
+        );
+        assert_eq!(split_combo(&Word::from("hunter2"), Delimiter(b':')), None);
+    }
+}
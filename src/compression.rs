@@ -0,0 +1,339 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+use std::string::String;
+
+/// The compression formats lists can be read from and written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Plain,
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+/// The file extensions of every compressed format, as (extension, format).
+const EXTENSIONS: &[(&str, Format)] = &[
+    ("gz", Format::Gzip),
+    ("bz2", Format::Bzip2),
+    ("xz", Format::Xz),
+    ("zst", Format::Zstd),
+];
+
+/// What follows a bzip2 header like BZh9: the magic number of the first block, or of the end of a stream with no
+/// blocks.
+const BZIP2_MAGICS: &[[u8; 6]] = &[*b"1AY&SY", [0x17, 0x72, 0x45, 0x38, 0x50, 0x90]];
+
+impl Format {
+    /// Returns the format with the given name or extension, like gzip or gz.
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "none" | "plain" => Some(Format::Plain),
+            "gzip" | "gz" => Some(Format::Gzip),
+            "bzip2" | "bz2" => Some(Format::Bzip2),
+            "xz" => Some(Format::Xz),
+            "zstd" | "zst" => Some(Format::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Returns the compressed format a file extension stands for, if any.
+    pub fn from_extension(extension: &str) -> Option<Format> {
+        EXTENSIONS
+            .iter()
+            .find(|(ext, _)| ext.eq_ignore_ascii_case(extension))
+            .map(|(_, format)| *format)
+    }
+
+    /// Works out the format of a file from its first few bytes. Anything that isn't compressed is plain.
+    pub fn from_magic(bytes: &[u8]) -> Format {
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            Format::Gzip
+        } else if bytes.len() >= 10
+            && bytes.starts_with(b"BZh")
+            && (b'1'..=b'9').contains(&bytes[3])
+            && BZIP2_MAGICS.iter().any(|magic| bytes[4..10] == magic[..])
+        {
+            Format::Bzip2
+        } else if bytes.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Format::Xz
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Format::Zstd
+        } else {
+            Format::Plain
+        }
+    }
+
+    /// The range of levels the format can be compressed at, and the level used when none is given.
+    fn levels(&self) -> (i32, i32, i32) {
+        match self {
+            Format::Plain => (0, 0, 0),
+            Format::Gzip => (0, 9, 6),
+            Format::Bzip2 => (1, 9, 9),
+            Format::Xz => (0, 9, 6),
+            Format::Zstd => (1, 22, 3),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Format::Plain => write!(f, "none"),
+            Format::Gzip => write!(f, "gzip"),
+            Format::Bzip2 => write!(f, "bzip2"),
+            Format::Xz => write!(f, "xz"),
+            Format::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+/// How an output is compressed: the format and the level to compress at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compression {
+    pub format: Format,
+    pub level: i32,
+}
+
+impl Compression {
+    /// No compression at all.
+    pub const PLAIN: Compression = Compression {
+        format: Format::Plain,
+        level: 0,
+    };
+
+    /// Returns the format at its default level.
+    pub fn new(format: Format) -> Compression {
+        Compression {
+            format,
+            level: format.levels().2,
+        }
+    }
+
+    /// Parses a format with an optional level, like zstd:19, gzip or none.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The format's name, optionally followed by a colon and a level.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let compression = Compression::parse("zstd:19").unwrap();
+    ///
+    /// assert_eq!(compression.format, Format::Zstd);
+    /// assert_eq!(compression.level, 19);
+    /// ```
+    pub fn parse(text: &str) -> Result<Compression, String> {
+        let (name, level) = match text.trim().split_once(':') {
+            None => (text.trim(), None),
+            Some((name, level)) => (name, Some(level)),
+        };
+
+        let format = match Format::from_name(&name.to_ascii_lowercase()) {
+            None => {
+                return Err(format!(
+                    "unknown compression \"{}\", expected none, gzip, bzip2, xz or zstd",
+                    name
+                ))
+            }
+            Some(format) => format,
+        };
+
+        let (min, max, _) = format.levels();
+        match level {
+            None => Ok(Compression::new(format)),
+            Some(_) if format == Format::Plain => {
+                Err(format!("\"{}\" can't have a compression level", text))
+            }
+            Some(level) => match level.trim().parse::<i32>() {
+                Ok(level) if level >= min && level <= max => Ok(Compression { format, level }),
+                _ => Err(format!(
+                    "invalid {} level \"{}\", expected {} to {}",
+                    format, level, min, max
+                )),
+            },
+        }
+    }
+
+    /// Works out the compression of an output from its extension, like zstd for words.lst.zst.
+    pub fn from_path(path: &Path) -> Compression {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Format::from_extension)
+            .map_or(Compression::PLAIN, Compression::new)
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.format {
+            Format::Plain => write!(f, "{}", self.format),
+            _ => write!(f, "{}:{}", self.format, self.level),
+        }
+    }
+}
+
+/// Returns true if the extension belongs to a compressed format.
+pub fn is_compressed_extension(extension: &str) -> bool {
+    Format::from_extension(extension).is_some()
+}
+
+/// Opens a file for reading, decompressing it on the fly if its first bytes show it's compressed.
+/// Files with several compressed streams back to back, like concatenated .gz files, are read to the end.
+///
+/// # Arguments
+///
+/// * `path` - The file to open.
+pub fn open(path: &Path) -> io::Result<Box<dyn BufRead>> {
+    let mut file = io::BufReader::new(fs::File::open(path)?);
+    let format = Format::from_magic(file.fill_buf()?);
+
+    Ok(match format {
+        Format::Plain => Box::new(file),
+        Format::Gzip => Box::new(io::BufReader::new(flate2::bufread::MultiGzDecoder::new(file))),
+        Format::Bzip2 => Box::new(io::BufReader::new(bzip2::bufread::MultiBzDecoder::new(file))),
+        Format::Xz => Box::new(io::BufReader::new(xz2::bufread::XzDecoder::new_multi_decoder(
+            file,
+        ))),
+        Format::Zstd => Box::new(io::BufReader::new(zstd::Decoder::with_buffer(file)?)),
+    })
+}
+
+/// A file being written to, compressed as it's written. Call finish once everything is written, so the end of
+/// the compressed stream is written and any errors are caught.
+pub enum Encoder<W: Write> {
+    Plain(W),
+    Gzip(flate2::write::GzEncoder<W>),
+    Bzip2(bzip2::write::BzEncoder<W>),
+    Xz(xz2::write::XzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> Encoder<W> {
+    /// Starts compressing everything written to the writer.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Where the compressed bytes go.
+    /// * `compression` - The format and level to compress with.
+    pub fn new(writer: W, compression: Compression) -> io::Result<Encoder<W>> {
+        let level = compression.level;
+
+        Ok(match compression.format {
+            Format::Plain => Encoder::Plain(writer),
+            Format::Gzip => Encoder::Gzip(flate2::write::GzEncoder::new(
+                writer,
+                flate2::Compression::new(level as u32),
+            )),
+            Format::Bzip2 => Encoder::Bzip2(bzip2::write::BzEncoder::new(
+                writer,
+                bzip2::Compression::new(level as u32),
+            )),
+            Format::Xz => Encoder::Xz(xz2::write::XzEncoder::new(writer, level as u32)),
+            Format::Zstd => Encoder::Zstd(zstd::Encoder::new(writer, level)?),
+        })
+    }
+
+    /// Writes the end of the compressed stream and flushes everything to the writer.
+    pub fn finish(self) -> io::Result<()> {
+        let mut writer = match self {
+            Encoder::Plain(writer) => writer,
+            Encoder::Gzip(encoder) => encoder.finish()?,
+            Encoder::Bzip2(encoder) => encoder.finish()?,
+            Encoder::Xz(encoder) => encoder.finish()?,
+            Encoder::Zstd(encoder) => encoder.finish()?,
+        };
+
+        writer.flush()
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Plain(writer) => writer.write(bytes),
+            Encoder::Gzip(encoder) => encoder.write(bytes),
+            Encoder::Bzip2(encoder) => encoder.write(bytes),
+            Encoder::Xz(encoder) => encoder.write(bytes),
+            Encoder::Zstd(encoder) => encoder.write(bytes),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(writer) => writer.flush(),
+            Encoder::Gzip(encoder) => encoder.flush(),
+            Encoder::Bzip2(encoder) => encoder.flush(),
+            Encoder::Xz(encoder) => encoder.flush(),
+            Encoder::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Compression::parse("zstd:19"),
+            Ok(Compression {
+                format: Format::Zstd,
+                level: 19
+            })
+        );
+        assert_eq!(Compression::parse("gz"), Ok(Compression::new(Format::Gzip)));
+        assert_eq!(Compression::parse("none"), Ok(Compression::PLAIN));
+        assert!(Compression::parse("zstd:23").is_err());
+        assert!(Compression::parse("none:1").is_err());
+        assert!(Compression::parse("lz4").is_err());
+    }
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(
+            Compression::from_path(Path::new("words.lst.zst")),
+            Compression::new(Format::Zstd)
+        );
+        assert_eq!(Compression::from_path(Path::new("words.lst")), Compression::PLAIN);
+    }
+
+    #[test]
+    fn test_from_magic() {
+        assert_eq!(Format::from_magic(b"BZh91AY&SY\x01"), Format::Bzip2);
+        assert_eq!(Format::from_magic(b"BZh9\x17\x72\x45\x38\x50\x90\0\0\0\0"), Format::Bzip2);
+        assert_eq!(Format::from_magic(b"BZhello\nBZh2\n"), Format::Plain);
+        assert_eq!(Format::from_magic(b"BZh91AY"), Format::Plain);
+        assert_eq!(Format::from_magic(b"\x1f\x8b\x08"), Format::Gzip);
+        assert_eq!(Format::from_magic(b"hello"), Format::Plain);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let content = "Hello\nWorld\n";
+
+        for format in &[Format::Plain, Format::Gzip, Format::Bzip2, Format::Xz, Format::Zstd] {
+            let path = std::path::PathBuf::from(format!("test_compression_round_trip_{}", format));
+
+            let mut encoder =
+                Encoder::new(fs::File::create(&path).unwrap(), Compression::new(*format)).unwrap();
+            encoder.write_all(content.as_bytes()).unwrap();
+            encoder.finish().unwrap();
+
+            let mut result = String::new();
+            let read = open(&path).and_then(|mut reader| reader.read_to_string(&mut result));
+
+            if let Err(why) = fs::remove_file(&path) {
+                eprintln!("couldn't remove testing file {}: {}", path.display(), why)
+            }
+
+            read.unwrap();
+            assert_eq!(result, content, "{}", format);
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::string::String;
+use std::vec::Vec;
+
+use serde::Deserialize;
+
+use crate::compression::Compression;
+use crate::encoding::Encoding;
+use crate::external;
+use crate::file_system::{ReadOptions, WriteOptions};
+use crate::pipeline::{self, Argument, Stage};
+use crate::processors::CountFormat;
+
+/// A config file, holding any number of named profiles.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    profiles: BTreeMap<String, RawProfile>,
+}
+
+/// A profile as it's written in the config file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawProfile {
+    sources: Vec<PathBuf>,
+    #[serde(default = "default_recursive")]
+    recursive: bool,
+    #[serde(default = "default_extensions")]
+    extensions: Vec<String>,
+    stages: Vec<RawStage>,
+    outputs: Vec<PathBuf>,
+    memory_limit: Option<String>,
+    keep_counts: Option<String>,
+    compress: Option<String>,
+    #[serde(default)]
+    hex: bool,
+    encoding: Option<String>,
+}
+
+/// A stage is either written in the pipeline language or as a table with a name and arguments.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawStage {
+    Pipeline(String),
+    Table {
+        stage: String,
+        #[serde(default)]
+        args: Vec<toml::Value>,
+    },
+}
+
+fn default_recursive() -> bool {
+    true
+}
+
+/// The extensions that are read when a profile doesn't list any.
+pub fn default_extensions() -> Vec<String> {
+    vec![String::from("txt"), String::from("lst")]
+}
+
+/// Everything needed to run one job: where words come from, what happens to them, and where they go.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub sources: Vec<PathBuf>,
+    pub recursive: bool,
+    pub extensions: Vec<String>,
+    pub stages: Vec<Stage>,
+    pub outputs: Vec<PathBuf>,
+    /// Memory limit for stages that remember words, see pipeline::Settings.
+    pub memory_limit: Option<usize>,
+    /// When set, counts are read along with the words and written out in this format, see pipeline::stream_counted.
+    pub keep_counts: Option<CountFormat>,
+    /// How outputs are compressed. When this is None, each output is compressed as its extension suggests.
+    pub compress: Option<Compression>,
+    /// Whether words are read and written in hashcat's $HEX[...] notation, see file_system::ReadOptions.
+    pub hex: bool,
+    /// The encoding every source is in. When this is None, each file's encoding is worked out from its contents.
+    pub encoding: Option<Encoding>,
+}
+
+impl Profile {
+    /// How the profile's sources are read. Each call starts a new report of converted files.
+    pub fn read_options(&self) -> ReadOptions {
+        ReadOptions {
+            hex: self.hex,
+            encoding: self.encoding,
+            ..ReadOptions::default()
+        }
+    }
+
+    /// How the profile's outputs are written.
+    pub fn write_options(&self) -> WriteOptions {
+        WriteOptions {
+            compress: self.compress,
+            hex: self.hex,
+        }
+    }
+}
+
+/// Converts a TOML value into a stage argument.
+fn to_argument(value: &toml::Value) -> Result<Argument, String> {
+    match value {
+        toml::Value::String(s) => Ok(Argument::Text(s.clone())),
+        toml::Value::Integer(n) if *n >= 0 => Ok(Argument::Number(*n as usize)),
+        other => Err(format!(
+            "arguments must be strings or positive integers, found {}",
+            other
+        )),
+    }
+}
+
+/// Converts the stages of a profile into pipeline stages.
+fn to_stages(raw: &[RawStage]) -> Result<Vec<Stage>, String> {
+    let mut stages = vec![];
+
+    for (i, stage) in raw.iter().enumerate() {
+        match stage {
+            RawStage::Pipeline(text) => match pipeline::parse(text) {
+                Err(why) => return Err(format!("stage {}: {}", i + 1, why.annotate(text))),
+                Ok(parsed) => stages.extend(parsed),
+            },
+            RawStage::Table { stage, args } => {
+                let args = args
+                    .iter()
+                    .map(to_argument)
+                    .collect::<Result<Vec<Argument>, String>>()
+                    .map_err(|why| format!("stage {}: {}", i + 1, why))?;
+
+                match Stage::from_parts(stage, &args) {
+                    Err(why) => return Err(format!("stage {}: {}", i + 1, why)),
+                    Ok(stage) => stages.push(stage),
+                }
+            }
+        }
+    }
+
+    Ok(stages)
+}
+
+/// Parses config file contents and returns the named profile.
+///
+/// # Arguments
+///
+/// * `contents` - The TOML text of a config file.
+/// * `name` - The name of the profile to return.
+///
+/// # Example
+///
+/// ```
+/// let contents = r#"
+///     [profiles.small]
+///     sources = ["lists"]
+///     stages = ["tcd", { stage = "remove_outside_lengths", args = [0, 50] }]
+///     outputs = ["small.lst"]
+/// "#;
+///
+/// let profile = parse_profile(contents, "small").unwrap();
+///
+/// assert_eq!(profile.stages.len(), 4);
+/// ```
+pub fn parse_profile(contents: &str, name: &str) -> Result<Profile, String> {
+    let mut config: ConfigFile = match toml::from_str(contents) {
+        Err(why) => return Err(why.to_string()),
+        Ok(config) => config,
+    };
+
+    let raw = match config.profiles.remove(name) {
+        None => {
+            let known: Vec<&str> = config.profiles.keys().map(String::as_str).collect();
+            return Err(format!(
+                "no profile named \"{}\", found: {}",
+                name,
+                known.join(", ")
+            ));
+        }
+        Some(raw) => raw,
+    };
+
+    let stages = to_stages(&raw.stages).map_err(|why| format!("profile {}: {}", name, why))?;
+    let memory_limit = match raw.memory_limit {
+        None => None,
+        Some(text) => {
+            Some(external::parse_size(&text).map_err(|why| format!("profile {}: {}", name, why))?)
+        }
+    };
+
+    let keep_counts = match raw.keep_counts {
+        None => None,
+        Some(format_name) => match CountFormat::from_name(&format_name) {
+            None => {
+                return Err(format!(
+                    "profile {}: unknown count format \"{}\", expected count-word or word-tab-count",
+                    name, format_name
+                ))
+            }
+            Some(format) => Some(format),
+        },
+    };
+
+    let compress = match raw.compress {
+        None => None,
+        Some(text) => {
+            Some(Compression::parse(&text).map_err(|why| format!("profile {}: {}", name, why))?)
+        }
+    };
+
+    let encoding = match raw.encoding {
+        None => None,
+        Some(encoding_name) => match Encoding::from_name(&encoding_name) {
+            None => {
+                return Err(format!(
+                    "profile {}: unknown encoding \"{}\", expected utf-8, latin-1, windows-1252, utf-16le or utf-16be",
+                    name, encoding_name
+                ))
+            }
+            Some(encoding) => Some(encoding),
+        },
+    };
+
+    Ok(Profile {
+        sources: raw.sources,
+        recursive: raw.recursive,
+        extensions: raw.extensions,
+        stages,
+        outputs: raw.outputs,
+        memory_limit,
+        keep_counts,
+        compress,
+        hex: raw.hex,
+        encoding,
+    })
+}
+
+/// Reads a config file and returns the named profile.
+///
+/// # Arguments
+///
+/// * `path` - The config file to read.
+/// * `name` - The name of the profile to return.
+pub fn load_profile(path: &PathBuf, name: &str) -> Result<Profile, String> {
+    let display = path.display();
+
+    let contents = match fs::read_to_string(path) {
+        Err(why) => return Err(format!("couldn't read {}: {}", display, why)),
+        Ok(contents) => contents,
+    };
+
+    parse_profile(&contents, name).map_err(|why| format!("{}: {}", display, why))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processors::{DedupMode, LengthUnit};
+
+    const CONFIG: &str = r#"
+        [profiles.passwords-clean]
+        sources = ["lists/one", "lists/two"]
+        stages = [
+            "trim_whitespaces",
+            "cd",
+            { stage = "remove_outside_lengths", args = [8, 64] },
+            { stage = "p", args = ["root "] },
+        ]
+        outputs = ["passwords.lst"]
+
+        [profiles.subdomains]
+        sources = ["dns"]
+        recursive = false
+        extensions = ["txt"]
+        stages = ["td"]
+        outputs = ["a.lst", "b.lst"]
+        memory_limit = "2G"
+        keep_counts = "word-tab-count"
+        compress = "zstd:19"
+        hex = true
+        encoding = "windows-1252"
+    "#;
+
+    #[test]
+    fn test_parse_profile() {
+        let result = parse_profile(CONFIG, "passwords-clean").unwrap();
+        let correct = Profile {
+            sources: vec![PathBuf::from("lists/one"), PathBuf::from("lists/two")],
+            recursive: true,
+            extensions: default_extensions(),
+            stages: vec![
+                Stage::TrimWhitespaces,
+                Stage::RemoveCounts,
+                Stage::Deduplicate(DedupMode::First, None),
+                Stage::RemoveOutsideLengths(8, 64, LengthUnit::Bytes),
+                Stage::Prefix(String::from("root ")),
+            ],
+            outputs: vec![PathBuf::from("passwords.lst")],
+            memory_limit: None,
+            keep_counts: None,
+            compress: None,
+            hex: false,
+            encoding: None,
+        };
+
+        assert_eq!(result, correct);
+    }
+
+    #[test]
+    fn test_parse_second_profile() {
+        let result = parse_profile(CONFIG, "subdomains").unwrap();
+
+        assert!(!result.recursive);
+        assert_eq!(result.extensions, vec![String::from("txt")]);
+        assert_eq!(
+            result.stages,
+            vec![Stage::TrimWhitespaces, Stage::Deduplicate(DedupMode::First, None)]
+        );
+        assert_eq!(result.outputs.len(), 2);
+        assert_eq!(result.memory_limit, Some(2 << 30));
+        assert_eq!(result.keep_counts, Some(CountFormat::WordTabCount));
+        assert_eq!(result.compress, Some(Compression::parse("zstd:19").unwrap()));
+        assert!(result.hex);
+        assert_eq!(result.encoding, Some(Encoding::Windows1252));
+    }
+
+    #[test]
+    fn test_parse_profile_errors() {
+        let error = parse_profile(CONFIG, "missing").unwrap_err();
+        assert_eq!(
+            error,
+            "no profile named \"missing\", found: passwords-clean, subdomains"
+        );
+
+        let bad_stage = r#"
+            [profiles.bad]
+            sources = ["lists"]
+            stages = ["t", "tx"]
+            outputs = ["out.lst"]
+        "#;
+        let error = parse_profile(bad_stage, "bad").unwrap_err();
+        assert!(error.starts_with("profile bad: stage 2: unknown stage"));
+
+        let bad_args = r#"
+            [profiles.bad]
+            sources = ["lists"]
+            stages = [{ stage = "remove_outside_lengths", args = [-1, 4] }]
+            outputs = ["out.lst"]
+        "#;
+        let error = parse_profile(bad_args, "bad").unwrap_err();
+        assert!(error.starts_with("profile bad: stage 1: arguments must be"));
+
+        let unknown_key = r#"
+            [profiles.bad]
+            source = ["lists"]
+            stages = ["t"]
+            outputs = ["out.lst"]
+        "#;
+        assert!(parse_profile(unknown_key, "bad").is_err());
+
+        let bad_format = r#"
+            [profiles.bad]
+            sources = ["lists"]
+            stages = ["t"]
+            outputs = ["out.lst"]
+            keep_counts = "tab"
+        "#;
+        let error = parse_profile(bad_format, "bad").unwrap_err();
+        assert!(error.starts_with("profile bad: unknown count format \"tab\""));
+
+        let bad_encoding = r#"
+            [profiles.bad]
+            sources = ["lists"]
+            stages = ["t"]
+            outputs = ["out.lst"]
+            encoding = "ebcdic"
+        "#;
+        let error = parse_profile(bad_encoding, "bad").unwrap_err();
+        assert!(error.starts_with("profile bad: unknown encoding \"ebcdic\""));
+    }
+}
@@ -0,0 +1,400 @@
+use std::char;
+use std::fmt;
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::str;
+use std::sync::{Arc, Mutex};
+use std::vec::Vec;
+
+/// How many bytes from the start of a file are looked at to work out its encoding.
+const SAMPLE_SIZE: usize = 1 << 16;
+
+/// How many bytes are read at a time when transcoding.
+const TRANSCODE_CHUNK_SIZE: usize = 1 << 13;
+
+/// The characters Windows-1252 has for bytes 0x80 to 0x9F, where Latin-1 has control characters.
+/// The five bytes Windows-1252 leaves undefined keep their Latin-1 meaning.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20ac}', '\u{81}', '\u{201a}', '\u{192}', '\u{201e}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2c6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8d}', '\u{17d}', '\u{8f}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201c}', '\u{201d}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2dc}', '\u{2122}', '\u{161}', '\u{203a}', '\u{153}', '\u{9d}', '\u{17e}', '\u{178}',
+];
+
+/// The bytes Windows-1252 doesn't define. Files that have them are binary or in some other encoding.
+const WINDOWS_1252_UNDEFINED: [u8; 5] = [0x81, 0x8d, 0x8f, 0x90, 0x9d];
+
+/// The text encodings lists can be read in. Everything is converted to UTF-8 as it's read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Latin1,
+    Windows1252,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Encoding {
+    /// Returns the encoding with the given name, like utf-8, latin-1, windows-1252, utf-16le or utf-16be.
+    pub fn from_name(name: &str) -> Option<Encoding> {
+        match name.to_ascii_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Some(Encoding::Utf8),
+            "latin-1" | "latin1" | "iso-8859-1" => Some(Encoding::Latin1),
+            "windows-1252" | "cp1252" => Some(Encoding::Windows1252),
+            "utf-16le" | "utf-16" => Some(Encoding::Utf16Le),
+            "utf-16be" => Some(Encoding::Utf16Be),
+            _ => None,
+        }
+    }
+
+    /// The byte order mark files in this encoding can start with.
+    fn bom(&self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => &[0xef, 0xbb, 0xbf],
+            Encoding::Utf16Le => &[0xff, 0xfe],
+            Encoding::Utf16Be => &[0xfe, 0xff],
+            Encoding::Latin1 | Encoding::Windows1252 => &[],
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Encoding::Utf8 => write!(f, "UTF-8"),
+            Encoding::Latin1 => write!(f, "Latin-1"),
+            Encoding::Windows1252 => write!(f, "Windows-1252"),
+            Encoding::Utf16Le => write!(f, "UTF-16LE"),
+            Encoding::Utf16Be => write!(f, "UTF-16BE"),
+        }
+    }
+}
+
+/// What was done to a file to turn it into UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conversion {
+    /// The encoding the file was read as.
+    pub encoding: Encoding,
+    /// Whether a byte order mark was removed from the start of the file.
+    pub bom: bool,
+    /// Whether the encoding was worked out from the file, rather than given.
+    pub detected: bool,
+}
+
+impl fmt::Display for Conversion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.encoding, self.bom) {
+            (Encoding::Utf8, _) => write!(f, "removed the UTF-8 byte order mark"),
+            (encoding, true) => write!(f, "{} with a byte order mark", encoding),
+            (encoding, false) if self.detected => write!(f, "{}, detected", encoding),
+            (encoding, false) => write!(f, "{}, as given", encoding),
+        }
+    }
+}
+
+/// The files that were converted to UTF-8 and how, filled in as files are read.
+/// Clones share the same list, so it can be handed to every thread that reads files.
+#[derive(Debug, Clone, Default)]
+pub struct Report(Arc<Mutex<Vec<(PathBuf, Conversion)>>>);
+
+impl Report {
+    /// Adds a converted file to the report.
+    pub fn add(&self, path: &Path, conversion: Conversion) {
+        self.0.lock().unwrap().push((path.to_path_buf(), conversion));
+    }
+
+    /// Every converted file so far, sorted by path.
+    pub fn files(&self) -> Vec<(PathBuf, Conversion)> {
+        let mut files = self.0.lock().unwrap().clone();
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+        files
+    }
+}
+
+/// Returns true if a sample of a file is valid UTF-8, other than a character cut off at the end of the sample.
+fn is_utf8(sample: &[u8]) -> bool {
+    match str::from_utf8(sample) {
+        Ok(_) => true,
+        Err(why) => why.error_len().is_none(),
+    }
+}
+
+/// Works out the encoding of a file from the first bytes of it, and whether it starts with a byte order mark.
+/// Returns None when the file is already UTF-8, or when the encoding is ambiguous, like binary data or a mix of
+/// UTF-8 and something else. Those files are read byte for byte.
+///
+/// # Arguments
+///
+/// * `sample` - The first bytes of the file.
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(detect(b"\xff\xfeh\x00i\x00"), Some((Encoding::Utf16Le, true)));
+/// assert_eq!(detect(b"caf\xe9\n"), Some((Encoding::Latin1, false)));
+/// assert_eq!(detect(b"hello\n"), None);
+/// ```
+pub fn detect(sample: &[u8]) -> Option<(Encoding, bool)> {
+    for encoding in &[Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be] {
+        if sample.starts_with(encoding.bom()) {
+            return Some((*encoding, true));
+        }
+    }
+
+    // Mostly ASCII text in UTF-16 has a zero in every other byte.
+    let pairs = sample.len() / 2;
+    if pairs >= 2 {
+        let zeros_at = |offset: usize| sample.iter().skip(offset).step_by(2).filter(|b| **b == 0).count();
+        let (even, odd) = (zeros_at(0), zeros_at(1));
+        if odd * 10 > pairs * 4 && even * 10 < pairs {
+            return Some((Encoding::Utf16Le, false));
+        }
+        if even * 10 > pairs * 4 && odd * 10 < pairs {
+            return Some((Encoding::Utf16Be, false));
+        }
+    }
+
+    if is_utf8(sample) {
+        return None;
+    }
+
+    let binary = sample
+        .iter()
+        .any(|b| *b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r'));
+    let mixed = sample
+        .utf8_chunks()
+        .any(|chunk| !chunk.valid().is_ascii());
+    let undefined = sample.iter().any(|b| WINDOWS_1252_UNDEFINED.contains(b));
+    if binary || mixed || undefined {
+        return None;
+    }
+
+    if sample.iter().any(|b| (0x80..0xa0).contains(b)) {
+        Some((Encoding::Windows1252, false))
+    } else {
+        Some((Encoding::Latin1, false))
+    }
+}
+
+/// Converts a stream of bytes in some encoding into UTF-8, a chunk at a time.
+struct Transcoder<R> {
+    inner: R,
+    encoding: Encoding,
+    /// Bytes that have been read but not converted yet, like half of a UTF-16 surrogate pair.
+    input: Vec<u8>,
+    output: Vec<u8>,
+    position: usize,
+    done: bool,
+}
+
+impl<R: Read> Transcoder<R> {
+    fn push(&mut self, c: char) {
+        let mut bytes = [0u8; 4];
+        self.output.extend_from_slice(c.encode_utf8(&mut bytes).as_bytes());
+    }
+
+    /// Converts as much of the input as possible. At the end of the stream anything left over is replaced.
+    fn convert(&mut self) {
+        let input = std::mem::take(&mut self.input);
+
+        match self.encoding {
+            Encoding::Utf8 => self.output.extend_from_slice(&input),
+            Encoding::Latin1 => input.iter().for_each(|b| self.push(*b as char)),
+            Encoding::Windows1252 => input.iter().for_each(|b| match b {
+                0x80..=0x9f => self.push(WINDOWS_1252_HIGH[(b - 0x80) as usize]),
+                _ => self.push(*b as char),
+            }),
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let mut units: Vec<u16> = input
+                    .chunks_exact(2)
+                    .map(|pair| match self.encoding {
+                        Encoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                        _ => u16::from_be_bytes([pair[0], pair[1]]),
+                    })
+                    .collect();
+                let mut left = input.len() / 2 * 2;
+
+                // Hold on to the first half of a surrogate pair until the second half is read.
+                if !self.done && matches!(units.last(), Some(0xd800..=0xdbff)) {
+                    units.pop();
+                    left -= 2;
+                }
+
+                for c in char::decode_utf16(units) {
+                    self.push(c.unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                self.input = input[left..].to_vec();
+
+                if self.done && !self.input.is_empty() {
+                    self.input.clear();
+                    self.push(char::REPLACEMENT_CHARACTER);
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read> Read for Transcoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.output.len() {
+            if self.done {
+                return Ok(0);
+            }
+
+            self.output.clear();
+            self.position = 0;
+
+            let mut chunk = [0u8; TRANSCODE_CHUNK_SIZE];
+            let read = self.inner.read(&mut chunk)?;
+            self.done = read == 0;
+            self.input.extend_from_slice(&chunk[..read]);
+            self.convert();
+        }
+
+        let available = &self.output[self.position..];
+        let length = available.len().min(buf.len());
+        buf[..length].copy_from_slice(&available[..length]);
+        self.position += length;
+
+        Ok(length)
+    }
+}
+
+/// Converts a file to UTF-8 as it's read, removing any byte order mark from the start.
+/// Returns the converted file along with what was done to it, or None if it's read as it is.
+///
+/// # Arguments
+///
+/// * `reader` - The contents of the file.
+/// * `encoding` - The encoding the file is in, or None to work it out from the start of the file (see detect).
+pub fn transcode(
+    mut reader: Box<dyn BufRead>,
+    encoding: Option<Encoding>,
+) -> io::Result<(Box<dyn BufRead>, Option<Conversion>)> {
+    let mut sample = vec![];
+    reader.by_ref().take(SAMPLE_SIZE as u64).read_to_end(&mut sample)?;
+
+    let conversion = match encoding {
+        None => detect(&sample).map(|(encoding, bom)| Conversion {
+            encoding,
+            bom,
+            detected: true,
+        }),
+        Some(encoding) => Some(Conversion {
+            encoding,
+            bom: sample.starts_with(encoding.bom()) && !encoding.bom().is_empty(),
+            detected: false,
+        }),
+    };
+
+    let conversion = match conversion {
+        Some(Conversion {
+            encoding: Encoding::Utf8,
+            bom: false,
+            ..
+        })
+        | None => return Ok((Box::new(io::Cursor::new(sample).chain(reader)), None)),
+        Some(conversion) => conversion,
+    };
+
+    if conversion.bom {
+        sample.drain(..conversion.encoding.bom().len());
+    }
+    let contents = io::Cursor::new(sample).chain(reader);
+    let transcoded: Box<dyn BufRead> = match conversion.encoding {
+        Encoding::Utf8 => Box::new(contents),
+        encoding => Box::new(io::BufReader::new(Transcoder {
+            inner: contents,
+            encoding,
+            input: vec![],
+            output: vec![],
+            position: 0,
+            done: false,
+        })),
+    };
+
+    Ok((transcoded, Some(conversion)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transcode_all(bytes: &[u8], encoding: Option<Encoding>) -> (String, Option<Conversion>) {
+        let reader: Box<dyn BufRead> = Box::new(io::Cursor::new(bytes.to_vec()));
+        let (mut reader, conversion) = transcode(reader, encoding).unwrap();
+
+        let mut text = String::new();
+        reader.read_to_string(&mut text).unwrap();
+        (text, conversion)
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(b"\xef\xbb\xbfhello"), Some((Encoding::Utf8, true)));
+        assert_eq!(detect(b"\xfe\xff\x00h\x00i"), Some((Encoding::Utf16Be, true)));
+        assert_eq!(detect(b"h\x00e\x00l\x00l\x00o\x00"), Some((Encoding::Utf16Le, false)));
+        assert_eq!(detect(b"\x00h\x00e\x00l\x00l\x00o"), Some((Encoding::Utf16Be, false)));
+        assert_eq!(detect(b"caf\xc3\xa9\n"), None);
+        assert_eq!(detect(b"caf\xc3"), None);
+        assert_eq!(detect(b"caf\xe9\nna\xefve\n"), Some((Encoding::Latin1, false)));
+        assert_eq!(detect(b"\x93quoted\x94\n"), Some((Encoding::Windows1252, false)));
+        // Binary junk, UTF-8 mixed with Latin-1, and bytes Windows-1252 doesn't have are all left alone.
+        assert_eq!(detect(b"\xe9\x01\x02\n"), None);
+        assert_eq!(detect(b"caf\xc3\xa9\ncaf\xe9\n"), None);
+        assert_eq!(detect(b"\x81\xe9\n"), None);
+    }
+
+    #[test]
+    fn test_transcode() {
+        let windows = Conversion {
+            encoding: Encoding::Windows1252,
+            bom: false,
+            detected: true,
+        };
+        assert_eq!(
+            transcode_all(b"\x93caf\xe9\x94\n", None),
+            (String::from("\u{201c}caf\u{e9}\u{201d}\n"), Some(windows))
+        );
+
+        let (text, conversion) = transcode_all(b"\xef\xbb\xbfhello\n", None);
+        assert_eq!(text, "hello\n");
+        assert_eq!(conversion.map(|c| c.bom), Some(true));
+
+        let (text, _) = transcode_all(b"\xff\xfeh\x00i\x00\n\x00=\xd8\x00\xde", None);
+        assert_eq!(text, "hi\n\u{1f600}");
+
+        let (text, conversion) = transcode_all(b"caf\xe9", Some(Encoding::Windows1252));
+        assert_eq!(text, "caf\u{e9}");
+        assert_eq!(conversion.map(|c| c.detected), Some(false));
+
+        assert_eq!(transcode_all(b"hello\n", None), (String::from("hello\n"), None));
+        assert_eq!(transcode_all(b"hello\n", Some(Encoding::Utf8)), (String::from("hello\n"), None));
+    }
+
+    #[test]
+    fn test_transcode_split_surrogates() {
+        // Reads one byte at a time, so surrogate pairs are always split between reads.
+        struct OneByte(io::Cursor<Vec<u8>>);
+        impl Read for OneByte {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let length = buf.len().min(1);
+                self.0.read(&mut buf[..length])
+            }
+        }
+
+        let mut transcoder = Transcoder {
+            inner: OneByte(io::Cursor::new(b"\x00a\xd8\x3d\xde\x00\x00b\xd8".to_vec())),
+            encoding: Encoding::Utf16Be,
+            input: vec![],
+            output: vec![],
+            position: 0,
+            done: false,
+        };
+        let mut text = String::new();
+        transcoder.read_to_string(&mut text).unwrap();
+
+        assert_eq!(text, "a\u{1f600}b\u{fffd}");
+    }
+}
@@ -0,0 +1,985 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::iter;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::string::String;
+use std::vec::Vec;
+
+use tempfile::TempDir;
+
+use crate::processors::DedupMode;
+use crate::word::Word;
+
+/// Roughly how many bytes a word takes up in a HashSet, on top of its own length.
+const WORD_OVERHEAD: usize = mem::size_of::<Word>() + 16;
+
+/// Roughly how many bytes a spelling takes up in deduplicate_grouped, on top of its own length.
+const SPELLING_OVERHEAD: usize = mem::size_of::<(Word, (u64, u64, u64))>() + 16;
+
+/// The most runs that are merged at once. When there are more, they're merged in passes first, see merge_passes.
+const MAX_OPEN_RUNS: usize = 64;
+
+/// Parses a size like 512M or 2G into a number of bytes. K, M, G and T are powers of 1024.
+///
+/// # Arguments
+///
+/// * `text` - The size, as a number with an optional suffix.
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(parse_size("2K"), Ok(2048));
+/// assert_eq!(parse_size("100"), Ok(100));
+/// ```
+pub fn parse_size(text: &str) -> Result<usize, String> {
+    let text = text.trim();
+    let (number, multiplier) = match text.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&text[..text.len() - 1], 1 << 10),
+        Some('M') => (&text[..text.len() - 1], 1 << 20),
+        Some('G') => (&text[..text.len() - 1], 1 << 30),
+        Some('T') => (&text[..text.len() - 1], 1 << 40),
+        _ => (text, 1),
+    };
+
+    match number.trim().parse::<usize>() {
+        Err(_) => Err(format!(
+            "invalid size \"{}\", expected something like 512M or 2G",
+            text
+        )),
+        Ok(n) => n
+            .checked_mul(multiplier)
+            .ok_or_else(|| format!("size \"{}\" is too large", text)),
+    }
+}
+
+/// A word along with where it was in the stream, or how many times it was seen.
+type Entry = (Word, u64);
+
+/// A spelling of a word along with the word it's grouped under, how many times it was seen, and where it was first
+/// and last seen, in that order.
+type GroupedEntry = (Word, Word, u64, u64, u64);
+
+/// Creates a run and fills it with write, panicking if either fails.
+fn write_file(path: &Path, write: impl FnOnce(&mut io::BufWriter<fs::File>) -> io::Result<()>) {
+    let display = path.display();
+
+    let file = match fs::File::create(path) {
+        Err(why) => panic!("couldn't create {}: {}", display, why),
+        Ok(file) => file,
+    };
+    let mut file = io::BufWriter::new(file);
+
+    if let Err(why) = write(&mut file).and_then(|_| file.flush()) {
+        panic!("couldn't write to {}: {}", display, why);
+    }
+}
+
+/// Writes a word as its length, then its bytes.
+fn write_word(file: &mut impl Write, word: &Word) -> io::Result<()> {
+    file.write_all(&(word.len() as u64).to_le_bytes())?;
+    file.write_all(word.as_bytes())
+}
+
+/// Writes a run of entries, each one as its position, its length, then the word.
+fn write_run(path: &Path, entries: impl IntoIterator<Item = Entry>) {
+    write_file(path, |file| {
+        for (word, index) in entries {
+            file.write_all(&index.to_le_bytes())?;
+            write_word(file, &word)?;
+        }
+        Ok(())
+    });
+}
+
+/// Writes a run of grouped entries, each one as its count, first and last positions, then the word it's grouped under
+/// and the spelling, both written like write_run's words.
+fn write_grouped_run(path: &Path, entries: impl IntoIterator<Item = GroupedEntry>) {
+    write_file(path, |file| {
+        for (key, spelling, count, first, last) in entries {
+            file.write_all(&count.to_le_bytes())?;
+            file.write_all(&first.to_le_bytes())?;
+            file.write_all(&last.to_le_bytes())?;
+            write_word(file, &key)?;
+            write_word(file, &spelling)?;
+        }
+        Ok(())
+    });
+}
+
+/// Reads the entries of a run written by write_run, in order.
+struct RunReader {
+    path: PathBuf,
+    file: io::BufReader<fs::File>,
+}
+
+impl RunReader {
+    fn open(path: PathBuf) -> RunReader {
+        let file = match fs::File::open(&path) {
+            Err(why) => panic!("couldn't open {}: {}", path.display(), why),
+            Ok(file) => io::BufReader::new(file),
+        };
+
+        RunReader { path, file }
+    }
+
+    fn read_u64(&mut self) -> Option<u64> {
+        let mut bytes = [0u8; 8];
+        match self.file.read_exact(&mut bytes) {
+            Err(ref why) if why.kind() == io::ErrorKind::UnexpectedEof => None,
+            Err(why) => panic!("couldn't read {}: {}", self.path.display(), why),
+            Ok(_) => Some(u64::from_le_bytes(bytes)),
+        }
+    }
+
+    /// Reads a number partway through an entry, where the run ending is an error.
+    fn expect_u64(&mut self) -> u64 {
+        match self.read_u64() {
+            None => panic!("corrupt run {}", self.path.display()),
+            Some(n) => n,
+        }
+    }
+
+    fn read_word(&mut self) -> Word {
+        let length = self.expect_u64() as usize;
+
+        let mut word = vec![0u8; length];
+        if let Err(why) = self.file.read_exact(&mut word) {
+            panic!("couldn't read {}: {}", self.path.display(), why);
+        }
+
+        Word::new(word)
+    }
+
+    fn next_entry(&mut self) -> Option<Entry> {
+        let index = self.read_u64()?;
+        Some((self.read_word(), index))
+    }
+
+    fn next_grouped(&mut self) -> Option<GroupedEntry> {
+        let count = self.read_u64()?;
+        let (first, last) = (self.expect_u64(), self.expect_u64());
+        let key = self.read_word();
+        Some((key, self.read_word(), count, first, last))
+    }
+}
+
+/// A temporary directory that's created the first time a run is written to it, and removed when dropped.
+struct Runs {
+    parent: PathBuf,
+    dir: Option<TempDir>,
+    written: usize,
+}
+
+impl Runs {
+    fn new(parent: &Path) -> Runs {
+        Runs {
+            parent: parent.to_path_buf(),
+            dir: None,
+            written: 0,
+        }
+    }
+
+    /// Writes entries, which must already be sorted, to a new run and returns its path.
+    fn write(&mut self, entries: impl IntoIterator<Item = Entry>) -> PathBuf {
+        let path = self.next_path();
+        write_run(&path, entries);
+        path
+    }
+
+    /// Writes grouped entries, which must already be sorted, to a new run and returns its path.
+    fn write_grouped(&mut self, entries: impl IntoIterator<Item = GroupedEntry>) -> PathBuf {
+        let path = self.next_path();
+        write_grouped_run(&path, entries);
+        path
+    }
+
+    /// Returns the path of a new run.
+    fn next_path(&mut self) -> PathBuf {
+        if self.dir.is_none() {
+            let dir = tempfile::Builder::new()
+                .prefix("word_processor")
+                .tempdir_in(&self.parent);
+            self.dir = match dir {
+                Err(why) => panic!(
+                    "couldn't create a temporary directory in {}: {}",
+                    self.parent.display(),
+                    why
+                ),
+                Ok(dir) => Some(dir),
+            };
+        }
+
+        let path = match &self.dir {
+            None => unreachable!(),
+            Some(dir) => dir.path().join(format!("run-{}", self.written)),
+        };
+        self.written += 1;
+
+        path
+    }
+}
+
+/// K-way merge of runs sorted by word, yielding every word once along with the positions (or counts) and runs it
+/// came from.
+struct WordMerge {
+    runs: Vec<RunReader>,
+    heap: BinaryHeap<Reverse<(Word, u64, usize)>>,
+}
+
+impl WordMerge {
+    fn new(paths: &[PathBuf]) -> WordMerge {
+        let mut runs: Vec<RunReader> = paths.iter().cloned().map(RunReader::open).collect();
+        let mut heap = BinaryHeap::new();
+
+        for (i, run) in runs.iter_mut().enumerate() {
+            if let Some((word, index)) = run.next_entry() {
+                heap.push(Reverse((word, index, i)));
+            }
+        }
+
+        WordMerge { runs, heap }
+    }
+
+    /// Takes the smallest entry off the heap, replacing it with the next entry from its run.
+    fn pop(&mut self) -> Option<(Word, u64, usize)> {
+        let Reverse((word, index, run)) = self.heap.pop()?;
+
+        if let Some((next, next_index)) = self.runs[run].next_entry() {
+            self.heap.push(Reverse((next, next_index, run)));
+        }
+
+        Some((word, index, run))
+    }
+}
+
+impl Iterator for WordMerge {
+    type Item = (Word, Vec<(u64, usize)>);
+
+    fn next(&mut self) -> Option<(Word, Vec<(u64, usize)>)> {
+        let (word, index, run) = self.pop()?;
+        let mut found = vec![(index, run)];
+
+        while let Some(Reverse((next, _, _))) = self.heap.peek() {
+            if *next != word {
+                break;
+            }
+            if let Some((_, index, run)) = self.pop() {
+                found.push((index, run));
+            }
+        }
+
+        Some((word, found))
+    }
+}
+
+/// K-way merge of runs sorted by position then word.
+struct IndexMerge {
+    runs: Vec<RunReader>,
+    heap: BinaryHeap<Reverse<(u64, Word, usize)>>,
+}
+
+impl IndexMerge {
+    fn new(paths: &[PathBuf]) -> IndexMerge {
+        let mut runs: Vec<RunReader> = paths.iter().cloned().map(RunReader::open).collect();
+        let mut heap = BinaryHeap::new();
+
+        for (i, run) in runs.iter_mut().enumerate() {
+            if let Some((word, index)) = run.next_entry() {
+                heap.push(Reverse((index, word, i)));
+            }
+        }
+
+        IndexMerge { runs, heap }
+    }
+
+    /// Takes the entry with the smallest position off the heap, replacing it with the next entry from its run.
+    fn pop(&mut self) -> Option<(u64, Word, usize)> {
+        let Reverse((index, word, run)) = self.heap.pop()?;
+
+        if let Some((next, next_index)) = self.runs[run].next_entry() {
+            self.heap.push(Reverse((next_index, next, run)));
+        }
+
+        Some((index, word, run))
+    }
+}
+
+/// Passes on the words of an iterator that reads runs, keeping the runs around until it's dropped.
+struct WithRuns<I> {
+    words: I,
+    _runs: Runs,
+}
+
+impl<I: Iterator<Item = Word>> Iterator for WithRuns<I> {
+    type Item = Word;
+
+    fn next(&mut self) -> Option<Word> {
+        self.words.next()
+    }
+}
+
+/// Merges runs in passes until there are at most MAX_OPEN_RUNS of them, so that merging what's left doesn't open too
+/// many files at once. Each pass merges up to MAX_OPEN_RUNS runs into a new run, keeping every entry, and removes
+/// the runs it merged.
+///
+/// # Arguments
+///
+/// * `runs` - Where the merged runs are written.
+/// * `paths` - The runs to merge.
+/// * `keep` - How many runs at the start are left as they are, and stay at the start.
+/// * `merge` - Merges a group of runs into a new run and returns its path.
+fn merge_passes(
+    runs: &mut Runs,
+    mut paths: Vec<PathBuf>,
+    keep: usize,
+    merge: impl Fn(&mut Runs, &[PathBuf]) -> PathBuf,
+) -> Vec<PathBuf> {
+    while paths.len() > MAX_OPEN_RUNS {
+        let merging = paths.split_off(keep);
+
+        for group in merging.chunks(MAX_OPEN_RUNS) {
+            let path = merge(runs, group);
+
+            for merged in group {
+                if let Err(why) = fs::remove_file(merged) {
+                    panic!("couldn't remove {}: {}", merged.display(), why);
+                }
+            }
+            paths.push(path);
+        }
+    }
+
+    paths
+}
+
+/// Merges runs sorted by word into a new run, see merge_passes.
+fn merge_by_word(runs: &mut Runs, paths: &[PathBuf]) -> PathBuf {
+    let mut merge = WordMerge::new(paths);
+    runs.write(iter::from_fn(|| merge.pop().map(|(word, index, _)| (word, index))))
+}
+
+/// Sorts entries by their position, then by word for entries that share a position.
+fn sort_by_index(entries: &mut [Entry]) {
+    entries.sort_unstable_by(|a, b| (a.1, &a.0).cmp(&(b.1, &b.0)));
+}
+
+/// Puts words back into the order of their positions, spilling sorted runs to disk as it goes over the limit.
+/// Words that share a position are sorted by their bytes.
+fn order_by_index(
+    entries: impl Iterator<Item = Entry>,
+    memory_limit: usize,
+    temp_dir: &Path,
+) -> Box<dyn Iterator<Item = Word>> {
+    let mut runs = Runs::new(temp_dir);
+    let mut paths = vec![];
+    let mut buffer: Vec<Entry> = vec![];
+    let mut buffer_bytes = 0;
+
+    for entry in entries {
+        buffer_bytes += entry.0.len() + WORD_OVERHEAD;
+        buffer.push(entry);
+
+        if buffer_bytes > memory_limit {
+            sort_by_index(&mut buffer);
+            paths.push(runs.write(buffer.drain(..)));
+            buffer_bytes = 0;
+        }
+    }
+
+    sort_by_index(&mut buffer);
+    if paths.is_empty() {
+        return Box::new(buffer.into_iter().map(|(word, _)| word));
+    }
+    if !buffer.is_empty() {
+        paths.push(runs.write(buffer));
+    }
+
+    let paths = merge_passes(&mut runs, paths, 0, |runs, group| {
+        let mut merge = IndexMerge::new(group);
+        runs.write(iter::from_fn(|| merge.pop().map(|(index, word, _)| (word, index))))
+    });
+    let mut merge = IndexMerge::new(&paths);
+    Box::new(WithRuns {
+        words: iter::from_fn(move || merge.pop().map(|(_, word, _)| word)),
+        _runs: runs,
+    })
+}
+
+/// Iterator returned by deduplicate.
+pub struct ExternalDeduplicate<I> {
+    words: I,
+    mode: DedupMode,
+    memory_limit: usize,
+    temp_dir: PathBuf,
+    /// Where runs are spilled to. It's removed along with the runs when this is dropped.
+    runs: Runs,
+    run_paths: Vec<PathBuf>,
+    /// Whether the words in the first run were already passed on, which only happens when keeping the first copy.
+    first_run_passed_on: bool,
+    position: u64,
+    /// Unique words since the last spill, along with the position of the copy being kept, or how many copies
+    /// there were when ordering by frequency.
+    seen: HashMap<Word, u64>,
+    seen_bytes: usize,
+    output: Option<Box<dyn Iterator<Item = Word>>>,
+}
+
+impl<I: Iterator<Item = Word>> ExternalDeduplicate<I> {
+    /// Sorts the words that have been seen and writes them to a new run, freeing their memory.
+    fn spill(&mut self) {
+        let mut entries: Vec<Entry> = mem::take(&mut self.seen).into_iter().collect();
+        entries.sort_unstable();
+
+        let path = self.runs.write(entries);
+        self.run_paths.push(path);
+        self.seen_bytes = 0;
+    }
+
+    /// Called once every word has been seen, returns the words that haven't been passed on yet.
+    fn finish(&mut self) -> Box<dyn Iterator<Item = Word>> {
+        if self.run_paths.is_empty() {
+            let seen = mem::take(&mut self.seen);
+            return match self.mode {
+                DedupMode::First => Box::new(iter::empty()),
+                DedupMode::Last => {
+                    Box::new(order_by_index(seen.into_iter(), usize::MAX, &self.temp_dir))
+                }
+                DedupMode::Sorted => {
+                    let mut words: Vec<Word> = seen.into_keys().collect();
+                    words.sort_unstable();
+                    Box::new(words.into_iter())
+                }
+                DedupMode::Frequency => Box::new(order_by_index(
+                    seen.into_iter().map(|(word, count)| (word, u64::MAX - count)),
+                    usize::MAX,
+                    &self.temp_dir,
+                )),
+            };
+        }
+
+        if !self.seen.is_empty() {
+            self.spill();
+        }
+        // The first run stays first when its words were already passed on, so they can be told apart.
+        let keep = if self.first_run_passed_on { 1 } else { 0 };
+        let paths = merge_passes(&mut self.runs, mem::take(&mut self.run_paths), keep, merge_by_word);
+        let merge = WordMerge::new(&paths);
+
+        match self.mode {
+            DedupMode::Sorted => Box::new(merge.map(|(word, _)| word)),
+            DedupMode::First => {
+                let passed_on = self.first_run_passed_on;
+                let entries = merge.filter_map(move |(word, found)| {
+                    if passed_on && found.iter().any(|(_, run)| *run == 0) {
+                        return None;
+                    }
+                    found
+                        .iter()
+                        .map(|(index, _)| *index)
+                        .min()
+                        .map(|i| (word, i))
+                });
+                order_by_index(entries, self.memory_limit, &self.temp_dir)
+            }
+            DedupMode::Last => {
+                let entries = merge.filter_map(|(word, found)| {
+                    found
+                        .iter()
+                        .map(|(index, _)| *index)
+                        .max()
+                        .map(|i| (word, i))
+                });
+                order_by_index(entries, self.memory_limit, &self.temp_dir)
+            }
+            DedupMode::Frequency => {
+                // Ordering by the count subtracted from the largest position puts the most common words first.
+                let entries = merge.map(|(word, found)| {
+                    let count = found.iter().map(|(count, _)| *count).sum::<u64>();
+                    (word, u64::MAX - count)
+                });
+                order_by_index(entries, self.memory_limit, &self.temp_dir)
+            }
+        }
+    }
+}
+
+impl<I: Iterator<Item = Word>> Iterator for ExternalDeduplicate<I> {
+    type Item = Word;
+
+    fn next(&mut self) -> Option<Word> {
+        if let Some(output) = &mut self.output {
+            return output.next();
+        }
+
+        while let Some(word) = self.words.next() {
+            let position = self.position;
+            self.position += 1;
+
+            if let Some(kept) = self.seen.get_mut(&word) {
+                match self.mode {
+                    DedupMode::Last => *kept = position,
+                    DedupMode::Frequency => *kept += 1,
+                    _ => {}
+                }
+                continue;
+            }
+
+            // When keeping the first copy, words are passed on as they're first seen until the limit is hit.
+            // Those words become the first run, and are skipped when the runs are merged.
+            let passing_on = self.mode == DedupMode::First && self.run_paths.is_empty();
+
+            self.seen_bytes += word.len() + WORD_OVERHEAD;
+            let kept = if self.mode == DedupMode::Frequency { 1 } else { position };
+            self.seen.insert(word.clone(), kept);
+            if self.seen_bytes > self.memory_limit {
+                self.first_run_passed_on |= passing_on;
+                self.spill();
+            }
+
+            if passing_on {
+                return Some(word);
+            }
+        }
+
+        let mut output = self.finish();
+        let word = output.next();
+        self.output = Some(output);
+        word
+    }
+}
+
+/// Deduplicates a stream of words while holding roughly memory_limit bytes of words or less.
+/// Once the unique words take up more than the limit, they're sorted and spilled to a run file in a temporary
+/// directory. When the stream ends the runs are merged. Words that need to be put back in the order they were
+/// seen are spilled and merged again by their position. The output is the same as deduplicating in memory.
+///
+/// # Arguments
+///
+/// * `words` - The stream of words to deduplicate.
+/// * `mode` - Which copy of each word to keep.
+/// * `memory_limit` - Roughly how many bytes the unique words may take up before spilling to disk.
+/// * `temp_dir` - The directory to create the temporary directory for runs in.
+///
+/// # Example
+///
+/// ```
+/// let input_words = vec!["b", "a", "b", "c", "a"].into_iter().map(Word::from);
+///
+/// let output_words: Vec<Word> =
+///     deduplicate(input_words, DedupMode::First, 1, &std::env::temp_dir()).collect();
+///
+/// assert!(output_words == vec!["b", "a", "c"]);
+/// ```
+pub fn deduplicate<I: Iterator<Item = Word>>(
+    words: I,
+    mode: DedupMode,
+    memory_limit: usize,
+    temp_dir: &Path,
+) -> ExternalDeduplicate<I> {
+    ExternalDeduplicate {
+        words,
+        mode,
+        memory_limit,
+        temp_dir: temp_dir.to_path_buf(),
+        runs: Runs::new(temp_dir),
+        run_paths: vec![],
+        first_run_passed_on: false,
+        position: 0,
+        seen: HashMap::new(),
+        seen_bytes: 0,
+        output: None,
+    }
+}
+
+/// Which spelling of each group deduplicate_grouped writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pick {
+    /// The spelling seen first.
+    First,
+    /// The spelling seen most often, ties going to the one seen first.
+    MostCommon,
+}
+
+/// K-way merge of grouped runs sorted by the word they're grouped under then spelling, yielding every spelling once
+/// with the counts and positions from each run put together.
+struct GroupedMerge {
+    runs: Vec<RunReader>,
+    heap: BinaryHeap<Reverse<(GroupedEntry, usize)>>,
+}
+
+impl GroupedMerge {
+    fn new(paths: &[PathBuf]) -> GroupedMerge {
+        let mut runs: Vec<RunReader> = paths.iter().cloned().map(RunReader::open).collect();
+        let mut heap = BinaryHeap::new();
+
+        for (i, run) in runs.iter_mut().enumerate() {
+            if let Some(entry) = run.next_grouped() {
+                heap.push(Reverse((entry, i)));
+            }
+        }
+
+        GroupedMerge { runs, heap }
+    }
+
+    /// Takes the smallest entry off the heap, replacing it with the next entry from its run.
+    fn pop(&mut self) -> Option<GroupedEntry> {
+        let Reverse((entry, run)) = self.heap.pop()?;
+
+        if let Some(next) = self.runs[run].next_grouped() {
+            self.heap.push(Reverse((next, run)));
+        }
+
+        Some(entry)
+    }
+}
+
+impl Iterator for GroupedMerge {
+    type Item = GroupedEntry;
+
+    fn next(&mut self) -> Option<GroupedEntry> {
+        let (key, spelling, mut count, mut first, mut last) = self.pop()?;
+
+        while let Some(Reverse(((next_key, next_spelling, _, _, _), _))) = self.heap.peek() {
+            if *next_key != key || *next_spelling != spelling {
+                break;
+            }
+            if let Some((_, _, next_count, next_first, next_last)) = self.pop() {
+                count = count.saturating_add(next_count);
+                first = first.min(next_first);
+                last = last.max(next_last);
+            }
+        }
+
+        Some((key, spelling, count, first, last))
+    }
+}
+
+/// Turns grouped entries sorted by the word they're grouped under into one entry for each of those words: the
+/// spelling picked for it, how many times it was seen in any spelling, and where it was first and last seen.
+fn groups(entries: impl Iterator<Item = GroupedEntry>, pick: Pick) -> impl Iterator<Item = (Word, u64, u64, u64)> {
+    let mut entries = entries.peekable();
+
+    iter::from_fn(move || {
+        let (key, mut spelling, mut count, mut first, mut last) = entries.next()?;
+        let (mut picked_count, mut picked_first) = (count, first);
+
+        while let Some((_, next_spelling, next_count, next_first, next_last)) = entries.next_if(|next| next.0 == key) {
+            let better = match pick {
+                Pick::First => next_first < picked_first,
+                Pick::MostCommon => (next_count, Reverse(next_first)) > (picked_count, Reverse(picked_first)),
+            };
+            if better {
+                spelling = next_spelling;
+                picked_count = next_count;
+                picked_first = next_first;
+            }
+            count = count.saturating_add(next_count);
+            first = first.min(next_first);
+            last = last.max(next_last);
+        }
+
+        Some((spelling, count, first, last))
+    })
+}
+
+/// Deduplicates every word of a stream by the word it's grouped under, see deduplicate_grouped.
+fn deduplicate_groups(
+    words: impl Iterator<Item = Word>,
+    key: &impl Fn(&Word) -> Word,
+    mode: DedupMode,
+    pick: Pick,
+    memory_limit: usize,
+    temp_dir: &Path,
+) -> Box<dyn Iterator<Item = Word>> {
+    let mut runs = Runs::new(temp_dir);
+    let mut paths = vec![];
+    // How many times each spelling was seen since the last spill, and where it was first and last seen.
+    let mut seen: HashMap<Word, (u64, u64, u64)> = HashMap::new();
+    let mut seen_bytes = 0;
+
+    let sorted = |seen: HashMap<Word, (u64, u64, u64)>| {
+        let mut entries: Vec<GroupedEntry> = seen
+            .into_iter()
+            .map(|(spelling, (count, first, last))| (key(&spelling), spelling, count, first, last))
+            .collect();
+        entries.sort_unstable();
+        entries
+    };
+
+    for (position, word) in (0u64..).zip(words) {
+        if let Some((count, _, last)) = seen.get_mut(&word) {
+            *count += 1;
+            *last = position;
+            continue;
+        }
+
+        seen_bytes += word.len() + SPELLING_OVERHEAD;
+        seen.insert(word, (1, position, position));
+        if seen_bytes > memory_limit {
+            paths.push(runs.write_grouped(sorted(mem::take(&mut seen))));
+            seen_bytes = 0;
+        }
+    }
+
+    let entries: Box<dyn Iterator<Item = GroupedEntry>> = if paths.is_empty() {
+        Box::new(sorted(seen).into_iter())
+    } else {
+        if !seen.is_empty() {
+            paths.push(runs.write_grouped(sorted(seen)));
+        }
+        let paths = merge_passes(&mut runs, paths, 0, |runs, group| runs.write_grouped(GroupedMerge::new(group)));
+        Box::new(GroupedMerge::new(&paths))
+    };
+    let groups = groups(entries, pick);
+
+    // Only sorted words come out of the merge in the order they're written, the rest are put in order first.
+    match mode {
+        DedupMode::Sorted => Box::new(WithRuns {
+            words: groups.map(|(spelling, _, _, _)| spelling),
+            _runs: runs,
+        }),
+        DedupMode::First => order_by_index(groups.map(|(spelling, _, first, _)| (spelling, first)), memory_limit, temp_dir),
+        DedupMode::Last => order_by_index(groups.map(|(spelling, _, _, last)| (spelling, last)), memory_limit, temp_dir),
+        DedupMode::Frequency => order_by_index(
+            groups.map(|(spelling, count, _, _)| (spelling, u64::MAX - count)),
+            memory_limit,
+            temp_dir,
+        ),
+    }
+}
+
+/// Deduplicates a stream of words by the word each one is grouped under, like its normal form or lowercase form,
+/// writing each group in the spelling that's picked for it. Works like deduplicate, except that nothing is passed on until the stream ends and
+/// every spelling is held along with how many times it was seen and where, so that the runs it spills keep the
+/// spellings too. Words of a group that's seen the same number of times as another when ordering by frequency are
+/// sorted by their spelling.
+///
+/// # Arguments
+///
+/// * `words` - The stream of words to deduplicate.
+/// * `key` - Returns the word a word is grouped under.
+/// * `mode` - Which copy of each group decides where it goes.
+/// * `pick` - Which spelling each group is written in.
+/// * `memory_limit` - Roughly how many bytes the spellings may take up before spilling to disk.
+/// * `temp_dir` - The directory to create the temporary directory for runs in.
+///
+/// # Example
+///
+/// ```
+/// let input_words = vec!["cafe\u{301}", "hello", "caf\u{e9}"].into_iter().map(Word::from);
+/// let nfc = |w: &Word| processors::normalize_word(w.clone(), NormalForm::Nfc);
+///
+/// let output_words: Vec<Word> =
+///     deduplicate_grouped(input_words, nfc, DedupMode::Last, Pick::First, 1, &std::env::temp_dir()).collect();
+///
+/// assert!(output_words == vec!["hello", "cafe\u{301}"]);
+/// ```
+pub fn deduplicate_grouped<'a, I, K>(
+    words: I,
+    key: K,
+    mode: DedupMode,
+    pick: Pick,
+    memory_limit: usize,
+    temp_dir: &Path,
+) -> Box<dyn Iterator<Item = Word> + 'a>
+where
+    I: Iterator<Item = Word> + 'a,
+    K: Fn(&Word) -> Word + 'a,
+{
+    let temp_dir = temp_dir.to_path_buf();
+    // Nothing is read until the first word is asked for.
+    Box::new(
+        iter::once(words).flat_map(move |words| deduplicate_groups(words, &key, mode, pick, memory_limit, &temp_dir)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_words(words: &[&str]) -> Vec<Word> {
+        words.iter().map(|w| Word::from(*w)).collect()
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("100"), Ok(100));
+        assert_eq!(parse_size("4k"), Ok(4096));
+        assert_eq!(parse_size("512M"), Ok(512 << 20));
+        assert_eq!(parse_size(" 2G "), Ok(2 << 30));
+        assert!(parse_size("2X").is_err());
+        assert!(parse_size("G").is_err());
+    }
+
+    fn deduplicate_all(words: &[Word], mode: DedupMode, limit: usize) -> Vec<Word> {
+        deduplicate(words.iter().cloned(), mode, limit, &std::env::temp_dir()).collect()
+    }
+
+    #[test]
+    fn test_deduplicate_in_memory() {
+        let words = to_words(&["b", "a", "b", "c", "a"]);
+
+        assert_eq!(
+            deduplicate_all(&words, DedupMode::First, 1 << 20),
+            to_words(&["b", "a", "c"])
+        );
+        assert_eq!(
+            deduplicate_all(&words, DedupMode::Last, 1 << 20),
+            to_words(&["b", "c", "a"])
+        );
+        assert_eq!(
+            deduplicate_all(&words, DedupMode::Sorted, 1 << 20),
+            to_words(&["a", "b", "c"])
+        );
+    }
+
+    #[test]
+    fn test_deduplicate_spills() {
+        let words = to_words(&[
+            "kiwi", "apple", "kiwi", "fig", "pear", "apple", "date", "fig", "lime", "plum", "date",
+            "kiwi", "plum",
+        ]);
+        let limit = 3 * (4 + WORD_OVERHEAD);
+
+        assert_eq!(
+            deduplicate_all(&words, DedupMode::First, limit),
+            to_words(&["kiwi", "apple", "fig", "pear", "date", "lime", "plum"])
+        );
+        assert_eq!(
+            deduplicate_all(&words, DedupMode::Last, limit),
+            to_words(&["pear", "apple", "fig", "lime", "date", "kiwi", "plum"])
+        );
+        assert_eq!(
+            deduplicate_all(&words, DedupMode::Sorted, limit),
+            to_words(&["apple", "date", "fig", "kiwi", "lime", "pear", "plum"])
+        );
+        assert_eq!(
+            deduplicate_all(&words, DedupMode::Frequency, limit),
+            to_words(&["kiwi", "apple", "date", "fig", "plum", "lime", "pear"])
+        );
+    }
+
+    #[test]
+    fn test_deduplicate_matches_in_memory() {
+        let words: Vec<Word> = (0..5000)
+            .map(|i| Word::from(format!("word{}", (i * 7919) % 1300)))
+            .collect();
+
+        for mode in &[
+            DedupMode::First,
+            DedupMode::Last,
+            DedupMode::Sorted,
+            DedupMode::Frequency,
+        ] {
+            let correct = crate::processors::deduplicate_with(words.clone(), *mode);
+
+            assert_eq!(deduplicate_all(&words, *mode, 1000), correct);
+            assert_eq!(deduplicate_all(&words, *mode, usize::MAX), correct);
+        }
+    }
+
+    #[test]
+    fn test_deduplicate_grouped() {
+        use crate::processors::{self, NormalForm, Normalization, Spelling};
+
+        // Each word is spelled two ways, so the spellings of a group end up in different runs.
+        let words: Vec<Word> = (0..1000)
+            .map(|i| {
+                let n = (i * 7919) % 150;
+                match i % 3 {
+                    0 => Word::from(format!("cafe\u{301}{}", n)),
+                    _ => Word::from(format!("caf\u{e9}{}", n)),
+                }
+            })
+            .collect();
+        let nfc = |w: &Word| processors::normalize_word(w.clone(), NormalForm::Nfc);
+        let normalization = Normalization {
+            form: NormalForm::Nfc,
+            spelling: Spelling::Original,
+        };
+
+        for mode in &[
+            DedupMode::First,
+            DedupMode::Last,
+            DedupMode::Sorted,
+            DedupMode::Frequency,
+        ] {
+            let correct = processors::deduplicate_normalized(words.clone(), *mode, normalization);
+            let grouped = |limit| -> Vec<Word> {
+                let words = words.iter().cloned();
+                deduplicate_grouped(words, nfc, *mode, Pick::First, limit, &std::env::temp_dir()).collect()
+            };
+
+            assert_eq!(grouped(1), correct, "{}", mode);
+            assert_eq!(grouped(usize::MAX), correct, "{}", mode);
+        }
+    }
+
+    #[test]
+    fn test_deduplicate_caseless() {
+        let words: Vec<Word> = (0..1000)
+            .map(|i| {
+                let n = (i * 7919) % 150;
+                match i % 5 {
+                    0 | 3 => Word::from(format!("Password{}", n)),
+                    1 => Word::from(format!("PASSWORD{}", n)),
+                    _ => Word::from(format!("password{}", n)),
+                }
+            })
+            .collect();
+        let lowercase = |w: &Word| crate::processors::lowercase_word(w.clone());
+        let correct = crate::processors::deduplicate_caseless(words.clone());
+
+        for limit in &[1, usize::MAX] {
+            let words = words.iter().cloned();
+            let result: Vec<Word> =
+                deduplicate_grouped(words, lowercase, DedupMode::First, Pick::MostCommon, *limit, &std::env::temp_dir())
+                    .collect();
+            assert_eq!(result, correct);
+        }
+    }
+
+    #[test]
+    fn test_merge_passes() {
+        let mut runs = Runs::new(&std::env::temp_dir());
+        let paths: Vec<PathBuf> = (0..200u64)
+            .map(|i| runs.write(vec![(Word::from(format!("word{}", i % 7)), i)]))
+            .collect();
+        let first = paths[0].clone();
+
+        let merged = merge_passes(&mut runs, paths, 1, merge_by_word);
+        assert!(merged.len() <= MAX_OPEN_RUNS);
+        assert_eq!(merged[0], first);
+
+        let entries: Vec<(Word, u64, usize)> = iter::from_fn({
+            let mut merge = WordMerge::new(&merged);
+            move || merge.pop()
+        })
+        .collect();
+        assert_eq!(entries.len(), 200);
+        assert!(entries.windows(2).all(|pair| (&pair[0].0, pair[0].1) <= (&pair[1].0, pair[1].1)));
+    }
+
+    #[test]
+    fn test_deduplicate_many_runs() {
+        // With the smallest limit every unique word is spilled to its own run, which is far more than MAX_OPEN_RUNS.
+        let words: Vec<Word> = (0..3000)
+            .map(|i| Word::from(format!("word{}", (i * 7919) % 900)))
+            .collect();
+
+        for mode in &[
+            DedupMode::First,
+            DedupMode::Last,
+            DedupMode::Sorted,
+            DedupMode::Frequency,
+        ] {
+            let correct = crate::processors::deduplicate_with(words.clone(), *mode);
+
+            assert_eq!(deduplicate_all(&words, *mode, 1), correct);
+        }
+    }
+}
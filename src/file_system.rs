@@ -1,16 +1,53 @@
-use std::error::Error;
 use std::fs;
 use std::io;
 use std::io::prelude::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
 use std::vec::Vec;
 
+use rayon::prelude::*;
+
+use crate::compression::{self, Compression, Encoder};
+use crate::encoding::{self, Encoding, Report};
+use crate::word::Word;
+
+/// How many words are sent at a time when reading files on other threads.
+const READ_CHUNK_SIZE: usize = 1 << 14;
+
+/// How many chunks of words each file may have read ahead of what's been used.
+const CHUNKS_IN_FLIGHT: usize = 4;
+
+/// The start of a word written in hashcat's $HEX[...] notation.
+const HEX_START: &[u8] = b"$HEX[";
+
+/// How files are read.
+#[derive(Debug, Clone, Default)]
+pub struct ReadOptions {
+    /// Whether lines written as $HEX[...] are decoded into their bytes, see decode_hex.
+    pub hex: bool,
+    /// The encoding every file is in. When this is None, each file's encoding is worked out from its contents,
+    /// see encoding::detect.
+    pub encoding: Option<Encoding>,
+    /// Where files that were converted to UTF-8 are listed.
+    pub report: Report,
+}
+
+/// How files are written.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WriteOptions {
+    /// How to compress the files. When this is None, files are compressed as their extensions suggest.
+    pub compress: Option<Compression>,
+    /// Whether words that can't be written as they are get written as $HEX[...], see encode_hex.
+    pub hex: bool,
+}
+
 pub fn list_files(root: &PathBuf, recursive: bool) -> Vec<PathBuf> {
     let mut files: Vec<PathBuf> = Vec::<PathBuf>::new();
 
     for res in fs::read_dir(root).unwrap() {
         let path = match res {
-            Err(why) => panic!("failed to read directory {}", why.to_string()),
+            Err(why) => panic!("failed to read directory {}", why),
             Ok(data) => data.path(),
         };
 
@@ -27,76 +64,338 @@ pub fn list_files(root: &PathBuf, recursive: bool) -> Vec<PathBuf> {
 }
 
 /// Read a file, seperating words by newline characters.
-fn read_lines(path: &PathBuf) -> Vec<String> {
-    let display = path.display();
+fn read_lines(path: &Path, options: &ReadOptions) -> Vec<Word> {
+    stream_lines(path.to_path_buf(), options).collect()
+}
 
-    let file = match fs::File::open(path) {
-        Err(why) => panic!("couldn't open {}: {}", display, why.to_string()),
-        Ok(file) => file,
-    };
+/// Streams the words of a file one at a time, seperating words by newline characters.
+/// A carriage return before the newline is removed, and empty lines are skipped. Lines are kept byte for byte,
+/// whether or not they're valid UTF-8.
+fn stream_lines(path: PathBuf, options: &ReadOptions) -> impl Iterator<Item = Word> {
+    match open_lines(&path, options) {
+        Err(why) => panic!("{}", why),
+        Ok(lines) => lines,
+    }
+}
 
-    let lines = io::BufReader::new(file).lines();
-    let mut contents: Vec<String> = vec![];
+/// Like stream_lines, but returns an error instead of panicking if the file can't be opened.
+/// Compressed files are decompressed as they're read, see compression::open, and files in other encodings are
+/// converted to UTF-8, see encoding::transcode.
+fn open_lines(path: &Path, options: &ReadOptions) -> Result<impl Iterator<Item = Word>, String> {
+    let display = path.display().to_string();
+    let hex = options.hex;
+
+    let file = match compression::open(path).and_then(|file| encoding::transcode(file, options.encoding)) {
+        Err(why) => return Err(format!("couldn't open {}: {}", display, why)),
+        Ok((file, None)) => file,
+        Ok((file, Some(conversion))) => {
+            options.report.add(path, conversion);
+            file
+        }
+    };
 
-    for line in lines {
-        let l: String = match line {
+    Ok(file
+        .split(b'\n')
+        .filter_map(move |line| match line {
             Err(why) => {
-                eprintln!("error in file {}: {}", display, why.to_string());
-                String::from("")
+                eprintln!("error in file {}: {}", display, why);
+                None
             }
-            Ok(data) => data,
-        };
+            Ok(mut data) => {
+                if data.last() == Some(&b'\r') {
+                    data.pop();
+                }
+                let word = Word::new(data);
+                Some(if hex { decode_hex(word) } else { word }).filter(|l| !l.is_empty())
+            }
+        }))
+}
 
-        if !l.is_empty() {
-            contents.push(l);
-        }
+/// Returns true if a word has to be written as $HEX[...] to be read back exactly. That's when it isn't valid
+/// UTF-8, has a control character like the newline that separates words, or already looks like $HEX[...].
+fn needs_hex(word: &Word) -> bool {
+    match word.to_str() {
+        None => true,
+        Some(text) => text.as_bytes().starts_with(HEX_START) || text.chars().any(char::is_control),
     }
+}
 
-    contents
+/// Returns the word in hashcat's $HEX[...] notation if it can't be written as it is, otherwise returns it unchanged.
+/// Hashcat and John read words written this way back into the same bytes.
+///
+/// # Arguments
+///
+/// * `word` - The word to encode.
+///
+/// # Example
+///
+/// ```
+/// assert!(encode_hex(Word::new(b"ab\xc3".to_vec())) == "$HEX[6162c3]");
+/// assert!(encode_hex(Word::from("abc")) == "abc");
+/// ```
+pub fn encode_hex(word: Word) -> Word {
+    if !needs_hex(&word) {
+        return word;
+    }
+
+    let mut encoded = String::from("$HEX[");
+    for byte in word.as_bytes() {
+        encoded.push_str(&format!("{:02x}", byte));
+    }
+    encoded.push(']');
+
+    Word::from(encoded)
+}
+
+/// Returns the bytes of a word written in hashcat's $HEX[...] notation. Words that aren't written that way, or
+/// that don't have an even number of hex digits between the brackets, are returned unchanged.
+///
+/// # Arguments
+///
+/// * `word` - The word to decode.
+///
+/// # Example
+///
+/// ```
+/// assert!(decode_hex(Word::from("$HEX[6162C3]")) == Word::new(b"ab\xc3".to_vec()));
+/// assert!(decode_hex(Word::from("$HEX[616]")) == "$HEX[616]");
+/// ```
+pub fn decode_hex(word: Word) -> Word {
+    let bytes = word.as_bytes();
+    if !bytes.starts_with(HEX_START) || bytes.last() != Some(&b']') {
+        return word;
+    }
+
+    let digits = &bytes[HEX_START.len()..bytes.len() - 1];
+    if !digits.len().is_multiple_of(2) {
+        return word;
+    }
+
+    let decoded: Option<Vec<u8>> = digits
+        .chunks(2)
+        .map(|pair| {
+            let high = (pair[0] as char).to_digit(16)?;
+            let low = (pair[1] as char).to_digit(16)?;
+            Some((high * 16 + low) as u8)
+        })
+        .collect();
+
+    match decoded {
+        None => word,
+        Some(decoded) => Word::new(decoded),
+    }
 }
 
 /// Removes all files that don't have one of the given extensions.
+/// Compressed files are kept if the extension before the compressed one is given, like list.lst.gz for lst.
 pub fn filter_extensions(files: Vec<PathBuf>, exts: Vec<&str>) -> Vec<PathBuf> {
+    let has_extension = |f: &Path| match f.extension() {
+        None => false,
+        Some(ext) => match ext.to_str() {
+            None => false,
+            Some(ext) => exts.contains(&ext),
+        },
+    };
+
     files
         .into_iter()
-        .filter(|f| match f.extension() {
-            None => false,
-            Some(ext) => match ext.to_str() {
-                None => false,
-                Some(ext) => exts.contains(&ext),
-            },
+        .filter(|f| {
+            if has_extension(f) {
+                return true;
+            }
+            match f.extension().and_then(|ext| ext.to_str()) {
+                Some(ext) if compression::is_compressed_extension(ext) => {
+                    has_extension(&f.with_extension(""))
+                }
+                _ => false,
+            }
         })
         .collect()
 }
 
 /// Reads all files found in the given path and returns all words.
-pub fn read_files(files: Vec<PathBuf>) -> Vec<String> {
-    let mut words: Vec<String> = Vec::<String>::new();
+pub fn read_files(files: Vec<PathBuf>, options: &ReadOptions) -> Vec<Word> {
+    let mut words: Vec<Word> = Vec::<Word>::new();
 
     for file in files {
-        words.extend(read_lines(&file));
+        words.extend(read_lines(&file, options));
     }
     words.shrink_to_fit();
 
     words
 }
 
+/// Reads all files found in the given path and returns all words, reading files on every thread.
+/// Words are in the same order as read_files returns them.
+pub fn read_files_parallel(files: Vec<PathBuf>, options: &ReadOptions) -> Vec<Word> {
+    let words: Vec<Vec<Word>> = files.par_iter().map(|file| read_lines(file, options)).collect();
+
+    words.into_iter().flatten().collect()
+}
+
+/// Streams the words of all the given files, one file after another.
+/// Files are only opened once the words before them have been used.
+pub fn stream_files(files: Vec<PathBuf>, options: &ReadOptions) -> impl Iterator<Item = Word> {
+    let options = options.clone();
+    files.into_iter().flat_map(move |file| stream_lines(file, &options))
+}
+
+/// Creates a file to write words to, compressed as given or as its extension suggests when compress is None.
+pub fn create(path: &Path, compress: Option<Compression>) -> Encoder<io::BufWriter<fs::File>> {
+    let display = path.display();
+    let compression = compress.unwrap_or_else(|| Compression::from_path(path));
+
+    let file = match fs::File::create(path) {
+        Err(why) => panic!("couldn't create {}: {}", display, why),
+        Ok(file) => io::BufWriter::new(file),
+    };
+
+    match Encoder::new(file, compression) {
+        Err(why) => panic!("couldn't start compressing {}: {}", display, why),
+        Ok(encoder) => encoder,
+    }
+}
+
+/// Writes one word followed by the separator, encoding it first if the options ask for it.
+pub fn write_word(file: &mut impl Write, word: &Word, options: &WriteOptions) -> io::Result<()> {
+    let sep = b"\n";
+
+    if options.hex && needs_hex(word) {
+        file.write_all(encode_hex(word.clone()).as_bytes())?;
+    } else {
+        file.write_all(word.as_bytes())?;
+    }
+    file.write_all(sep)
+}
+
 /// Write words, seperating by a newline character.
-pub fn write_words(path: &PathBuf, words: &Vec<String>) {
-    let sep = "\n";
+/// The file is compressed as the options say, or as its extension suggests.
+pub fn write_words(path: &Path, options: &WriteOptions, words: &Vec<Word>) {
     let display = path.display();
 
-    let mut file = match fs::File::create(&path) {
-        Err(why) => panic!("couldn't create {}: {}", display, why.to_string()),
-        Ok(file) => file,
+    let mut file = create(path, options.compress);
+
+    for word in words {
+        if let Err(why) = write_word(&mut file, word, options) {
+            panic!("couldn't write to {}: {}", display, why);
+        }
+    }
+
+    if let Err(why) = file.finish() {
+        panic!("couldn't write to {}: {}", display, why);
+    }
+}
+
+/// Reads one file in chunks of words, sending them down a channel. Stops early if nothing is listening.
+fn send_chunks(
+    path: &Path,
+    options: &ReadOptions,
+    sender: &mpsc::SyncSender<Result<Vec<Word>, String>>,
+) {
+    let mut lines = match open_lines(path, options) {
+        Err(why) => {
+            let _ = sender.send(Err(why));
+            return;
+        }
+        Ok(lines) => lines,
     };
 
+    loop {
+        let chunk: Vec<Word> = lines.by_ref().take(READ_CHUNK_SIZE).collect();
+        if chunk.is_empty() || sender.send(Ok(chunk)).is_err() {
+            return;
+        }
+    }
+}
+
+/// Streams the words of all the given files, reading up to `threads` files at the same time.
+/// When ordered is true, words come out in the same order as stream_files gives them. Otherwise words
+/// come out as soon as they're read, so the order depends on how quickly each file is read.
+/// Only a few chunks of words per thread are held in memory at once.
+///
+/// # Arguments
+///
+/// * `files` - The files to read.
+/// * `threads` - How many files to read at the same time.
+/// * `ordered` - Whether words must come out in the order of the files.
+/// * `options` - How to read the files.
+pub fn stream_files_parallel(
+    files: Vec<PathBuf>,
+    threads: usize,
+    ordered: bool,
+    options: &ReadOptions,
+) -> Box<dyn Iterator<Item = Word>> {
+    let mut jobs = vec![];
+    let mut receivers = vec![];
+
+    if ordered {
+        for file in files {
+            let (sender, receiver) = mpsc::sync_channel(CHUNKS_IN_FLIGHT);
+            jobs.push((file, sender));
+            receivers.push(receiver);
+        }
+    } else {
+        let (sender, receiver) = mpsc::sync_channel(CHUNKS_IN_FLIGHT * threads);
+        for file in files {
+            jobs.push((file, sender.clone()));
+        }
+        receivers.push(receiver);
+    }
+
+    // Files are handed out in order, so the file the reader is waiting on has always been started.
+    let jobs = Arc::new(Mutex::new(jobs.into_iter()));
+    for _ in 0..threads.max(1) {
+        let jobs = Arc::clone(&jobs);
+        let options = options.clone();
+        thread::spawn(move || loop {
+            let job = jobs.lock().unwrap().next();
+            match job {
+                None => return,
+                Some((path, sender)) => send_chunks(&path, &options, &sender),
+            }
+        });
+    }
+
+    Box::new(
+        receivers
+            .into_iter()
+            .flat_map(|receiver| receiver.into_iter())
+            .flat_map(|chunk| match chunk {
+                Err(why) => panic!("{}", why),
+                Ok(words) => words,
+            }),
+    )
+}
+
+/// Write words from a stream to every given path, seperating by a newline character.
+/// Files are compressed as the options say, or as their extensions suggest.
+/// Returns the number of words written.
+pub fn write_stream(
+    paths: &[PathBuf],
+    options: &WriteOptions,
+    words: impl Iterator<Item = Word>,
+) -> usize {
+    let mut count = 0;
+
+    let mut files: Vec<Encoder<io::BufWriter<fs::File>>> =
+        paths.iter().map(|path| create(path, options.compress)).collect();
+
     for word in words {
-        match file.write(format!("{}{}", word, sep).as_bytes()) {
-            Err(why) => panic!("couldn't write to {}: {}", display, why.to_string()),
-            Ok(_) => (),
-        };
+        for (file, path) in files.iter_mut().zip(paths) {
+            if let Err(why) = write_word(file, &word, options) {
+                panic!("couldn't write to {}: {}", path.display(), why);
+            }
+        }
+        count += 1;
     }
+
+    for (file, path) in files.into_iter().zip(paths) {
+        if let Err(why) = file.finish() {
+            panic!("couldn't write to {}: {}", path.display(), why);
+        }
+    }
+
+    count
 }
 
 #[cfg(test)]
@@ -107,22 +406,16 @@ mod tests {
         fn create_dir(path: &PathBuf) {
             let display = path.display();
 
-            match fs::create_dir(path) {
-                Err(why) => eprintln!(
-                    "couldn't create directory {}: {}",
-                    display,
-                    why.to_string()
-                ),
-                Ok(_) => (),
+            if let Err(why) = fs::create_dir(path) {
+                eprintln!("couldn't create directory {}: {}", display, why)
             }
         }
 
         fn create_file(path: &PathBuf) {
             let display = path.display();
 
-            match fs::File::create(path) {
-                Err(why) => eprintln!("couldn't create file {}: {}", display, why.to_string()),
-                Ok(_) => (),
+            if let Err(why) = fs::File::create(path) {
+                eprintln!("couldn't create file {}: {}", display, why)
             }
         }
         let paths = vec![
@@ -149,13 +442,8 @@ mod tests {
         let path = PathBuf::from(root);
         let display = path.display();
 
-        match fs::remove_dir_all(&path) {
-            Err(why) => panic!(
-                "failed to remove directory {}: {}",
-                display,
-                why.to_string()
-            ),
-            Ok(_) => (),
+        if let Err(why) = fs::remove_dir_all(&path) {
+            panic!("failed to remove directory {}: {}", display, why)
         }
     }
 
@@ -177,12 +465,12 @@ mod tests {
 
     #[test]
     fn test_filter_extensions() {
-        let files: Vec<PathBuf> = vec!["file.txt", "file.jpeg", "file.lst"]
+        let files: Vec<PathBuf> = vec!["file.txt", "file.jpeg", "file.lst", "file.lst.gz", "file.gz", "file.jpeg.zst"]
             .into_iter()
             .map(PathBuf::from)
             .collect();
         let exts = vec!["txt", "lst"];
-        let correct: Vec<PathBuf> = vec!["file.txt", "file.lst"]
+        let correct: Vec<PathBuf> = vec!["file.txt", "file.lst", "file.lst.gz"]
             .into_iter()
             .map(PathBuf::from)
             .collect();
@@ -216,24 +504,18 @@ mod tests {
         let display = path.display();
 
         let mut file = match fs::File::create(&path) {
-            Err(why) => panic!("couldn't create {}: {}", display, why.to_string()),
+            Err(why) => panic!("couldn't create {}: {}", display, why),
             Ok(file) => file,
         };
 
-        match file.write_all(content.as_bytes()) {
-            Err(why) => panic!("couldn't write to {}: {}", display, why.to_string()),
-            Ok(_) => (),
+        if let Err(why) = file.write_all(content.as_bytes()) {
+            panic!("couldn't write to {}: {}", display, why)
         }
 
-        let results = read_lines(&path);
+        let results = read_lines(&path, &ReadOptions::default());
 
-        match fs::remove_file(&path) {
-            Err(why) => eprintln!(
-                "couldn't remove testing file {}: {}",
-                display,
-                why.description()
-            ),
-            Ok(_) => (),
+        if let Err(why) = fs::remove_file(&path) {
+            eprintln!("couldn't remove testing file {}: {}", display, why)
         };
 
         assert_eq!(results, correct);
@@ -247,53 +529,269 @@ mod tests {
         let display = path.display();
 
         let mut file = match fs::File::create(&path) {
-            Err(why) => panic!("couldn't create {}: {}", display, why.description()),
+            Err(why) => panic!("couldn't create {}: {}", display, why),
             Ok(file) => file,
         };
 
-        match file.write_all(content.as_bytes()) {
-            Err(why) => panic!("couldn't write to {}: {}", display, why.description()),
-            Ok(_) => (),
+        if let Err(why) = file.write_all(content.as_bytes()) {
+            panic!("couldn't write to {}: {}", display, why)
         }
 
-        let results = read_lines(&path);
+        let results = read_lines(&path, &ReadOptions::default());
 
-        match fs::remove_file(&path) {
-            Err(why) => eprintln!(
-                "couldn't remove testing file {}: {}",
-                display,
-                why.description()
-            ),
-            Ok(_) => (),
+        if let Err(why) = fs::remove_file(&path) {
+            eprintln!("couldn't remove testing file {}: {}", display, why)
         };
 
         assert_eq!(results, correct);
     }
 
+    #[test]
+    fn test_stream_files() {
+        let paths: Vec<PathBuf> = vec!["test_stream_files_one.txt", "test_stream_files_two.txt"]
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+        fs::write(&paths[0], "Hello\n\nWorld\n").unwrap();
+        fs::write(&paths[1], "How\nAre\nYou?").unwrap();
+
+        let results: Vec<Word> = stream_files(paths.clone(), &ReadOptions::default()).collect();
+
+        for path in &paths {
+            if let Err(why) = fs::remove_file(path) {
+                eprintln!("couldn't remove testing file {}: {}", path.display(), why)
+            }
+        }
+
+        assert_eq!(results, vec!["Hello", "World", "How", "Are", "You?"]);
+    }
+
+    #[test]
+    fn test_stream_files_parallel() {
+        let paths: Vec<PathBuf> = (0..6)
+            .map(|i| PathBuf::from(format!("test_stream_files_parallel_{}.txt", i)))
+            .collect();
+        for (i, path) in paths.iter().enumerate() {
+            let content: String = (0..(i * 10_000))
+                .map(|j| format!("{}-{}\n", i, j))
+                .collect();
+            fs::write(path, content).unwrap();
+        }
+
+        let correct: Vec<Word> = stream_files(paths.clone(), &ReadOptions::default()).collect();
+        let ordered: Vec<Word> = stream_files_parallel(paths.clone(), 3, true, &ReadOptions::default()).collect();
+        let mut unordered: Vec<Word> = stream_files_parallel(paths.clone(), 3, false, &ReadOptions::default()).collect();
+        let in_memory = read_files_parallel(paths.clone(), &ReadOptions::default());
+
+        for path in &paths {
+            if let Err(why) = fs::remove_file(path) {
+                eprintln!("couldn't remove testing file {}: {}", path.display(), why)
+            }
+        }
+
+        assert_eq!(ordered, correct);
+        assert_eq!(in_memory, correct);
+        let mut sorted = correct.clone();
+        sorted.sort();
+        unordered.sort();
+        assert_eq!(unordered, sorted);
+    }
+
+    #[test]
+    fn test_write_stream() {
+        let correct = "Hello\nThere\nJorge\n";
+        let words = vec!["Hello", "There", "Jorge"]
+            .into_iter()
+            .map(Word::from);
+        let paths: Vec<PathBuf> = vec!["test_write_stream_one.txt", "test_write_stream_two.txt"]
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+
+        let count = write_stream(&paths, &WriteOptions::default(), words);
+
+        let results: Vec<String> = paths
+            .iter()
+            .map(|path| fs::read_to_string(path).unwrap())
+            .collect();
+        for path in &paths {
+            if let Err(why) = fs::remove_file(path) {
+                eprintln!("couldn't remove testing file {}: {}", path.display(), why)
+            }
+        }
+
+        assert_eq!(count, 3);
+        assert_eq!(results, vec![correct, correct]);
+    }
+
     #[test]
     fn test_write_words() {
         let correct = "Hello\nThere\nJorge\n";
-        let words: Vec<String> = vec!["Hello", "There", "Jorge"]
+        let words: Vec<Word> = vec!["Hello", "There", "Jorge"]
             .into_iter()
-            .map(String::from)
+            .map(Word::from)
             .collect();
         let path = PathBuf::from("test_write_words.txt");
         let display = path.display();
 
-        write_words(&path, &words);
+        write_words(&path, &WriteOptions::default(), &words);
 
-        let result =
-            fs::read_to_string(&path).expect(&format!("couldn't read output file {}", display));
+        let result = fs::read_to_string(&path)
+            .unwrap_or_else(|_| panic!("couldn't read output file {}", display));
 
-        match fs::remove_file(&path) {
-            Err(why) => eprintln!(
-                "couldn't remove testing file {}: {}",
-                display,
-                why.description()
-            ),
-            Ok(_) => (),
+        if let Err(why) = fs::remove_file(&path) {
+            eprintln!("couldn't remove testing file {}: {}", display, why)
         };
 
         assert_eq!(result, correct);
     }
+
+    #[test]
+    fn test_compressed_round_trip() {
+        let words = ["Hello", "There", "Jorge"];
+        // The zst file is written as gzip, so it can only be read back by its magic bytes.
+        let paths: Vec<PathBuf> = vec!["test_compressed_round_trip.lst.gz", "test_compressed_round_trip.lst.zst"]
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+
+        write_stream(&paths[..1], &WriteOptions::default(), words.iter().map(|w| Word::from(*w)));
+        let gzip = WriteOptions {
+            compress: Some(Compression::parse("gzip:9").unwrap()),
+            ..WriteOptions::default()
+        };
+        write_stream(&paths[1..], &gzip, words.iter().map(|w| Word::from(*w)));
+
+        let magic: Vec<Vec<u8>> = paths.iter().map(|path| fs::read(path).unwrap()[..2].to_vec()).collect();
+        let results: Vec<Word> = stream_files(paths.clone(), &ReadOptions::default()).collect();
+        let kept = filter_extensions(paths.clone(), vec!["lst"]);
+
+        for path in &paths {
+            if let Err(why) = fs::remove_file(path) {
+                eprintln!("couldn't remove testing file {}: {}", path.display(), why)
+            }
+        }
+
+        assert_eq!(magic, vec![vec![0x1f, 0x8b], vec![0x1f, 0x8b]]);
+        assert_eq!(results, vec!["Hello", "There", "Jorge", "Hello", "There", "Jorge"]);
+        assert_eq!(kept, paths);
+    }
+
+    #[test]
+    fn test_non_utf8_round_trip() {
+        let content: &[u8] = b"Passw\xf6rd\r\n\xff\xfe\x00binary\nHello\n";
+        let path = PathBuf::from("test_non_utf8_round_trip.txt");
+        let output = PathBuf::from("test_non_utf8_round_trip_output.txt");
+        fs::write(&path, content).unwrap();
+
+        let words = read_lines(&path, &ReadOptions::default());
+        write_words(&output, &WriteOptions::default(), &words);
+        let result = fs::read(&output).unwrap();
+
+        for path in &[path, output] {
+            if let Err(why) = fs::remove_file(path) {
+                eprintln!("couldn't remove testing file {}: {}", path.display(), why)
+            }
+        }
+
+        assert_eq!(words.len(), 3);
+        assert_eq!(words[0], Word::new(b"Passw\xf6rd".to_vec()));
+        assert_eq!(result, b"Passw\xf6rd\n\xff\xfe\x00binary\nHello\n".to_vec());
+    }
+
+    #[test]
+    fn test_hex() {
+        let words = vec![
+            Word::from("Hello"),
+            Word::new(b"ab\xc3".to_vec()),
+            Word::from("new\nline"),
+            Word::from("$HEX[41]"),
+            Word::from("caf\u{e9}"),
+        ];
+        let encoded: Vec<Word> = words.iter().cloned().map(encode_hex).collect();
+
+        assert_eq!(
+            encoded,
+            vec!["Hello", "$HEX[6162c3]", "$HEX[6e65770a6c696e65]", "$HEX[244845585b34315d]", "caf\u{e9}"]
+        );
+        assert_eq!(encoded.into_iter().map(decode_hex).collect::<Vec<Word>>(), words);
+        assert_eq!(decode_hex(Word::from("$HEX[4G]")), "$HEX[4G]");
+        assert_eq!(decode_hex(Word::from("$HEX[414]")), "$HEX[414]");
+        assert_eq!(decode_hex(Word::from("$HEX[]")), "");
+    }
+
+    #[test]
+    fn test_hex_round_trip() {
+        let words = vec![Word::from("Hello"), Word::new(b"ab\xc3".to_vec()), Word::from("new\nline")];
+        let path = PathBuf::from("test_hex_round_trip.txt");
+        let hex = WriteOptions {
+            hex: true,
+            ..WriteOptions::default()
+        };
+
+        write_words(&path, &hex, &words);
+        let written = fs::read_to_string(&path).unwrap();
+        let read = read_lines(
+            &path,
+            &ReadOptions {
+                hex: true,
+                ..ReadOptions::default()
+            },
+        );
+
+        if let Err(why) = fs::remove_file(&path) {
+            eprintln!("couldn't remove testing file {}: {}", path.display(), why)
+        }
+
+        assert_eq!(written, "Hello\n$HEX[6162c3]\n$HEX[6e65770a6c696e65]\n");
+        assert_eq!(read, words);
+    }
+
+    #[test]
+    fn test_read_encodings() {
+        let files: [(&str, &[u8]); 3] = [
+            ("test_read_encodings_bom.txt", b"\xef\xbb\xbfHello\nWorld\n"),
+            ("test_read_encodings_utf16.txt", b"\xff\xfeH\x00i\x00\r\x00\n\x00\xe9\x00\n\x00"),
+            ("test_read_encodings_latin1.txt", b"Passw\xf6rd\n"),
+        ];
+        let paths: Vec<PathBuf> = files.iter().map(|(name, _)| PathBuf::from(name)).collect();
+        for (path, (_, content)) in paths.iter().zip(files.iter()) {
+            fs::write(path, content).unwrap();
+        }
+
+        let options = ReadOptions::default();
+        let detected: Vec<Word> = stream_files(paths.clone(), &options).collect();
+        let given: Vec<Word> = stream_files(
+            paths[2..].to_vec(),
+            &ReadOptions {
+                encoding: Some(Encoding::Utf8),
+                ..ReadOptions::default()
+            },
+        )
+        .collect();
+
+        for path in &paths {
+            if let Err(why) = fs::remove_file(path) {
+                eprintln!("couldn't remove testing file {}: {}", path.display(), why)
+            }
+        }
+
+        assert_eq!(detected, vec!["Hello", "World", "Hi", "\u{e9}", "Passw\u{f6}rd"]);
+        assert_eq!(given, vec![Word::new(b"Passw\xf6rd".to_vec())]);
+
+        let report: Vec<String> = options
+            .report
+            .files()
+            .iter()
+            .map(|(path, conversion)| format!("{}: {}", path.display(), conversion))
+            .collect();
+        assert_eq!(
+            report,
+            vec![
+                "test_read_encodings_bom.txt: removed the UTF-8 byte order mark",
+                "test_read_encodings_latin1.txt: Latin-1, detected",
+                "test_read_encodings_utf16.txt: UTF-16LE with a byte order mark",
+            ]
+        );
+    }
 }
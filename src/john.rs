@@ -0,0 +1,375 @@
+use std::path::Path;
+use std::str;
+use std::string::String;
+use std::vec::Vec;
+
+/// The most rules the preprocessor may make from one line.
+const MAX_EXPANSION: usize = 1 << 20;
+
+/// The start of the sections of a john.conf style file that hold rules.
+const RULES_SECTION: &str = "list.rules:";
+
+/// A line of a rule file: its line number and the rules the preprocessor made from it.
+pub type Line = (usize, Vec<Vec<u8>>);
+
+/// One piece of a line before the preprocessor expands it: a byte, or one of the line's ranges by index.
+enum Piece {
+    Literal(u8),
+    Range(usize),
+}
+
+/// A range of characters like [0-9]. Each rule made from the line uses one of them.
+struct Range {
+    chars: Vec<u8>,
+    /// The range this one moves in step with, for ranges written as \p[...].
+    parallel: Option<usize>,
+}
+
+/// Reads the byte after a backslash, which is either written in hex like \x41 or is the byte itself.
+fn escaped(line: &[u8], i: &mut usize) -> Result<u8, String> {
+    let c = match line.get(*i) {
+        None => return Err(String::from("the line ends with a \\")),
+        Some(c) => *c,
+    };
+    *i += 1;
+
+    let hex = line
+        .get(*i..*i + 2)
+        .and_then(|digits| str::from_utf8(digits).ok())
+        .and_then(|digits| u8::from_str_radix(digits, 16).ok());
+    match (c, hex) {
+        (b'x', Some(byte)) => {
+            *i += 2;
+            Ok(byte)
+        }
+        _ => Ok(c),
+    }
+}
+
+/// Reads the characters of a range like [0-9a-f], starting just after its [ and ending just after its ].
+fn range(line: &[u8], i: &mut usize) -> Result<Vec<u8>, String> {
+    let mut chars = vec![];
+
+    loop {
+        let c = match line.get(*i) {
+            None => return Err(String::from("unclosed range")),
+            Some(b']') => {
+                *i += 1;
+                break;
+            }
+            Some(b'\\') => {
+                *i += 1;
+                escaped(line, i)?
+            }
+            Some(c) => {
+                *i += 1;
+                *c
+            }
+        };
+
+        let is_span = line.get(*i) == Some(&b'-') && !matches!(line.get(*i + 1), None | Some(b']'));
+        if !is_span {
+            chars.push(c);
+            continue;
+        }
+
+        *i += 1;
+        let end = match line[*i] {
+            b'\\' => {
+                *i += 1;
+                escaped(line, i)?
+            }
+            end => {
+                *i += 1;
+                end
+            }
+        };
+        if end < c {
+            return Err(format!("range {}-{} goes backwards", c as char, end as char));
+        }
+        chars.extend(c..=end);
+    }
+
+    if chars.is_empty() {
+        return Err(String::from("empty range"));
+    }
+
+    Ok(chars)
+}
+
+/// Runs a line through John's rule preprocessor, returning every rule it stands for.
+/// A range like [0-9] makes a rule for each of its characters, and a line with several ranges makes a rule for
+/// every combination of them, the last range changing fastest. A range written as \p[...] moves in step with
+/// the range before it, and \pN[...] with the Nth range of the line. A backslash makes the next byte literal,
+/// so \[ is the command that deletes the first character, and \xNN is a byte written in hex.
+///
+/// # Arguments
+///
+/// * `line` - The line to expand.
+///
+/// # Example
+///
+/// ```
+/// let rules = expand(b"$[0-2]\\p[a-c]").unwrap();
+///
+/// assert_eq!(rules, vec![b"$0a".to_vec(), b"$1b".to_vec(), b"$2c".to_vec()]);
+/// ```
+pub fn expand(line: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+    let mut pieces = vec![];
+    let mut ranges: Vec<Range> = vec![];
+    let mut i = 0;
+
+    while i < line.len() {
+        match line[i] {
+            b'\\' if line.get(i + 1) == Some(&b'p') => {
+                i += 2;
+                let parallel = match line.get(i) {
+                    Some(n @ b'1'..=b'9') => {
+                        i += 1;
+                        (n - b'1') as usize
+                    }
+                    _ => ranges.len().wrapping_sub(1),
+                };
+                if line.get(i) != Some(&b'[') {
+                    return Err(String::from("\\p has to be followed by a range"));
+                }
+                if parallel >= ranges.len() {
+                    return Err(String::from("\\p refers to a range that doesn't come before it"));
+                }
+
+                i += 1;
+                let chars = range(line, &mut i)?;
+                pieces.push(Piece::Range(ranges.len()));
+                ranges.push(Range {
+                    chars,
+                    parallel: Some(parallel),
+                });
+            }
+            b'\\' => {
+                i += 1;
+                pieces.push(Piece::Literal(escaped(line, &mut i)?));
+            }
+            b'[' => {
+                i += 1;
+                let chars = range(line, &mut i)?;
+                pieces.push(Piece::Range(ranges.len()));
+                ranges.push(Range {
+                    chars,
+                    parallel: None,
+                });
+            }
+            c => {
+                pieces.push(Piece::Literal(c));
+                i += 1;
+            }
+        }
+    }
+
+    // Ranges that don't move in step with another are counted through like the digits of a number.
+    let free: Vec<usize> = (0..ranges.len())
+        .filter(|r| ranges[*r].parallel.is_none())
+        .collect();
+    let total = free
+        .iter()
+        .try_fold(1usize, |total, r| total.checked_mul(ranges[*r].chars.len()))
+        .filter(|total| *total <= MAX_EXPANSION)
+        .ok_or_else(|| format!("the line makes more than {} rules", MAX_EXPANSION))?;
+
+    let mut rules = Vec::with_capacity(total);
+    let mut choices = vec![0; ranges.len()];
+    for combination in 0..total {
+        let mut rest = combination;
+        for r in free.iter().rev() {
+            let length = ranges[*r].chars.len();
+            choices[*r] = rest % length;
+            rest /= length;
+        }
+        for r in 0..ranges.len() {
+            if let Some(base) = ranges[r].parallel {
+                choices[r] = choices[base] % ranges[r].chars.len();
+            }
+        }
+
+        let rule = pieces
+            .iter()
+            .map(|piece| match piece {
+                Piece::Literal(c) => *c,
+                Piece::Range(r) => ranges[*r].chars[choices[*r]],
+            })
+            .collect();
+        rules.push(rule);
+    }
+
+    Ok(rules)
+}
+
+/// Removes the flags from the start of a rule, returning None if they say the rule shouldn't run.
+/// John's flags are about the hashes being cracked. Lists are taken to be for case-sensitive, 8-bit hashes of
+/// any length that weren't split and aren't cracked in word pairs, so -c, -8, ->N and -<N rules run, and -s and
+/// -p rules don't. -u and -U go by John's internal codepage, which isn't used here.
+fn strip_flags(rule: &[u8]) -> Result<Option<&[u8]>, String> {
+    let mut rest = rule.trim_ascii_start();
+
+    while let [b'-', flag, after @ ..] = rest {
+        rest = match (flag, after) {
+            (b':' | b'c' | b'8' | b'U', _) => after,
+            (b's' | b'p' | b'u', _) => return Ok(None),
+            (b'>' | b'<', [_, after @ ..]) => after,
+            (b'>' | b'<', []) => return Err(format!("flag -{} needs a length", *flag as char)),
+            _ => return Err(format!("unknown flag -{}", *flag as char)),
+        }
+        .trim_ascii_start();
+    }
+
+    Ok(Some(rest))
+}
+
+/// Returns the name of the section a line starts, like List.Rules:Wordlist for [List.Rules:Wordlist].
+/// Lines of rules can start with [ too, so only names with a . or : in them, or Options, are sections.
+fn section_name(line: &[u8]) -> Option<&str> {
+    let name = line.strip_prefix(b"[")?.strip_suffix(b"]")?;
+    let name = str::from_utf8(name).ok()?;
+
+    let is_name = name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "._:-".contains(c))
+        && (name.contains(['.', ':']) || name == "Options");
+    Some(name).filter(|_| is_name)
+}
+
+/// Reads the rules of a John rule file or john.conf style file, returning the line number of each line along
+/// with the rules the preprocessor made from it, see expand. Rules whose flags say they shouldn't run are left
+/// out. Empty lines, comments starting with # or ; and .include directives are skipped.
+///
+/// # Arguments
+///
+/// * `path` - The file the contents came from, for error messages.
+/// * `contents` - The contents of the file.
+/// * `section` - The name of the [List.Rules:...] section to read, like Wordlist. When this is None, every line
+///   outside of a section is read, along with every rules section.
+pub fn rules(
+    path: &Path,
+    contents: &[u8],
+    section: Option<&str>,
+) -> Result<Vec<Line>, String> {
+    let wanted_section = section.map(|s| format!("{}{}", RULES_SECTION, s.to_ascii_lowercase()));
+    let mut current: Option<String> = None;
+    let mut found = false;
+    let mut lines = vec![];
+
+    for (i, line) in contents.split(|b| *b == b'\n').enumerate() {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let trimmed = line.trim_ascii();
+        if trimmed.is_empty() || trimmed[0] == b'#' || trimmed[0] == b';' || trimmed.starts_with(b".include") {
+            continue;
+        }
+
+        if let Some(name) = section_name(trimmed) {
+            let name = name.to_ascii_lowercase();
+            found |= Some(&name) == wanted_section.as_ref();
+            current = Some(name);
+            continue;
+        }
+
+        let wanted = match (&wanted_section, &current) {
+            (Some(wanted), Some(current)) => wanted == current,
+            (Some(_), None) => false,
+            (None, Some(current)) => current.starts_with(RULES_SECTION),
+            (None, None) => true,
+        };
+        if !wanted {
+            continue;
+        }
+
+        let error = |why: String| format!("{} line {}: {}", path.display(), i + 1, why);
+        let mut kept = vec![];
+        for rule in expand(line).map_err(error)? {
+            if let Some(rule) = strip_flags(&rule).map_err(error)? {
+                kept.push(rule.to_vec());
+            }
+        }
+        lines.push((i + 1, kept));
+    }
+
+    match section {
+        Some(section) if !found => Err(format!(
+            "{} has no [List.Rules:{}] section",
+            path.display(),
+            section
+        )),
+        _ => Ok(lines),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_text(line: &str) -> Vec<String> {
+        expand(line.as_bytes())
+            .unwrap()
+            .into_iter()
+            .map(|rule| String::from_utf8(rule).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(expand_text("$[0-2]"), vec!["$0", "$1", "$2"]);
+        assert_eq!(expand_text("^[ab]$[12]"), vec!["^a$1", "^a$2", "^b$1", "^b$2"]);
+        assert_eq!(expand_text("s[ab]\\p[AB]"), vec!["saA", "sbB"]);
+        assert_eq!(expand_text("$[0-1]$[x-y]\\p1[AB]"), vec!["$0$xA", "$0$yA", "$1$xB", "$1$yB"]);
+        assert_eq!(expand_text("\\[$\\x41"), vec!["[$A"]);
+        assert_eq!(expand_text("$[\\]-]"), vec!["$]", "$-"]);
+        assert_eq!(expand_text("c"), vec!["c"]);
+
+        assert_eq!(expand(b"$[0-9").unwrap_err(), "unclosed range");
+        assert_eq!(expand(b"$[9-0]").unwrap_err(), "range 9-0 goes backwards");
+        assert_eq!(
+            expand(b"\\p[ab]").unwrap_err(),
+            "\\p refers to a range that doesn't come before it"
+        );
+    }
+
+    #[test]
+    fn test_strip_flags() {
+        assert_eq!(strip_flags(b"-c -8 c"), Ok(Some(&b"c"[..])));
+        assert_eq!(strip_flags(b"-c-8c"), Ok(Some(&b"c"[..])));
+        assert_eq!(strip_flags(b"->8 $-"), Ok(Some(&b"$-"[..])));
+        assert_eq!(strip_flags(b"-s c"), Ok(None));
+        assert_eq!(strip_flags(b"-x c"), Err(String::from("unknown flag -x")));
+    }
+
+    #[test]
+    fn test_rules() {
+        let contents = b"\
+# A comment
+[Options]
+Wordlist = x.lst
+
+[List.Rules:Append]
+-[:c] $[0-1]
+-s c
+
+[List.Rules:Other]
+u
+";
+        let path = Path::new("john.conf");
+
+        let append = rules(path, contents, Some("append")).unwrap();
+        let every = rules(path, contents, None).unwrap();
+
+        assert_eq!(
+            append,
+            vec![
+                (6, vec![b"$0".to_vec(), b"$1".to_vec(), b"$0".to_vec(), b"$1".to_vec()]),
+                (7, vec![])
+            ]
+        );
+        assert_eq!(every.len(), 3);
+        assert_eq!(every[2], (10, vec![b"u".to_vec()]));
+        assert_eq!(
+            rules(path, contents, Some("Missing")).unwrap_err(),
+            "john.conf has no [List.Rules:Missing] section"
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::string::String;
+use std::vec::Vec;
+
+use crate::word::Word;
+
+/// The most variants made from a word when no cap is given, so words with many characters to substitute can't
+/// make more than fit in memory.
+const DEFAULT_MAX_VARIANTS: usize = 1024;
+
+/// Which variants of a word are made from the substitutions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeetMode {
+    /// Every combination of substitutions, from none to every character that can be substituted.
+    All,
+    /// The word with one character substituted at a time.
+    Single,
+}
+
+impl LeetMode {
+    /// Returns the mode with the given name, all or single.
+    pub fn from_name(name: &str) -> Option<LeetMode> {
+        match name {
+            "all" => Some(LeetMode::All),
+            "single" => Some(LeetMode::Single),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LeetMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LeetMode::All => write!(f, "all"),
+            LeetMode::Single => write!(f, "single"),
+        }
+    }
+}
+
+/// A table of leetspeak substitutions, like a to 4 or @, and the variants of each word it makes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Leetspeak {
+    /// The file the table was read from.
+    pub path: PathBuf,
+    pub mode: LeetMode,
+    /// The most variants made from each word, counting the word itself, or None for DEFAULT_MAX_VARIANTS.
+    pub max_variants: Option<usize>,
+    /// Each character that can be substituted, and what it can be substituted with.
+    table: Vec<(Vec<u8>, Vec<Vec<u8>>)>,
+}
+
+impl Leetspeak {
+    /// Reads a substitution table, one character per line followed by what it can be substituted with, separated by
+    /// whitespace, like `a 4 @`. Substitutions can be more than one character, like `m /\/\`. Empty lines and lines
+    /// starting with # are skipped. Letters are substituted whatever their case.
+    /// Returns a human readable message if the file can't be read or has a line that doesn't fit.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The table to read.
+    /// * `mode` - Which variants of each word are made.
+    /// * `max_variants` - The most variants made from each word, or None for DEFAULT_MAX_VARIANTS.
+    pub fn load(path: &Path, mode: LeetMode, max_variants: Option<usize>) -> Result<Leetspeak, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|why| format!("couldn't read {}: {}", path.display(), why))?;
+
+        let mut table: Vec<(Vec<u8>, Vec<Vec<u8>>)> = vec![];
+        for (i, line) in contents.lines().enumerate() {
+            let mut fields = line.split_whitespace();
+            let key = match fields.next() {
+                None => continue,
+                Some(key) if key.starts_with('#') => continue,
+                Some(key) => key,
+            };
+            if key.chars().count() != 1 {
+                return Err(format!(
+                    "{} line {}: \"{}\" should be a single character",
+                    path.display(),
+                    i + 1,
+                    key
+                ));
+            }
+
+            let key = key.as_bytes().to_vec();
+            let substitutes: Vec<Vec<u8>> = fields
+                .map(|substitute| substitute.as_bytes().to_vec())
+                .filter(|substitute| !substitute.eq_ignore_ascii_case(&key))
+                .collect();
+            if substitutes.is_empty() {
+                return Err(format!(
+                    "{} line {}: {} has nothing to be substituted with",
+                    path.display(),
+                    i + 1,
+                    String::from_utf8_lossy(&key)
+                ));
+            }
+
+            let index = match table.iter().position(|(k, _)| k.eq_ignore_ascii_case(&key)) {
+                Some(index) => index,
+                None => {
+                    table.push((key, vec![]));
+                    table.len() - 1
+                }
+            };
+            for substitute in substitutes {
+                if !table[index].1.contains(&substitute) {
+                    table[index].1.push(substitute);
+                }
+            }
+        }
+
+        Ok(Leetspeak {
+            path: path.to_path_buf(),
+            mode,
+            max_variants,
+            table,
+        })
+    }
+
+    /// The most words each word can be turned into.
+    pub fn expansion(&self) -> usize {
+        self.max_variants.unwrap_or(DEFAULT_MAX_VARIANTS)
+    }
+
+    /// Splits a word into parts, each with the bytes it can be. Characters in the table can be themselves or any of
+    /// their substitutions, and everything else can only be itself.
+    fn parts<'a>(&'a self, word: &'a [u8]) -> Vec<Vec<&'a [u8]>> {
+        let mut parts = vec![];
+        let mut literal = 0;
+        let mut i = 0;
+
+        while i < word.len() {
+            let entry = self.table.iter().find(|(key, _)| {
+                word.len() - i >= key.len() && word[i..i + key.len()].eq_ignore_ascii_case(key)
+            });
+
+            match entry {
+                None => i += 1,
+                Some((key, substitutes)) => {
+                    if literal < i {
+                        parts.push(vec![&word[literal..i]]);
+                    }
+                    let mut choices = vec![&word[i..i + key.len()]];
+                    choices.extend(substitutes.iter().map(Vec::as_slice));
+                    parts.push(choices);
+                    i += key.len();
+                    literal = i;
+                }
+            }
+        }
+        if literal < word.len() {
+            parts.push(vec![&word[literal..]]);
+        }
+
+        parts
+    }
+
+    /// Returns the variants of a word, starting with the word itself. In all mode, the last substitutable character
+    /// changes fastest, and in single mode the characters are substituted from first to last.
+    ///
+    /// # Arguments
+    ///
+    /// * `word` - The word to make variants of.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// // Using a table with the lines 'a 4 @' and 'e 3'.
+    /// let leetspeak = Leetspeak::load(Path::new("leet.txt"), LeetMode::Single, None).unwrap();
+    ///
+    /// assert!(leetspeak.apply(&Word::from("tea")) == vec!["tea", "t3a", "te4", "te@"]);
+    /// ```
+    pub fn apply(&self, word: &Word) -> Vec<Word> {
+        let parts = self.parts(word.as_bytes());
+        let max = self.expansion();
+        let join = |indexes: &[usize]| -> Word {
+            Word::new(parts.iter().zip(indexes).flat_map(|(part, i)| part[*i].iter().cloned()).collect())
+        };
+
+        let mut made = vec![join(&vec![0; parts.len()])];
+        match self.mode {
+            LeetMode::All => {
+                let mut indexes = vec![0; parts.len()];
+                'variants: while made.len() < max {
+                    let mut i = parts.len();
+                    loop {
+                        if i == 0 {
+                            break 'variants;
+                        }
+                        i -= 1;
+                        indexes[i] += 1;
+                        if indexes[i] < parts[i].len() {
+                            break;
+                        }
+                        indexes[i] = 0;
+                    }
+                    made.push(join(&indexes));
+                }
+            }
+            LeetMode::Single => {
+                'parts: for (i, part) in parts.iter().enumerate() {
+                    for choice in 1..part.len() {
+                        if made.len() >= max {
+                            break 'parts;
+                        }
+                        let mut indexes = vec![0; parts.len()];
+                        indexes[i] = choice;
+                        made.push(join(&indexes));
+                    }
+                }
+            }
+        }
+
+        made
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_and_apply() {
+        let path = PathBuf::from("test_leet_load.txt");
+        fs::write(&path, "# vowels\na 4 @\n\ne 3\r\nA /-\\ 4\nm /\\/\\\n").unwrap();
+        let bad_path = PathBuf::from("test_leet_load_bad.txt");
+        fs::write(&bad_path, "a 4\nss 5\n").unwrap();
+        let empty_path = PathBuf::from("test_leet_load_empty.txt");
+        fs::write(&empty_path, "o\n").unwrap();
+
+        let all = Leetspeak::load(&path, LeetMode::All, None);
+        let capped = Leetspeak::load(&path, LeetMode::All, Some(3));
+        let single = Leetspeak::load(&path, LeetMode::Single, None);
+        let bad = Leetspeak::load(&bad_path, LeetMode::All, None);
+        let empty = Leetspeak::load(&empty_path, LeetMode::All, None);
+
+        for path in &[path, bad_path, empty_path] {
+            if let Err(why) = fs::remove_file(path) {
+                eprintln!("couldn't remove testing file {}: {}", path.display(), why)
+            }
+        }
+
+        let all = all.unwrap();
+        assert_eq!(
+            all.apply(&Word::from("Tea")),
+            vec!["Tea", "Te4", "Te@", "Te/-\\", "T3a", "T34", "T3@", "T3/-\\"]
+        );
+        assert_eq!(all.apply(&Word::from("mom")), vec!["mom", "mo/\\/\\", "/\\/\\om", "/\\/\\o/\\/\\"]);
+        assert_eq!(all.apply(&Word::from("xyz")), vec!["xyz"]);
+        assert_eq!(all.apply(&Word::new(b"\xffA".to_vec())).len(), 4);
+        assert_eq!(all.expansion(), DEFAULT_MAX_VARIANTS);
+        assert_eq!(all.apply(&Word::from("a".repeat(40).as_str())).len(), DEFAULT_MAX_VARIANTS);
+
+        let capped = capped.unwrap();
+        assert_eq!(capped.apply(&Word::from("Tea")), vec!["Tea", "Te4", "Te@"]);
+        assert_eq!(capped.expansion(), 3);
+
+        assert_eq!(
+            single.unwrap().apply(&Word::from("TEA")),
+            vec!["TEA", "T3A", "TE4", "TE@", "TE/-\\"]
+        );
+        assert_eq!(bad.unwrap_err(), "test_leet_load_bad.txt line 2: \"ss\" should be a single character");
+        assert_eq!(empty.unwrap_err(), "test_leet_load_empty.txt line 1: o has nothing to be substituted with");
+    }
+}
@@ -1,18 +1,48 @@
 extern crate clap;
 
+mod classify;
+mod combinator;
+mod combo;
+mod compression;
+mod config;
+mod encoding;
+mod external;
 mod file_system;
+mod john;
+mod leet;
+mod mask;
+mod parallel;
+mod patterns;
+mod pipeline;
 mod processors;
+mod rules;
+mod stats;
+mod template;
+mod word;
 
 use std::io;
 use std::io::Write;
-use clap::{App, Arg};
-use std::path::PathBuf;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use std::path::{Path, PathBuf};
 use std::process::exit;
+use std::sync::mpsc;
+use std::thread;
 use std::time::Instant;
 
-fn main() {
-    let extensions: Vec<&str> = vec!["txt", "lst"];
+/// The processing that was hard-coded here before pipelines could be given on the command line.
+const DEFAULT_PIPELINE: &str =
+    "trim_whitespaces remove_counts deduplicate remove_outside_lengths(0, 50) prefix(\"root \")";
+
+/// The config file profiles are read from when --config isn't given.
+const DEFAULT_CONFIG: &str = "word_processor.toml";
+
+/// The most words a mask may make when --max-keyspace isn't given.
+const DEFAULT_MAX_KEYSPACE: u64 = 10_000_000_000;
 
+/// How many users can wait for the users pipeline while splitting combo lists.
+const USER_QUEUE: usize = 4096;
+
+fn main() {
     let matches = App::new("Word Processor")
         .version("1.0")
         .author("Terrence Plunkett <eightys3v3n@gmail.com>")
@@ -22,6 +52,7 @@ fn main() {
                 .short("s")
                 .long("source-path")
                 .takes_value(true)
+                .global(true)
                 .help("Path to scan for new word/password list files"),
         )
         .arg(
@@ -29,54 +60,904 @@ fn main() {
                 .short("o")
                 .long("output-path")
                 .takes_value(true)
+                .global(true)
                 .help("Path to output the processed word/password list"),
         )
+        .arg(
+            Arg::with_name("pipeline")
+                .short("p")
+                .long("pipeline")
+                .takes_value(true)
+                .global(true)
+                .help("Processors to run, in order. For example 'tcd l0:50' or 'trim_whitespaces deduplicate'"),
+        )
+        .arg(
+            Arg::with_name("config")
+                .short("c")
+                .long("config")
+                .takes_value(true)
+                .global(true)
+                .help("Config file to read profiles from (default word_processor.toml)"),
+        )
+        .arg(
+            Arg::with_name("profile")
+                .long("profile")
+                .takes_value(true)
+                .global(true)
+                .help("Name of the profile in the config file to run. Other options override its settings"),
+        )
+        .arg(
+            Arg::with_name("compress")
+                .long("compress")
+                .takes_value(true)
+                .global(true)
+                .help("Compress outputs with none, gzip, bzip2, xz or zstd, optionally with a level like zstd:19 (default is to go by each output's extension)"),
+        )
+        .arg(
+            Arg::with_name("hex")
+                .long("hex")
+                .global(true)
+                .help("Decode lines written as $HEX[...] when reading, and write words that aren't printable UTF-8 or contain a newline that way"),
+        )
+        .arg(
+            Arg::with_name("encoding")
+                .long("encoding")
+                .takes_value(true)
+                .global(true)
+                .help("Read every file as utf-8, latin-1, windows-1252, utf-16le or utf-16be (default is to work it out for each file)"),
+        )
+        .arg(
+            Arg::with_name("keep_counts")
+                .long("keep-counts")
+                .takes_value(true)
+                .possible_values(&["count-word", "word-tab-count"])
+                .global(true)
+                .help("Read counts like '1230 World' along with words, add them up when deduplicating, and write them out in this format"),
+        )
+        .arg(
+            Arg::with_name("in_memory")
+                .long("in-memory")
+                .global(true)
+                .help("Hold every word in memory and run one stage at a time, showing how long each stage takes"),
+        )
+        .arg(
+            Arg::with_name("memory_limit")
+                .long("memory-limit")
+                .takes_value(true)
+                .global(true)
+                .help("Roughly how much memory deduplicate may use before spilling to disk, like 512M or 4G"),
+        )
+        .arg(
+            Arg::with_name("temp_dir")
+                .long("temp-dir")
+                .takes_value(true)
+                .global(true)
+                .help("Directory to spill temporary files to (default is the system's temporary directory)"),
+        )
+        .arg(
+            Arg::with_name("threads")
+                .short("j")
+                .long("threads")
+                .takes_value(true)
+                .global(true)
+                .help("How many threads to read and process words on (default is one per core)"),
+        )
+        .arg(
+            Arg::with_name("deterministic")
+                .long("deterministic")
+                .global(true)
+                .help("Keep words in the same order as a single threaded run, so output is identical between runs"),
+        )
+        .subcommand(
+            SubCommand::with_name("generate")
+                .about("Generates words and runs them through the pipeline")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("mask")
+                        .about("Generates every word a hashcat mask like ?u?l?l?l?d?d?s makes, after the words of any lists given with -s")
+                        .arg(
+                            Arg::with_name("mask")
+                                .required(true)
+                                .index(1)
+                                .help("The mask, using ?l ?u ?d ?h ?H ?s ?a ?b, the custom charsets ?1 to ?4, ?? for a ? and anything else as itself"),
+                        )
+                        .arg(
+                            Arg::with_name("charset1")
+                                .short("1")
+                                .long("custom-charset1")
+                                .takes_value(true)
+                                .help("Characters ?1 can be, like ?l?d or abc"),
+                        )
+                        .arg(
+                            Arg::with_name("charset2")
+                                .short("2")
+                                .long("custom-charset2")
+                                .takes_value(true)
+                                .help("Characters ?2 can be"),
+                        )
+                        .arg(
+                            Arg::with_name("charset3")
+                                .short("3")
+                                .long("custom-charset3")
+                                .takes_value(true)
+                                .help("Characters ?3 can be"),
+                        )
+                        .arg(
+                            Arg::with_name("charset4")
+                                .short("4")
+                                .long("custom-charset4")
+                                .takes_value(true)
+                                .help("Characters ?4 can be"),
+                        )
+                        .arg(
+                            Arg::with_name("increment")
+                                .short("i")
+                                .long("increment")
+                                .takes_value(true)
+                                .min_values(0)
+                                .require_equals(true)
+                                .help("Also generate the shorter lengths of the mask, optionally only from MIN to MAX like --increment=4:8"),
+                        )
+                        .arg(
+                            Arg::with_name("keyspace")
+                                .long("keyspace")
+                                .help("Print how many words the mask makes and exit"),
+                        )
+                        .arg(
+                            Arg::with_name("max_keyspace")
+                                .long("max-keyspace")
+                                .takes_value(true)
+                                .help("Refuse masks that make more words than this, like 1e9 (default 1e10)"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("combine")
+                        .about("Joins every word of the first list with every word of the next, and so on for each list")
+                        .arg(
+                            Arg::with_name("lists")
+                                .required(true)
+                                .index(1)
+                                .multiple(true)
+                                .min_values(2)
+                                .help("The lists to combine from left to right, each a file or directory"),
+                        )
+                        .arg(
+                            Arg::with_name("side")
+                                .long("side")
+                                .takes_value(true)
+                                .multiple(true)
+                                .number_of_values(1)
+                                .help("A pipeline for one of the lists before they're combined, like --side 2='t d' for the second"),
+                        )
+                        .arg(
+                            Arg::with_name("separator")
+                                .long("separator")
+                                .takes_value(true)
+                                .multiple(true)
+                                .number_of_values(1)
+                                .allow_hyphen_values(true)
+                                .help("What goes between the words, given once for every join or once for each join in order (default nothing)"),
+                        )
+                        .arg(
+                            Arg::with_name("min_length")
+                                .long("min-length")
+                                .takes_value(true)
+                                .help("Skip combinations shorter than this many bytes"),
+                        )
+                        .arg(
+                            Arg::with_name("max_length")
+                                .long("max-length")
+                                .takes_value(true)
+                                .help("Skip combinations longer than this many bytes"),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("stats")
+                .about("Reports the lengths, charsets, masks, prefixes and suffixes of the words, like PACK's statsgen")
+                .arg(
+                    Arg::with_name("top")
+                        .long("top")
+                        .takes_value(true)
+                        .help("How many of the most common masks, prefixes and suffixes to list (default 10)"),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["text", "json"])
+                        .help("Print the report as text to read or as JSON (default text)"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("split")
+                .about("Splits combo lists like user:password, sending passwords through the pipeline and users through their own")
+                .arg(
+                    Arg::with_name("users")
+                        .long("users")
+                        .takes_value(true)
+                        .help("Where to save the users, which are thrown away without it"),
+                )
+                .arg(
+                    Arg::with_name("user_pipeline")
+                        .long("user-pipeline")
+                        .takes_value(true)
+                        .help("The stages to run the users through (default none)"),
+                )
+                .arg(
+                    Arg::with_name("delimiter")
+                        .long("delimiter")
+                        .takes_value(true)
+                        .help("The delimiter of every list, like ; or \\t, instead of working it out for each one"),
+                ),
+        )
         .get_matches();
 
-    let source_path = PathBuf::from(matches.value_of("source_path").unwrap_or("lists"));
-    let output_path = PathBuf::from(matches.value_of("output_path").unwrap_or("output.lst"));
+    if matches.is_present("config") && !matches.is_present("profile") {
+        eprintln!("--config only chooses where profiles are read from, so it needs --profile too");
+        exit(1);
+    }
+
+    let mut profile = match matches.value_of("profile") {
+        None => config::Profile {
+            sources: vec![PathBuf::from("lists")],
+            recursive: true,
+            extensions: config::default_extensions(),
+            stages: vec![],
+            outputs: vec![PathBuf::from("output.lst")],
+            memory_limit: None,
+            keep_counts: None,
+            compress: None,
+            hex: false,
+            encoding: None,
+        },
+        Some(name) => {
+            let config_path = PathBuf::from(matches.value_of("config").unwrap_or(DEFAULT_CONFIG));
+            match config::load_profile(&config_path, name) {
+                Err(why) => {
+                    eprintln!("invalid config: {}", why);
+                    exit(1);
+                }
+                Ok(profile) => profile,
+            }
+        }
+    };
+
+    if let Some(source_path) = matches.value_of("source_path") {
+        profile.sources = vec![PathBuf::from(source_path)];
+    }
+    if let Some(output_path) = matches.value_of("output_path") {
+        profile.outputs = vec![PathBuf::from(output_path)];
+    }
+    if let Some(memory_limit) = matches.value_of("memory_limit") {
+        profile.memory_limit = match external::parse_size(memory_limit) {
+            Err(why) => {
+                eprintln!("invalid memory limit: {}", why);
+                exit(1);
+            }
+            Ok(limit) => Some(limit),
+        };
+    }
+    if let Some(compress) = matches.value_of("compress") {
+        profile.compress = match compression::Compression::parse(compress) {
+            Err(why) => {
+                eprintln!("invalid compression: {}", why);
+                exit(1);
+            }
+            Ok(compression) => Some(compression),
+        };
+    }
+    profile.hex |= matches.is_present("hex");
+    if let Some(name) = matches.value_of("encoding") {
+        profile.encoding = match encoding::Encoding::from_name(name) {
+            None => {
+                eprintln!(
+                    "invalid encoding \"{}\", expected utf-8, latin-1, windows-1252, utf-16le or utf-16be",
+                    name
+                );
+                exit(1);
+            }
+            Some(encoding) => Some(encoding),
+        };
+    }
+    if let Some(format) = matches.value_of("keep_counts") {
+        profile.keep_counts = processors::CountFormat::from_name(format);
+    }
+    // Statistics and split passwords are only run through a pipeline that was asked for, rather than the default one.
+    // A profile always asks for its stages, even when it lists none.
+    let pipeline_given = matches.is_present("pipeline") || matches.is_present("profile");
+    if matches.is_present("pipeline") || !pipeline_given {
+        let pipeline_text = matches.value_of("pipeline").unwrap_or(DEFAULT_PIPELINE);
+
+        profile.stages = match pipeline::parse(pipeline_text) {
+            Err(why) => {
+                eprintln!("invalid pipeline: {}", why.annotate(pipeline_text));
+                exit(1);
+            }
+            Ok(stages) => stages,
+        };
+    }
+
+    let mut settings = pipeline::Settings {
+        memory_limit: profile.memory_limit,
+        ..pipeline::Settings::default()
+    };
+    if let Some(temp_dir) = matches.value_of("temp_dir") {
+        settings.temp_dir = PathBuf::from(temp_dir);
+    }
+    if let Some(threads) = matches.value_of("threads") {
+        settings.threads = match threads.parse::<usize>() {
+            Ok(threads) if threads > 0 => threads,
+            _ => {
+                eprintln!("invalid thread count \"{}\", expected a number above 0", threads);
+                exit(1);
+            }
+        };
+    }
+    settings.deterministic = matches.is_present("deterministic");
+    pipeline::set_write_options(&profile.stages, &profile.write_options());
 
-    if !source_path.exists() {
-        eprintln!("lists/ directory doesn't exist.");
+    if let Err(why) = rayon::ThreadPoolBuilder::new()
+        .num_threads(settings.threads)
+        .build_global()
+    {
+        eprintln!("couldn't start {} threads: {}", settings.threads, why);
         exit(1);
     }
 
+    if let ("generate", Some(generate)) = matches.subcommand() {
+        if profile.keep_counts.is_some() || matches.is_present("in_memory") {
+            eprintln!("generated words are always streamed without counts, so --keep-counts and --in-memory can't be used with generate");
+            exit(1);
+        }
+        match generate.subcommand() {
+            ("mask", Some(arguments)) => {
+                // Lists are only read alongside the mask when they were asked for.
+                let files = if matches.is_present("source_path") || matches.is_present("profile") {
+                    list_sources(&profile.sources, &profile)
+                } else {
+                    vec![]
+                };
+                let stages = if pipeline_given { &profile.stages[..] } else { &[] };
+                run_mask(&profile, &settings, files, stages, arguments)
+            }
+            ("combine", Some(arguments)) => {
+                let stages = if pipeline_given { &profile.stages[..] } else { &[] };
+                run_combine(&profile, &settings, stages, arguments)
+            }
+            _ => (),
+        }
+        return;
+    }
+
+    if let ("split", Some(arguments)) = matches.subcommand() {
+        if profile.keep_counts.is_some() || matches.is_present("in_memory") {
+            eprintln!("combo lists are always streamed without counts, so --keep-counts and --in-memory can't be used with split");
+            exit(1);
+        }
+        let files = list_sources(&profile.sources, &profile);
+        let stages = if pipeline_given { &profile.stages[..] } else { &[] };
+        run_split(&profile, &settings, files, stages, arguments);
+        return;
+    }
+
+    if let ("stats", Some(arguments)) = matches.subcommand() {
+        let files = list_sources(&profile.sources, &profile);
+        let stages = if pipeline_given { &profile.stages[..] } else { &[] };
+        run_stats(&profile, &settings, files, stages, arguments);
+        return;
+    }
+
     println!("Getting file list...");
-    let files = file_system::list_files(&source_path, true);
+    let files = list_sources(&profile.sources, &profile);
+    if let Some(format) = profile.keep_counts {
+        run_counted(&profile, &settings, files, format);
+    } else if matches.is_present("in_memory") {
+        run_in_memory(&profile, &settings, files);
+    } else {
+        run_streaming(&profile, &settings, files);
+    }
+}
+
+/// Lists the files in the sources that have one of the profile's extensions.
+fn list_sources(sources: &[PathBuf], profile: &config::Profile) -> Vec<PathBuf> {
+    for source_path in sources {
+        if !source_path.exists() {
+            eprintln!("{} doesn't exist.", source_path.display());
+            exit(1);
+        }
+    }
 
-    println!("Getting valid files...");
-    let files = file_system::filter_extensions(files, extensions);
+    let mut files = vec![];
+    for source_path in sources {
+        files.extend(file_system::list_files(source_path, profile.recursive));
+    }
 
-    println!("Reading lines from files...");
-    let words = file_system::read_files(files);
-    println!("Found {} words.", words.len());
+    let extensions: Vec<&str> = profile.extensions.iter().map(String::as_str).collect();
+    file_system::filter_extensions(files, extensions)
+}
 
-    println!("Trimming leading and tailing whitespace...");
-    let words = processors::trim_whitespaces(words);
-    
-    print!("Removing counts...");
+/// Lists the files of one of combine's lists. A list that's a file is read whatever its extension, like
+/// prefixes.txt or names.csv.
+fn list_combined(list: &Path, profile: &config::Profile) -> Vec<PathBuf> {
+    if list.is_file() {
+        vec![list.to_path_buf()]
+    } else {
+        list_sources(&[list.to_path_buf()], profile)
+    }
+}
+
+/// Streams the words of the files, reading several at once when there's more than one thread.
+fn read_stream(
+    files: Vec<PathBuf>,
+    options: &file_system::ReadOptions,
+    settings: &pipeline::Settings,
+) -> pipeline::WordStream<'static> {
+    if settings.threads > 1 {
+        file_system::stream_files_parallel(files, settings.threads, settings.deterministic, options)
+    } else {
+        Box::new(file_system::stream_files(files, options))
+    }
+}
+
+/// Lists the files that were converted to UTF-8 as they were read, and how.
+fn print_conversions(report: &encoding::Report) {
+    let files = report.files();
+    if files.is_empty() {
+        return;
+    }
+
+    println!("Converted {} files to UTF-8:", files.len());
+    for (path, conversion) in files {
+        println!("  {}: {}", path.display(), conversion);
+    }
+}
+
+/// Streams words from the files through the pipeline and into the outputs, one word at a time.
+fn run_streaming(profile: &config::Profile, settings: &pipeline::Settings, files: Vec<PathBuf>) {
+    let stages: Vec<String> = profile.stages.iter().map(|s| s.to_string()).collect();
+    let mut read = 0;
+
+    print!("Streaming words through {}...", stages.join(" "));
     io::stdout().flush().unwrap();
     let now = Instant::now();
-    let words = processors::remove_counts(words);
+    let read_options = profile.read_options();
+    let words = read_stream(files, &read_options, settings);
+    let words: pipeline::WordStream = Box::new(words.inspect(|_| read += 1));
+    let words = pipeline::stream(&profile.stages, words, settings);
+    let written = file_system::write_stream(&profile.outputs, &profile.write_options(), words);
     println!(" {}ms", now.elapsed().as_millis());
-    
-    print!("Deduplicating...");
+
+    print_conversions(&read_options.report);
+    println!("Read {} words, saved {} words.", read, written);
+}
+
+/// Streams words and their counts from the files through the pipeline, writing each word out with its count.
+fn run_counted(
+    profile: &config::Profile,
+    settings: &pipeline::Settings,
+    files: Vec<PathBuf>,
+    format: processors::CountFormat,
+) {
+    let stages: Vec<String> = profile.stages.iter().map(|s| s.to_string()).collect();
+    let mut read = 0;
+
+    print!("Streaming counted words through {}...", stages.join(" "));
     io::stdout().flush().unwrap();
     let now = Instant::now();
-    let words = processors::deduplicate(words);
+    // Only the word is in $HEX[...] notation, not the count alongside it.
+    let hex = profile.hex;
+    let read_options = file_system::ReadOptions {
+        hex: false,
+        ..profile.read_options()
+    };
+    let write_options = file_system::WriteOptions {
+        hex: false,
+        ..profile.write_options()
+    };
+
+    let words = read_stream(files, &read_options, settings);
+    let words: pipeline::CountedStream = Box::new(
+        words
+            .inspect(|_| read += 1)
+            .map(processors::split_count)
+            .map(move |(word, count)| {
+                let word = if hex { file_system::decode_hex(word) } else { word };
+                (word, count)
+            }),
+    );
+    let words = pipeline::stream_counted(&profile.stages, words).map(move |(word, count)| {
+        let word = if hex { file_system::encode_hex(word) } else { word };
+        processors::format_count(&word, count, format)
+    });
+    let written = file_system::write_stream(&profile.outputs, &write_options, words);
     println!(" {}ms", now.elapsed().as_millis());
-    println!("Found {} unique words.", words.len());
-
-    println!("Removing any words longer than 50 characters... (some of the lists I sourced off Github had HTML pointing to their site)");
-    let words = processors::remove_outside_lengths(words, 0, 50);
-    println!("{} words left.", words.len());
-
-    println!("Inserting 'root ' at the beginning of every password");
-    let words = processors::prefix("root ", words);
-    println!("Inserted 'root ' prefix.");
-    
-    println!("Saving words...");
-    file_system::write_words(&output_path, &words);
+
+    print_conversions(&read_options.report);
+    println!("Read {} words, saved {} words.", read, written);
+}
+
+/// Generates the words of a mask and streams them through the stages, after the words of any files, unless it would
+/// make too many.
+fn run_mask(
+    profile: &config::Profile,
+    settings: &pipeline::Settings,
+    files: Vec<PathBuf>,
+    stages: &[pipeline::Stage],
+    arguments: &ArgMatches,
+) {
+    let mut custom = [None, None, None, None];
+    for (i, charset) in custom.iter_mut().enumerate() {
+        if let Some(text) = arguments.value_of(format!("charset{}", i + 1)) {
+            *charset = match mask::parse_charset(text) {
+                Err(why) => {
+                    eprintln!("invalid custom charset {}: {}", i + 1, why);
+                    exit(1);
+                }
+                Ok(parsed) => Some(parsed),
+            };
+        }
+    }
+
+    let text = arguments.value_of("mask").unwrap();
+    let mut mask = match mask::Mask::parse(text, &custom) {
+        Err(why) => {
+            eprintln!("invalid mask: {}", why);
+            exit(1);
+        }
+        Ok(mask) => mask,
+    };
+    if arguments.is_present("increment") {
+        if let Err(why) = mask.increment(arguments.value_of("increment")) {
+            eprintln!("invalid mask: {}", why);
+            exit(1);
+        }
+    }
+
+    let max_keyspace = match arguments.value_of("max_keyspace").map(mask::parse_count) {
+        None => DEFAULT_MAX_KEYSPACE,
+        Some(Ok(max)) => max,
+        Some(Err(why)) => {
+            eprintln!("invalid keyspace cap: {}", why);
+            exit(1);
+        }
+    };
+    let keyspace = match mask.keyspace() {
+        None => format!("more than {}", u64::MAX),
+        Some(keyspace) => keyspace.to_string(),
+    };
+    if arguments.is_present("keyspace") {
+        println!("{}", keyspace);
+        return;
+    }
+    if mask.keyspace().is_none_or(|keyspace| keyspace > max_keyspace) {
+        eprintln!(
+            "mask {} makes {} words, more than the cap of {}. Raise it with --max-keyspace",
+            text, keyspace, max_keyspace
+        );
+        exit(1);
+    }
+
+    let names: Vec<String> = stages.iter().map(|s| s.to_string()).collect();
+    let mut read = 0;
+    let mut generated = 0;
+
+    println!("Mask {} makes {} words.", text, keyspace);
+    print!("Streaming generated words through {}...", names.join(" "));
+    io::stdout().flush().unwrap();
+    let now = Instant::now();
+    let read_options = profile.read_options();
+    let listed = read_stream(files, &read_options, settings).inspect(|_| read += 1);
+    let words: pipeline::WordStream = Box::new(listed.chain(mask.words().inspect(|_| generated += 1)));
+    let words = pipeline::stream(stages, words, settings);
+    let written = file_system::write_stream(&profile.outputs, &profile.write_options(), words);
+    println!(" {}ms", now.elapsed().as_millis());
+
+    print_conversions(&read_options.report);
+    println!("Read {} words, generated {} words, saved {} words.", read, generated, written);
+}
+
+/// Reads a length limit given to combine, exiting if it isn't a number.
+fn length_argument(arguments: &ArgMatches, name: &str, default: usize) -> usize {
+    match arguments.value_of(name).map(str::parse::<usize>) {
+        None => default,
+        Some(Ok(length)) => length,
+        Some(Err(_)) => {
+            eprintln!(
+                "invalid --{} \"{}\", expected a number",
+                name.replace('_', "-"),
+                arguments.value_of(name).unwrap()
+            );
+            exit(1);
+        }
+    }
+}
+
+/// Streams the first list through its pipeline and joins each word with every combination of the other lists,
+/// which are read into memory through their own pipelines first. The combinations go through the stages.
+fn run_combine(
+    profile: &config::Profile,
+    settings: &pipeline::Settings,
+    stages: &[pipeline::Stage],
+    arguments: &ArgMatches,
+) {
+    let lists: Vec<PathBuf> = arguments.values_of("lists").unwrap().map(PathBuf::from).collect();
+
+    let mut sides: Vec<Vec<pipeline::Stage>> = vec![vec![]; lists.len()];
+    for side in arguments.values_of("side").into_iter().flatten() {
+        let (index, pipeline_text) = match side.find('=').map(|i| (side[..i].parse::<usize>(), &side[i + 1..])) {
+            Some((Ok(index), pipeline_text)) if index >= 1 && index <= lists.len() => (index, pipeline_text),
+            _ => {
+                eprintln!(
+                    "invalid side \"{}\", expected a list number from 1 to {} and a pipeline like 2='t d'",
+                    side,
+                    lists.len()
+                );
+                exit(1);
+            }
+        };
+        sides[index - 1] = match pipeline::parse(pipeline_text) {
+            Err(why) => {
+                eprintln!("invalid pipeline for list {}: {}", index, why.annotate(pipeline_text));
+                exit(1);
+            }
+            Ok(stages) => stages,
+        };
+        pipeline::set_write_options(&sides[index - 1], &profile.write_options());
+    }
+
+    let separators: Vec<Vec<u8>> = arguments
+        .values_of("separator")
+        .map(|values| values.map(|separator| separator.as_bytes().to_vec()).collect())
+        .unwrap_or_default();
+    let separators = match separators.len() {
+        0 => vec![vec![]; lists.len() - 1],
+        1 => vec![separators[0].clone(); lists.len() - 1],
+        count if count == lists.len() - 1 => separators,
+        count => {
+            eprintln!(
+                "invalid separators: {} were given for {} joins, expected one for all of them or one for each",
+                count,
+                lists.len() - 1
+            );
+            exit(1);
+        }
+    };
+    let min_length = length_argument(arguments, "min_length", 0);
+    let max_length = length_argument(arguments, "max_length", usize::MAX);
+
+    let read_options = profile.read_options();
+    let mut rest = vec![];
+    for (i, list) in lists.iter().enumerate().skip(1) {
+        let files = list_combined(list, profile);
+        let words = read_stream(files, &read_options, settings);
+        let words: Vec<word::Word> = pipeline::stream(&sides[i], words, settings).collect();
+        println!("List {} has {} words.", i + 1, words.len());
+        rest.push(words);
+    }
+    let combinator = combinator::Combinator::new(rest, separators, min_length, max_length);
+
+    let names: Vec<String> = stages.iter().map(|s| s.to_string()).collect();
+    let mut read = 0;
+    let mut generated = 0;
+
+    print!("Streaming combined words through {}...", names.join(" "));
+    io::stdout().flush().unwrap();
+    let now = Instant::now();
+    let files = list_combined(&lists[0], profile);
+    let words = read_stream(files, &read_options, settings);
+    let words = pipeline::stream(&sides[0], words, settings);
+    let words: pipeline::WordStream = Box::new(
+        words
+            .inspect(|_| read += 1)
+            .flat_map(|word| combinator.combine(word))
+            .inspect(|_| generated += 1),
+    );
+    let words = pipeline::stream(stages, words, settings);
+    let written = file_system::write_stream(&profile.outputs, &profile.write_options(), words);
+    println!(" {}ms", now.elapsed().as_millis());
+
+    print_conversions(&read_options.report);
+    println!(
+        "Read {} words from list 1, generated {} words, saved {} words.",
+        read, generated, written
+    );
+}
+
+/// Splits the lines of combo lists into users and passwords at each file's delimiter. Passwords are streamed through
+/// the stages and into the outputs, while users go through their own pipeline on another thread.
+fn run_split(
+    profile: &config::Profile,
+    settings: &pipeline::Settings,
+    files: Vec<PathBuf>,
+    stages: &[pipeline::Stage],
+    arguments: &ArgMatches,
+) {
+    let forced = match arguments.value_of("delimiter") {
+        None => None,
+        Some("\\t") => Some(combo::Delimiter(b'\t')),
+        Some(text) if text.len() == 1 => Some(combo::Delimiter(text.as_bytes()[0])),
+        Some(text) => {
+            eprintln!("invalid delimiter \"{}\", expected a single character like ; or \\t", text);
+            exit(1);
+        }
+    };
+    let user_stages = match arguments.value_of("user_pipeline") {
+        None => vec![],
+        Some(pipeline_text) => match pipeline::parse(pipeline_text) {
+            Err(why) => {
+                eprintln!("invalid user pipeline: {}", why.annotate(pipeline_text));
+                exit(1);
+            }
+            Ok(stages) => stages,
+        },
+    };
+    pipeline::set_write_options(&user_stages, &profile.write_options());
+    let users_path = arguments.value_of("users").map(PathBuf::from);
+
+    let read_options = profile.read_options();
+    let mut lists = vec![];
+    println!("Delimiters:");
+    for file in files {
+        let delimiter = forced.or_else(|| {
+            // A report of its own, so sampled files aren't listed twice.
+            let sample_options = file_system::ReadOptions {
+                report: encoding::Report::default(),
+                ..read_options.clone()
+            };
+            let sample: Vec<word::Word> = file_system::stream_files(vec![file.clone()], &sample_options)
+                .take(combo::SAMPLE_LINES)
+                .collect();
+            combo::detect_delimiter(&sample)
+        });
+
+        match delimiter {
+            None => println!("  {}: none found, skipped", file.display()),
+            Some(delimiter) => {
+                println!("  {}: {}", file.display(), delimiter);
+                lists.push((file, delimiter));
+            }
+        }
+    }
+
+    let stage_names: Vec<String> = stages.iter().map(|s| s.to_string()).collect();
+    let mut read = 0;
+    let mut skipped = 0;
+
+    print!("Streaming passwords through {}...", stage_names.join(" "));
+    io::stdout().flush().unwrap();
+    let now = Instant::now();
+    let (written, users_written) = thread::scope(|scope| {
+        let (sender, users) = match &users_path {
+            None => (None, None),
+            Some(path) => {
+                let (sender, receiver) = mpsc::sync_channel(USER_QUEUE);
+                let user_stages = &user_stages;
+                let write_options = profile.write_options();
+                let users = scope.spawn(move || {
+                    let words: pipeline::WordStream = Box::new(receiver.into_iter());
+                    let words = pipeline::stream(user_stages, words, settings);
+                    file_system::write_stream(std::slice::from_ref(path), &write_options, words)
+                });
+                (Some(sender), Some(users))
+            }
+        };
+
+        let skipped = &mut skipped;
+        let read_options = &read_options;
+        let passwords = lists
+            .into_iter()
+            .flat_map(move |(file, delimiter)| {
+                file_system::stream_files(vec![file], read_options).map(move |line| (line, delimiter))
+            })
+            .inspect(|_| read += 1)
+            .filter_map(move |(line, delimiter)| match combo::split_combo(&line, delimiter) {
+                None => {
+                    *skipped += 1;
+                    None
+                }
+                Some((user, password)) => {
+                    if let Some(sender) = &sender {
+                        if !user.is_empty() {
+                            // The users thread only stops early if it failed, which it reports itself.
+                            let _ = sender.send(user);
+                        }
+                    }
+                    Some(password).filter(|password| !password.is_empty())
+                }
+            });
+        let passwords = pipeline::stream(stages, Box::new(passwords), settings);
+        let written = file_system::write_stream(&profile.outputs, &profile.write_options(), passwords);
+
+        (written, users.map(|users| users.join().unwrap()))
+    });
+    println!(" {}ms", now.elapsed().as_millis());
+
+    print_conversions(&read_options.report);
+    println!("Read {} lines, skipped {} without a delimiter.", read, skipped);
+    match users_written {
+        None => println!("Saved {} passwords.", written),
+        Some(users_written) => println!("Saved {} passwords and {} users.", written, users_written),
+    }
+}
+
+/// Streams words from the files through the stages and prints statistics about the words that come out.
+/// With counts kept, each word is counted as many times as it was seen.
+fn run_stats(
+    profile: &config::Profile,
+    settings: &pipeline::Settings,
+    files: Vec<PathBuf>,
+    stages: &[pipeline::Stage],
+    arguments: &ArgMatches,
+) {
+    let top = match arguments.value_of("top").map(str::parse::<usize>) {
+        None => 10,
+        Some(Ok(top)) => top,
+        Some(Err(_)) => {
+            eprintln!("invalid --top \"{}\", expected a number", arguments.value_of("top").unwrap());
+            exit(1);
+        }
+    };
+
+    let mut stats = stats::Stats::default();
+    if profile.keep_counts.is_some() {
+        let hex = profile.hex;
+        let read_options = file_system::ReadOptions {
+            hex: false,
+            ..profile.read_options()
+        };
+        let words = read_stream(files, &read_options, settings);
+        let words: pipeline::CountedStream = Box::new(words.map(processors::split_count).map(
+            move |(word, count)| {
+                let word = if hex { file_system::decode_hex(word) } else { word };
+                (word, count)
+            },
+        ));
+        for (word, count) in pipeline::stream_counted(stages, words) {
+            stats.add(&word, count);
+        }
+    } else {
+        let words = read_stream(files, &profile.read_options(), settings);
+        for word in pipeline::stream(stages, words, settings) {
+            stats.add(&word, 1);
+        }
+    }
+
+    match arguments.value_of("format") {
+        Some("json") => print!("{}", stats.to_json(top)),
+        _ => print!("{}", stats.to_text(top)),
+    }
+}
+
+/// Reads every word into memory, then runs each stage over all of them before the next.
+fn run_in_memory(profile: &config::Profile, settings: &pipeline::Settings, files: Vec<PathBuf>) {
+    println!("Reading lines from files...");
+    let read_options = profile.read_options();
+    let mut words = if settings.threads > 1 {
+        file_system::read_files_parallel(files, &read_options)
+    } else {
+        file_system::read_files(files, &read_options)
+    };
+    print_conversions(&read_options.report);
+    println!("Found {} words.", words.len());
+
+    for stage in &profile.stages {
+        print!("Running {}...", stage);
+        io::stdout().flush().unwrap();
+        let now = Instant::now();
+        words = if settings.threads > 1 && stage.is_per_word() {
+            parallel::apply(stage, words)
+        } else {
+            stage.apply(words)
+        };
+        println!(" {}ms", now.elapsed().as_millis());
+        println!("{} words left.", words.len());
+    }
+
+    for output_path in &profile.outputs {
+        println!("Saving words to {}...", output_path.display());
+        file_system::write_words(output_path, &profile.write_options(), &words);
+    }
 }
 
 // TODO: Profile this beast.
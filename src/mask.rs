@@ -0,0 +1,388 @@
+use std::string::String;
+use std::vec::Vec;
+
+use crate::word::Word;
+
+/// hashcat's ?s, every printable ASCII character that isn't a letter or digit, along with space.
+const SYMBOLS: &[u8] = b" !\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+/// The characters of one of hashcat's built-in charsets, like l for ?l.
+fn builtin(name: u8) -> Option<Vec<u8>> {
+    let charset = match name {
+        b'l' => (b'a'..=b'z').collect(),
+        b'u' => (b'A'..=b'Z').collect(),
+        b'd' => (b'0'..=b'9').collect(),
+        b'h' => (b'0'..=b'9').chain(b'a'..=b'f').collect(),
+        b'H' => (b'0'..=b'9').chain(b'A'..=b'F').collect(),
+        b's' => SYMBOLS.to_vec(),
+        b'a' => (b'a'..=b'z')
+            .chain(b'A'..=b'Z')
+            .chain(b'0'..=b'9')
+            .chain(SYMBOLS.iter().cloned())
+            .collect(),
+        b'b' => (0..=255).collect(),
+        _ => return None,
+    };
+    Some(charset)
+}
+
+/// Splits a mask or custom charset into the characters each of its parts can be.
+///
+/// # Arguments
+///
+/// * `text` - The mask, made of literal characters and charsets like ?l.
+/// * `custom` - The custom charsets ?1 to ?4 can be, or None where they can't be used.
+fn positions(text: &str, custom: Option<&[Option<Vec<u8>>; 4]>) -> Result<Vec<Vec<u8>>, String> {
+    let mut positions = vec![];
+    let mut bytes = text.bytes();
+
+    while let Some(byte) = bytes.next() {
+        if byte != b'?' {
+            positions.push(vec![byte]);
+            continue;
+        }
+
+        let name = match bytes.next() {
+            None => return Err(format!("\"{}\" ends with a ? that isn't followed by a charset", text)),
+            Some(name) => name,
+        };
+        let charset = match (name, custom) {
+            (b'?', _) => vec![b'?'],
+            (b'1'..=b'4', Some(custom)) => match &custom[(name - b'1') as usize] {
+                None => {
+                    return Err(format!(
+                        "?{} is used but custom charset {} isn't given",
+                        name as char, name as char
+                    ))
+                }
+                Some(charset) => charset.clone(),
+            },
+            (b'1'..=b'4', None) => {
+                return Err(format!(
+                    "?{} can't be used in a custom charset",
+                    name as char
+                ))
+            }
+            _ => match builtin(name) {
+                None => {
+                    return Err(format!(
+                        "unknown charset \"?{}\" in \"{}\", expected ?l, ?u, ?d, ?h, ?H, ?s, ?a, ?b, ?1 to ?4 or ??",
+                        String::from_utf8_lossy(&[name]),
+                        text
+                    ))
+                }
+                Some(charset) => charset,
+            },
+        };
+        positions.push(charset);
+    }
+
+    Ok(positions)
+}
+
+/// Parses a custom charset like ?l?d or abc123 into its characters, each one only once.
+///
+/// # Arguments
+///
+/// * `text` - The charset, made of literal characters and built-in charsets.
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(parse_charset("?dabc0"), Ok(b"0123456789abc".to_vec()));
+/// ```
+pub fn parse_charset(text: &str) -> Result<Vec<u8>, String> {
+    let mut seen = [false; 256];
+    let mut charset = vec![];
+
+    for byte in positions(text, None)?.into_iter().flatten() {
+        if !seen[byte as usize] {
+            seen[byte as usize] = true;
+            charset.push(byte);
+        }
+    }
+
+    if charset.is_empty() {
+        return Err("custom charsets can't be empty".to_string());
+    }
+    Ok(charset)
+}
+
+/// Parses a number of words like 1000000 or 1e9.
+///
+/// # Arguments
+///
+/// * `text` - The number, either written out or as a power of ten.
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(parse_count("2e3"), Ok(2000));
+/// ```
+pub fn parse_count(text: &str) -> Result<u64, String> {
+    let invalid = || format!("invalid number \"{}\", expected something like 1000000 or 1e9", text);
+    let text = text.trim();
+
+    match text.find(['e', 'E']) {
+        None => text.parse::<u64>().map_err(|_| invalid()),
+        Some(e) => {
+            let base = text[..e].parse::<u64>().map_err(|_| invalid())?;
+            let exponent = text[e + 1..].parse::<u32>().map_err(|_| invalid())?;
+            10u64
+                .checked_pow(exponent)
+                .and_then(|power| base.checked_mul(power))
+                .ok_or_else(|| format!("number \"{}\" is too large", text))
+        }
+    }
+}
+
+/// A hashcat mask like ?u?l?l?l?d?d?s, and the lengths of it that are generated.
+#[derive(Debug, Clone)]
+pub struct Mask {
+    positions: Vec<Vec<u8>>,
+    min_length: usize,
+    max_length: usize,
+}
+
+impl Mask {
+    /// Parses a mask, giving each position the characters it can be.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The mask. ?l, ?u, ?d, ?h, ?H, ?s, ?a and ?b are hashcat's charsets, ?1 to ?4 are the custom ones, ?? is a ? and anything else is itself.
+    /// * `custom` - The custom charsets, already parsed with `parse_charset`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mask = Mask::parse("?d?1", &[Some(b"ab".to_vec()), None, None, None]).unwrap();
+    /// assert_eq!(mask.keyspace(), Some(20));
+    /// ```
+    pub fn parse(text: &str, custom: &[Option<Vec<u8>>; 4]) -> Result<Mask, String> {
+        let positions = positions(text, Some(custom))?;
+        if positions.is_empty() {
+            return Err("the mask is empty".to_string());
+        }
+
+        let length = positions.len();
+        Ok(Mask {
+            positions,
+            min_length: length,
+            max_length: length,
+        })
+    }
+
+    /// Generates the mask at several lengths, like hashcat's --increment. Each length uses that many of the mask's first positions.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The shortest and longest lengths, like 4:8, or None for every length of the mask.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut mask = Mask::parse("?d?d?d", &[None, None, None, None]).unwrap();
+    /// mask.increment(Some("2:3")).unwrap();
+    /// assert_eq!(mask.keyspace(), Some(1100));
+    /// ```
+    pub fn increment(&mut self, range: Option<&str>) -> Result<(), String> {
+        let positions = self.positions.len();
+        let (min, max) = match range {
+            None => (1, positions),
+            Some(range) => {
+                let invalid = || format!("invalid increment \"{}\", expected MIN:MAX like 4:8", range);
+                let mut parts = range.splitn(2, ':');
+                let min = parts.next().unwrap().trim().parse::<usize>().map_err(|_| invalid())?;
+                let max = match parts.next() {
+                    None => positions,
+                    Some(max) => max.trim().parse::<usize>().map_err(|_| invalid())?,
+                };
+                (min, max)
+            }
+        };
+
+        if min == 0 || min > max || max > positions {
+            return Err(format!(
+                "increment {}:{} doesn't fit a mask of {} positions",
+                min, max, positions
+            ));
+        }
+        self.min_length = min;
+        self.max_length = max;
+        Ok(())
+    }
+
+    /// How many words the mask makes across all its lengths, or None if that doesn't fit in a u64.
+    pub fn keyspace(&self) -> Option<u64> {
+        let mut total: u64 = 0;
+        let mut product: u64 = 1;
+
+        for (i, charset) in self.positions[..self.max_length].iter().enumerate() {
+            product = product.checked_mul(charset.len() as u64)?;
+            if i + 1 >= self.min_length {
+                total = total.checked_add(product)?;
+            }
+        }
+        Some(total)
+    }
+
+    /// Generates every word the mask makes, shortest first, with the last position changing fastest.
+    pub fn words(&self) -> MaskWords {
+        MaskWords::new(self.positions.clone(), self.min_length, self.max_length)
+    }
+}
+
+/// The words a mask makes, generated one at a time.
+pub struct MaskWords {
+    positions: Vec<Vec<u8>>,
+    max_length: usize,
+    indexes: Vec<usize>,
+    current: Vec<u8>,
+    done: bool,
+}
+
+impl MaskWords {
+    fn new(positions: Vec<Vec<u8>>, min_length: usize, max_length: usize) -> MaskWords {
+        let mut words = MaskWords {
+            positions,
+            max_length,
+            indexes: vec![],
+            current: vec![],
+            done: false,
+        };
+        words.start(min_length);
+        words
+    }
+
+    /// Starts over at the first word of the given length.
+    fn start(&mut self, length: usize) {
+        self.indexes = vec![0; length];
+        self.current = self.positions[..length].iter().map(|charset| charset[0]).collect();
+    }
+}
+
+impl Iterator for MaskWords {
+    type Item = Word;
+
+    fn next(&mut self) -> Option<Word> {
+        if self.done {
+            return None;
+        }
+        let word = Word::from(self.current.clone());
+
+        for i in (0..self.indexes.len()).rev() {
+            self.indexes[i] += 1;
+            if self.indexes[i] < self.positions[i].len() {
+                self.current[i] = self.positions[i][self.indexes[i]];
+                return Some(word);
+            }
+            self.indexes[i] = 0;
+            self.current[i] = self.positions[i][0];
+        }
+
+        let length = self.indexes.len() + 1;
+        if length > self.max_length {
+            self.done = true;
+        } else {
+            self.start(length);
+        }
+        Some(word)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NO_CUSTOM: [Option<Vec<u8>>; 4] = [None, None, None, None];
+
+    #[test]
+    fn test_parse() {
+        let mask = Mask::parse("?u?l?l?l?d?d?s", &NO_CUSTOM).unwrap();
+        let sizes: Vec<usize> = mask.positions.iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![26, 26, 26, 26, 10, 10, 33]);
+        assert_eq!(mask.keyspace(), Some(26 * 26 * 26 * 26 * 10 * 10 * 33));
+
+        let mask = Mask::parse("?a?b?h?H", &NO_CUSTOM).unwrap();
+        let sizes: Vec<usize> = mask.positions.iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![95, 256, 16, 16]);
+
+        let mask = Mask::parse("a??b", &NO_CUSTOM).unwrap();
+        assert_eq!(mask.positions, vec![vec![b'a'], vec![b'?'], vec![b'b']]);
+
+        assert_eq!(parse_charset("?dabc0"), Ok(b"0123456789abc".to_vec()));
+        let custom = [Some(parse_charset("?l?d").unwrap()), None, Some(b"!".to_vec()), None];
+        let mask = Mask::parse("?1?3", &custom).unwrap();
+        assert_eq!(mask.keyspace(), Some(36));
+
+        assert_eq!(Mask::parse("?b?b?b?b?b?b?b?b?b", &NO_CUSTOM).unwrap().keyspace(), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Mask::parse("", &NO_CUSTOM).unwrap_err(), "the mask is empty");
+        assert_eq!(
+            Mask::parse("?d?", &NO_CUSTOM).unwrap_err(),
+            "\"?d?\" ends with a ? that isn't followed by a charset"
+        );
+        assert_eq!(
+            Mask::parse("?x", &NO_CUSTOM).unwrap_err(),
+            "unknown charset \"?x\" in \"?x\", expected ?l, ?u, ?d, ?h, ?H, ?s, ?a, ?b, ?1 to ?4 or ??"
+        );
+        assert_eq!(
+            Mask::parse("?2", &NO_CUSTOM).unwrap_err(),
+            "?2 is used but custom charset 2 isn't given"
+        );
+        assert_eq!(parse_charset("?1").unwrap_err(), "?1 can't be used in a custom charset");
+        assert_eq!(parse_charset("").unwrap_err(), "custom charsets can't be empty");
+
+        assert_eq!(parse_count("1e9"), Ok(1_000_000_000));
+        assert_eq!(parse_count("250"), Ok(250));
+        assert!(parse_count("1e30").is_err());
+        assert!(parse_count("lots").is_err());
+    }
+
+    #[test]
+    fn test_words() {
+        let custom = [Some(b"ab".to_vec()), None, None, None];
+        let mask = Mask::parse("x?1?d", &custom).unwrap();
+        let words: Vec<Word> = mask.words().take(4).collect();
+        assert_eq!(words, vec!["xa0", "xa1", "xa2", "xa3"]);
+        let words: Vec<Word> = mask.words().collect();
+        assert_eq!(words.len() as u64, mask.keyspace().unwrap());
+        assert_eq!(words[9], "xa9");
+        assert_eq!(words[10], "xb0");
+        assert_eq!(words[19], "xb9");
+    }
+
+    #[test]
+    fn test_increment() {
+        let custom = [Some(b"ab".to_vec()), None, None, None];
+        let mut mask = Mask::parse("?1?1?1", &custom).unwrap();
+        mask.increment(None).unwrap();
+        assert_eq!(mask.keyspace(), Some(2 + 4 + 8));
+        let words: Vec<Word> = mask.words().collect();
+        assert_eq!(
+            words,
+            vec![
+                "a", "b", "aa", "ab", "ba", "bb", "aaa", "aab", "aba", "abb", "baa", "bab", "bba",
+                "bbb"
+            ]
+        );
+
+        mask.increment(Some("2:2")).unwrap();
+        let words: Vec<Word> = mask.words().collect();
+        assert_eq!(words, vec!["aa", "ab", "ba", "bb"]);
+        mask.increment(Some("3")).unwrap();
+        assert_eq!(mask.keyspace(), Some(8));
+
+        assert_eq!(
+            mask.increment(Some("2:4")).unwrap_err(),
+            "increment 2:4 doesn't fit a mask of 3 positions"
+        );
+        assert_eq!(
+            mask.increment(Some("two")).unwrap_err(),
+            "invalid increment \"two\", expected MIN:MAX like 4:8"
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::vec;
+use std::vec::Vec;
+
+use rayon::prelude::*;
+
+use crate::pipeline::{Stage, WordStream};
+use crate::word::Word;
+
+/// How many words are handed out to the threads at a time.
+const CHUNK_SIZE: usize = 1 << 16;
+
+/// Takes the next chunk of words from a stream. The chunk is only empty once the stream has ended.
+fn next_chunk(words: &mut WordStream, size: usize) -> Vec<Word> {
+    words.by_ref().take(size).collect()
+}
+
+/// Runs a word through stages that work one word at a time, adding whatever comes out of the last stage to
+/// processed. A word stops once a stage removes it.
+fn process_word(stages: &[Stage], word: Word, processed: &mut Vec<Word>) {
+    let (stage, rest) = match stages.split_first() {
+        None => return processed.push(word),
+        Some(split) => split,
+    };
+
+    if stage.expands() {
+        for word in stage.expand_word(word) {
+            process_word(rest, word, processed);
+        }
+    } else if let Some(word) = stage.process_word(word) {
+        process_word(rest, word, processed);
+    }
+}
+
+/// Runs every word of a chunk through the stages, spreading the words across every thread.
+/// Words come out in the same order they went in.
+fn process_chunk(stages: &[Stage], chunk: Vec<Word>) -> Vec<Word> {
+    chunk
+        .into_par_iter()
+        .fold(Vec::new, |mut processed, word| {
+            process_word(stages, word, &mut processed);
+            processed
+        })
+        .flatten_iter()
+        .collect()
+}
+
+/// Iterator returned by stages.
+pub struct ParallelStages<'a> {
+    words: WordStream<'a>,
+    stages: &'a [Stage],
+    chunk_size: usize,
+    processed: vec::IntoIter<Word>,
+}
+
+impl<'a> Iterator for ParallelStages<'a> {
+    type Item = Word;
+
+    fn next(&mut self) -> Option<Word> {
+        loop {
+            if let Some(word) = self.processed.next() {
+                return Some(word);
+            }
+
+            let chunk = next_chunk(&mut self.words, self.chunk_size);
+            if chunk.is_empty() {
+                return None;
+            }
+
+            self.processed = process_chunk(self.stages, chunk).into_iter();
+        }
+    }
+}
+
+/// Runs stages that work one word at a time over chunks of words, spreading each chunk across every thread.
+/// Words come out in the same order they went in. Chunks are made smaller when stages can turn one word into
+/// several, so a chunk's words still fit in memory once they've been processed.
+///
+/// # Arguments
+///
+/// * `words` - The stream of words to process.
+/// * `stages` - The stages to run, all of which must work one word at a time (see Stage::is_per_word).
+pub fn stages<'a>(words: WordStream<'a>, stages: &'a [Stage]) -> ParallelStages<'a> {
+    let expansion = stages
+        .iter()
+        .fold(1usize, |expansion, stage| expansion.saturating_mul(stage.expansion().max(1)));
+
+    ParallelStages {
+        words,
+        stages,
+        chunk_size: (CHUNK_SIZE / expansion).max(rayon::current_num_threads()),
+        processed: vec![].into_iter(),
+    }
+}
+
+/// Runs one stage over every word in memory, spreading the words across every thread.
+/// Words come out in the same order they went in.
+///
+/// # Arguments
+///
+/// * `stage` - The stage to run, which must work one word at a time (see Stage::is_per_word).
+/// * `words` - A vector of all the words to process, one word per string.
+pub fn apply(stage: &Stage, words: Vec<Word>) -> Vec<Word> {
+    process_chunk(std::slice::from_ref(stage), words)
+}
+
+/// Picks which shard a word belongs to. Every copy of a word always lands in the same shard.
+fn shard_of(word: &Word, shards: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    word.hash(&mut hasher);
+    (hasher.finish() % shards as u64) as usize
+}
+
+/// Iterator returned by deduplicate.
+pub struct ShardedDeduplicate<'a> {
+    words: WordStream<'a>,
+    shards: Vec<HashSet<Word>>,
+    unique: vec::IntoIter<Word>,
+}
+
+impl<'a> Iterator for ShardedDeduplicate<'a> {
+    type Item = Word;
+
+    fn next(&mut self) -> Option<Word> {
+        loop {
+            if let Some(word) = self.unique.next() {
+                return Some(word);
+            }
+
+            let chunk = next_chunk(&mut self.words, CHUNK_SIZE);
+            if chunk.is_empty() {
+                self.shards = vec![];
+                return None;
+            }
+
+            let shard_count = self.shards.len();
+            let mut positions: Vec<Vec<usize>> = vec![vec![]; shard_count];
+            let shard_indexes: Vec<usize> = chunk
+                .par_iter()
+                .map(|word| shard_of(word, shard_count))
+                .collect();
+            for (position, shard) in shard_indexes.into_iter().enumerate() {
+                positions[shard].push(position);
+            }
+
+            // Every shard checks its own words, in the order they came in, so the first copy is the one kept.
+            let kept: Vec<Vec<usize>> = self
+                .shards
+                .par_iter_mut()
+                .zip(positions)
+                .map(|(seen, positions)| {
+                    positions
+                        .into_iter()
+                        .filter(|p| seen.insert(chunk[*p].clone()))
+                        .collect()
+                })
+                .collect();
+
+            let mut keep = vec![false; chunk.len()];
+            for position in kept.into_iter().flatten() {
+                keep[position] = true;
+            }
+
+            self.unique = chunk
+                .into_iter()
+                .zip(keep)
+                .filter(|(_, keep)| *keep)
+                .map(|(word, _)| word)
+                .collect::<Vec<Word>>()
+                .into_iter();
+        }
+    }
+}
+
+/// Deduplicates a stream of words using every thread. Words are split between one set per thread by their
+/// hash, so the sets never overlap. Like WordIterator::deduplicate, words are passed on the first time they're
+/// seen, so the output is the same as deduplicating with one thread.
+///
+/// # Arguments
+///
+/// * `words` - The stream of words to deduplicate.
+pub fn deduplicate(words: WordStream) -> ShardedDeduplicate {
+    ShardedDeduplicate {
+        words,
+        shards: vec![HashSet::new(); rayon::current_num_threads().max(1)],
+        unique: vec![].into_iter(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processors::{LengthUnit, WordIterator};
+
+    fn words(count: usize) -> Vec<Word> {
+        (0..count)
+            .map(|i| Word::from(format!(" {} word{} ", i % 7, (i * 31) % 1000)))
+            .collect()
+    }
+
+    #[test]
+    fn test_stages_keep_order() {
+        let stages = vec![
+            Stage::TrimWhitespaces,
+            Stage::RemoveCounts,
+            Stage::RemoveOutsideLengths(0, 6, LengthUnit::Bytes),
+            Stage::Prefix(String::from("> ")),
+        ];
+        let correct: Vec<Word> = words(200_000)
+            .into_iter()
+            .trim_whitespaces()
+            .remove_counts()
+            .remove_outside_lengths(0, 6, LengthUnit::Bytes)
+            .prefix("> ")
+            .collect();
+
+        let result: Vec<Word> =
+            super::stages(Box::new(words(200_000).into_iter()), &stages).collect();
+
+        assert_eq!(result, correct);
+    }
+
+    #[test]
+    fn test_apply() {
+        let stage = Stage::RemoveCounts;
+        let correct = crate::processors::remove_counts(words(1000));
+
+        let result = apply(&stage, words(1000));
+
+        assert_eq!(result, correct);
+    }
+
+    #[test]
+    fn test_deduplicate_keeps_first() {
+        let correct: Vec<Word> = words(200_000).into_iter().deduplicate().collect();
+
+        let result: Vec<Word> = deduplicate(Box::new(words(200_000).into_iter())).collect();
+
+        assert_eq!(result, correct);
+    }
+}
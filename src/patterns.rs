@@ -0,0 +1,271 @@
+use std::fmt;
+use std::string::String;
+use std::vec::Vec;
+
+use lazy_static::lazy_static;
+use regex::bytes::{Regex, RegexBuilder, RegexSet};
+
+use crate::word::Word;
+
+lazy_static! {
+    /// The parts of a sed replacement that mean something: & for the whole match, a backslash before a digit, &,
+    /// backslash, n or $, the regex crate's ${1} and ${name}, and any other $, which is escaped for the regex crate.
+    static ref SED_REPLACEMENT: regex::Regex = regex::Regex::new(r"\\([0-9&\\n$])|&|\$\{\w+\}|\$").unwrap();
+}
+
+/// Describes why a regex couldn't be compiled, keeping only the reason from the end of the regex crate's message.
+fn invalid_regex(source: &str, why: regex::Error) -> String {
+    let why = why.to_string();
+    let reason = why.lines().last().unwrap_or("").trim_start_matches("error: ");
+    format!("invalid regex \"{}\": {}", source, reason)
+}
+
+/// A list of regexes that words are checked against, all at once.
+#[derive(Debug, Clone)]
+pub struct Patterns {
+    /// The regexes as they were written.
+    pub sources: Vec<String>,
+    set: RegexSet,
+}
+
+impl Patterns {
+    /// Compiles a list of regexes.
+    ///
+    /// # Arguments
+    ///
+    /// * `sources` - The regexes, in the regex crate's syntax.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let patterns = Patterns::new(&[String::from(r"^\d+$"), String::from("@")]).unwrap();
+    ///
+    /// assert!(patterns.is_match(&Word::from("hello@example.com")));
+    /// ```
+    pub fn new(sources: &[String]) -> Result<Patterns, String> {
+        for source in sources {
+            if let Err(why) = regex::bytes::Regex::new(source) {
+                return Err(invalid_regex(source, why));
+            }
+        }
+
+        let set = RegexSet::new(sources).map_err(|why| why.to_string())?;
+        Ok(Patterns {
+            sources: sources.to_vec(),
+            set,
+        })
+    }
+
+    /// Returns true if any of the regexes match somewhere in the word.
+    pub fn is_match(&self, word: &Word) -> bool {
+        self.set.is_match(word.as_bytes())
+    }
+}
+
+/// Patterns are the same if they're made from the same regexes.
+impl PartialEq for Patterns {
+    fn eq(&self, other: &Patterns) -> bool {
+        self.sources == other.sources
+    }
+}
+
+/// A sed-like substitution, like s/(\w+)@.*/\1/g.
+#[derive(Debug, Clone)]
+pub struct Substitution {
+    /// The substitution as it was written.
+    pub expression: String,
+    regex: Regex,
+    /// The replacement, in the regex crate's syntax.
+    replacement: Vec<u8>,
+    /// Whether every match is replaced rather than only the first.
+    global: bool,
+}
+
+impl Substitution {
+    /// Parses a substitution written like sed's s command: s, a delimiter, the regex, the delimiter, the replacement,
+    /// the delimiter and then any flags. Any character can be the delimiter, and a backslash before it makes it
+    /// part of the regex or replacement. In the replacement, & is the whole match and \1 to \9 are capture groups,
+    /// as well as the regex crate's ${1} and ${name}. Any other $ is a literal $, as it is in sed, and so is \$.
+    /// The flags are g to replace every match instead of the first, and i to ignore case.
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - The substitution, like s/^(\d+)(\w+)$/\2\1/.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let substitution = Substitution::parse(r"s/(\w+)@.*/\1/").unwrap();
+    ///
+    /// assert_eq!(substitution.apply(Word::from("admin@example.com")), "admin");
+    /// ```
+    pub fn parse(expression: &str) -> Result<Substitution, String> {
+        let mut chars = expression.chars();
+        let delimiter = match (chars.next(), chars.next()) {
+            (Some('s'), Some(delimiter)) if delimiter != '\\' && !delimiter.is_alphanumeric() => delimiter,
+            _ => {
+                return Err(format!(
+                    "substitution \"{}\" should look like s/pattern/replacement/",
+                    expression
+                ))
+            }
+        };
+
+        let mut parts = vec![String::new()];
+        let mut escaped = false;
+        for c in chars {
+            let count = parts.len();
+            let part = parts.last_mut().unwrap();
+            if escaped {
+                if c != delimiter {
+                    part.push('\\');
+                }
+                part.push(c);
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == delimiter && count < 3 {
+                parts.push(String::new());
+            } else {
+                part.push(c);
+            }
+        }
+        if escaped {
+            parts.last_mut().unwrap().push('\\');
+        }
+
+        if parts.len() < 3 {
+            return Err(format!(
+                "substitution \"{}\" is missing its closing {}",
+                expression, delimiter
+            ));
+        }
+        let (pattern, replacement, flags) = (&parts[0], &parts[1], &parts[2]);
+
+        let mut global = false;
+        let mut builder = RegexBuilder::new(pattern);
+        for flag in flags.chars() {
+            match flag {
+                'g' => global = true,
+                'i' => {
+                    builder.case_insensitive(true);
+                }
+                flag => {
+                    return Err(format!(
+                        "unknown flag \"{}\" in substitution \"{}\", expected g or i",
+                        flag, expression
+                    ))
+                }
+            }
+        }
+        let regex = builder.build().map_err(|why| invalid_regex(pattern, why))?;
+
+        let replacement = SED_REPLACEMENT.replace_all(replacement, |captures: &regex::Captures| {
+            match captures.get(1).map(|escaped| escaped.as_str()) {
+                None if &captures[0] == "&" => String::from("${0}"),
+                None if &captures[0] == "$" => String::from("$$"),
+                None => String::from(&captures[0]),
+                Some("&") => String::from("&"),
+                Some("$") => String::from("$$"),
+                Some("\\") => String::from("\\"),
+                Some("n") => String::from("\n"),
+                Some(digit) => format!("${{{}}}", digit),
+            }
+        });
+
+        Ok(Substitution {
+            expression: String::from(expression),
+            regex,
+            replacement: replacement.into_owned().into_bytes(),
+            global,
+        })
+    }
+
+    /// Returns the word with the first match of the regex replaced, or every match with the g flag.
+    pub fn apply(&self, word: Word) -> Word {
+        let replaced = if self.global {
+            self.regex.replace_all(word.as_bytes(), &self.replacement[..])
+        } else {
+            self.regex.replace(word.as_bytes(), &self.replacement[..])
+        };
+        Word::from(replaced.into_owned())
+    }
+}
+
+/// Substitutions are the same if they're written the same.
+impl PartialEq for Substitution {
+    fn eq(&self, other: &Substitution) -> bool {
+        self.expression == other.expression
+    }
+}
+
+impl fmt::Display for Substitution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patterns() {
+        let patterns = Patterns::new(&[String::from(r"^\d+$"), String::from("@")]).unwrap();
+
+        assert!(patterns.is_match(&Word::from("123456")));
+        assert!(patterns.is_match(&Word::from("admin@example.com")));
+        assert!(!patterns.is_match(&Word::from("password1")));
+        assert!(!patterns.is_match(&Word::new(b"\xff\xfe".to_vec())));
+
+        assert_eq!(
+            Patterns::new(&[String::from("a"), String::from("(b")]).unwrap_err(),
+            "invalid regex \"(b\": unclosed group"
+        );
+    }
+
+    #[test]
+    fn test_substitution() {
+        let apply = |expression: &str, word: &str| Substitution::parse(expression).unwrap().apply(Word::from(word));
+
+        assert_eq!(apply(r"s/(\w+)@.*/\1/", "admin@example.com"), "admin");
+        assert_eq!(apply("s/a/4/", "banana"), "b4nana");
+        assert_eq!(apply("s/a/4/g", "banana"), "b4n4n4");
+        assert_eq!(apply("s/A/4/gi", "bAnana"), "b4n4n4");
+        assert_eq!(apply(r"s/^(\d+)(\D+)$/\2\1/", "123abc"), "abc123");
+        assert_eq!(apply("s/[0-9]+/<&>/", "abc123"), "abc<123>");
+        assert_eq!(apply(r"s/b/\&\\/", "abc"), "a&\\c");
+        assert_eq!(apply("s/(?P<year>\\d{4})/${year}!/", "summer2024"), "summer2024!");
+        assert_eq!(apply("s/(a)/${1}${1}/", "pass"), "paass");
+        assert_eq!(apply("s/^/$up/", "pass"), "$uppass");
+        assert_eq!(apply(r"s/a/\$x/", "pass"), "p$xss");
+        assert_eq!(apply("s/$/$$/", "pass"), "pass$$");
+        assert_eq!(apply("s/s$/$1/", "pass"), "pas$1");
+        assert_eq!(apply(r"s|/|\||g", "a/b/c"), "a|b|c");
+        assert_eq!(apply(r"s/\//-/g", "a/b"), "a-b");
+        assert_eq!(apply("s/x/y/", "abc"), "abc");
+
+        let latin1 = Substitution::parse("s/^P/p/").unwrap().apply(Word::new(b"Passw\xf6rd".to_vec()));
+        assert_eq!(latin1, Word::new(b"passw\xf6rd".to_vec()));
+    }
+
+    #[test]
+    fn test_substitution_errors() {
+        assert_eq!(
+            Substitution::parse("y/a/b/").unwrap_err(),
+            "substitution \"y/a/b/\" should look like s/pattern/replacement/"
+        );
+        assert_eq!(
+            Substitution::parse("s/a/b").unwrap_err(),
+            "substitution \"s/a/b\" is missing its closing /"
+        );
+        assert_eq!(
+            Substitution::parse("s/a/b/x").unwrap_err(),
+            "unknown flag \"x\" in substitution \"s/a/b/x\", expected g or i"
+        );
+        assert_eq!(
+            Substitution::parse("s/a[/b/").unwrap_err(),
+            "invalid regex \"a[\": unclosed character class"
+        );
+    }
+}
@@ -0,0 +1,1712 @@
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::string::String;
+use std::thread;
+use std::vec::Vec;
+
+use crate::classify::{self, Class, ClassRoute};
+use crate::external::{self, Pick};
+use crate::file_system::WriteOptions;
+use crate::leet::{LeetMode, Leetspeak};
+use crate::parallel;
+use crate::patterns::{Patterns, Substitution};
+use crate::processors;
+use crate::processors::{
+    DedupMode, LengthUnit, NormalForm, Normalization, Policy, Spelling, WordIterator,
+};
+use crate::rules::{Dialect, Rules, Variants};
+use crate::template::{Template, TemplateMode};
+use crate::word::Word;
+
+/// A stream of words being passed through a pipeline.
+pub type WordStream<'a> = Box<dyn Iterator<Item = Word> + 'a>;
+
+/// A stream of words along with how many times each one was seen, see stream_counted.
+pub type CountedStream<'a> = Box<dyn Iterator<Item = (Word, u64)> + 'a>;
+
+/// Settings that apply to every stage of a pipeline, rather than being part of the pipeline itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    /// Roughly how many bytes of words stages that remember words may hold before spilling to disk.
+    /// When this is None, they hold everything in memory.
+    pub memory_limit: Option<usize>,
+    /// Where temporary files are created when spilling to disk.
+    pub temp_dir: PathBuf,
+    /// How many threads to spread the work over. With one thread everything runs on the main thread.
+    pub threads: usize,
+    /// Whether words must come out in the same order no matter how many threads are used.
+    pub deterministic: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            memory_limit: None,
+            temp_dir: env::temp_dir(),
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            deterministic: false,
+        }
+    }
+}
+
+/// The stages a pipeline can contain, as (long name, short name). Stages without a short name can only be written
+/// out in full.
+/// Long names match the function in processors.rs that the stage runs.
+const STAGE_NAMES: &[(&str, Option<char>)] = &[
+    ("trim_whitespaces", Some('t')),
+    ("remove_counts", Some('c')),
+    ("deduplicate", Some('d')),
+    ("deduplicate_caseless", None),
+    ("remove_outside_lengths", Some('l')),
+    ("remove_contains_symbols", Some('a')),
+    ("remove_lacking_symbols", Some('s')),
+    ("require_policy", Some('y')),
+    ("lowercase", None),
+    ("uppercase", None),
+    ("title_case", None),
+    ("toggle_case", None),
+    ("permute_case", None),
+    ("normalize", None),
+    ("keep_matching", Some('g')),
+    ("remove_matching", Some('v')),
+    ("replace", Some('e')),
+    ("keep_classes", None),
+    ("remove_classes", None),
+    ("route_classes", None),
+    ("prefix", Some('p')),
+    ("template", None),
+    ("apply_rules", Some('h')),
+    ("apply_john_rules", Some('j')),
+    ("leetspeak", Some('k')),
+];
+
+/// A single literal argument given to a stage.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Argument {
+    Number(usize),
+    Text(String),
+}
+
+impl fmt::Display for Argument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Argument::Number(n) => write!(f, "{}", n),
+            Argument::Text(s) => write!(f, "{:?}", s),
+        }
+    }
+}
+
+/// One step of a pipeline. Every stage maps to a function in processors.rs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stage {
+    TrimWhitespaces,
+    RemoveCounts,
+    Deduplicate(DedupMode, Option<Normalization>),
+    DeduplicateCaseless,
+    RemoveOutsideLengths(usize, usize, LengthUnit),
+    RemoveContainsSymbols,
+    RemoveLackingSymbols,
+    RequirePolicy(Policy),
+    Lowercase,
+    Uppercase,
+    TitleCase,
+    ToggleCase(usize),
+    PermuteCase(Option<usize>),
+    Normalize(NormalForm),
+    KeepMatching(Patterns),
+    RemoveMatching(Patterns),
+    Replace(Substitution),
+    KeepClasses(Vec<Class>),
+    RemoveClasses(Vec<Class>),
+    RouteClasses(ClassRoute),
+    Prefix(String),
+    Template(Template),
+    ApplyRules(Rules),
+    Leetspeak(Leetspeak),
+}
+
+impl Stage {
+    /// Builds a stage from its name (long or short) and its arguments.
+    /// Returns a human readable message if the name is unknown or the arguments don't fit.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The long or single character name of the stage.
+    /// * `args` - The arguments given to the stage, in order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let stage = Stage::from_parts("l", &[Argument::Number(10), Argument::Number(20)]);
+    ///
+    /// assert_eq!(stage, Ok(Stage::RemoveOutsideLengths(10, 20, LengthUnit::Bytes)));
+    /// ```
+    pub fn from_parts(name: &str, args: &[Argument]) -> Result<Stage, String> {
+        let long = match resolve_name(name) {
+            None => return Err(format!("unknown stage \"{}\"", name)),
+            Some(long) => long,
+        };
+
+        match (long, args) {
+            ("trim_whitespaces", []) => Ok(Stage::TrimWhitespaces),
+            ("remove_counts", []) => Ok(Stage::RemoveCounts),
+            ("deduplicate", []) => Ok(Stage::Deduplicate(DedupMode::First, None)),
+            ("deduplicate", [Argument::Text(mode), rest @ ..]) if rest.len() <= 2 => {
+                let mode = match DedupMode::from_name(mode) {
+                    None => {
+                        return Err(format!(
+                            "unknown deduplicate mode \"{}\", expected first, last, sorted or frequency",
+                            mode
+                        ))
+                    }
+                    Some(mode) => mode,
+                };
+                let normalization = match rest {
+                    [] => None,
+                    [Argument::Text(form), spelling @ ..] => {
+                        let form = NormalForm::from_name(form).ok_or_else(|| unknown_normal_form(form))?;
+                        let spelling = match spelling {
+                            [] => Spelling::Original,
+                            [Argument::Text(spelling)] => Spelling::from_name(spelling).ok_or_else(|| {
+                                format!(
+                                    "unknown deduplicate spelling \"{}\", expected original or normalized",
+                                    spelling
+                                )
+                            })?,
+                            _ => return Err(format!("{} expects {}", long, expected_arguments(long))),
+                        };
+                        Some(Normalization { form, spelling })
+                    }
+                    _ => return Err(format!("{} expects {}", long, expected_arguments(long))),
+                };
+                Ok(Stage::Deduplicate(mode, normalization))
+            }
+            ("deduplicate_caseless", []) => Ok(Stage::DeduplicateCaseless),
+            ("normalize", [Argument::Text(form)]) => match NormalForm::from_name(form) {
+                None => Err(unknown_normal_form(form)),
+                Some(form) => Ok(Stage::Normalize(form)),
+            },
+            ("lowercase", []) => Ok(Stage::Lowercase),
+            ("uppercase", []) => Ok(Stage::Uppercase),
+            ("title_case", []) => Ok(Stage::TitleCase),
+            ("toggle_case", [Argument::Number(position)]) => Ok(Stage::ToggleCase(*position)),
+            ("permute_case", []) => Ok(Stage::PermuteCase(None)),
+            ("permute_case", [Argument::Number(0)]) => {
+                Err(format!("{} needs to make at least 1 variant of each word", long))
+            }
+            ("permute_case", [Argument::Number(max)]) => Ok(Stage::PermuteCase(Some(*max))),
+            ("remove_outside_lengths", [Argument::Number(min), Argument::Number(max), rest @ ..]) if rest.len() <= 1 => {
+                let unit = match rest {
+                    [] => LengthUnit::Bytes,
+                    [Argument::Text(unit)] => match LengthUnit::from_name(unit) {
+                        None => {
+                            return Err(format!(
+                                "unknown length unit \"{}\", expected bytes, chars, graphemes or width",
+                                unit
+                            ))
+                        }
+                        Some(unit) => unit,
+                    },
+                    _ => return Err(format!("{} expects {}", long, expected_arguments(long))),
+                };
+
+                if min > max {
+                    Err(format!(
+                        "{} needs a minimum that isn't larger than the maximum, got {}:{}",
+                        long, min, max
+                    ))
+                } else {
+                    Ok(Stage::RemoveOutsideLengths(*min, *max, unit))
+                }
+            }
+            ("remove_contains_symbols", []) => Ok(Stage::RemoveContainsSymbols),
+            ("remove_lacking_symbols", []) => Ok(Stage::RemoveLackingSymbols),
+            ("require_policy", [Argument::Number(min_length), rest @ ..]) if rest.len() <= 2 => {
+                let numbers: Option<Vec<usize>> = rest
+                    .iter()
+                    .map(|arg| match arg {
+                        Argument::Number(n) => Some(*n),
+                        Argument::Text(_) => None,
+                    })
+                    .collect();
+                let policy = match numbers.as_deref() {
+                    Some(&[]) => Policy { min_length: *min_length, min_classes: 0, max_repeats: None },
+                    Some(&[classes]) => Policy { min_length: *min_length, min_classes: classes, max_repeats: None },
+                    Some(&[classes, repeats]) => Policy {
+                        min_length: *min_length,
+                        min_classes: classes,
+                        max_repeats: Some(repeats),
+                    },
+                    _ => return Err(format!("{} expects {}", long, expected_arguments(long))),
+                };
+
+                if policy.min_classes > 4 {
+                    Err(format!(
+                        "{} needs at most 4 character classes, got {}",
+                        long, policy.min_classes
+                    ))
+                } else if policy.max_repeats == Some(0) {
+                    Err(format!("{} needs characters to be allowed at least once in a row", long))
+                } else {
+                    Ok(Stage::RequirePolicy(policy))
+                }
+            }
+            ("keep_matching", args) | ("remove_matching", args) if !args.is_empty() => {
+                let sources: Option<Vec<String>> = args
+                    .iter()
+                    .map(|arg| match arg {
+                        Argument::Text(source) => Some(source.clone()),
+                        Argument::Number(_) => None,
+                    })
+                    .collect();
+                let patterns = match sources {
+                    None => return Err(format!("{} expects {}", long, expected_arguments(long))),
+                    Some(sources) => Patterns::new(&sources)?,
+                };
+
+                if long == "keep_matching" {
+                    Ok(Stage::KeepMatching(patterns))
+                } else {
+                    Ok(Stage::RemoveMatching(patterns))
+                }
+            }
+            ("replace", [Argument::Text(expression)]) => Substitution::parse(expression).map(Stage::Replace),
+            ("keep_classes", args) | ("remove_classes", args) | ("route_classes", args) if !args.is_empty() => {
+                let texts: Option<Vec<&str>> = args
+                    .iter()
+                    .map(|arg| match arg {
+                        Argument::Text(text) => Some(text.as_str()),
+                        Argument::Number(_) => None,
+                    })
+                    .collect();
+                let texts = match texts {
+                    Some(texts) if long != "route_classes" || texts.len() >= 2 => texts,
+                    _ => return Err(format!("{} expects {}", long, expected_arguments(long))),
+                };
+
+                match long {
+                    "keep_classes" => classify::parse_classes(&texts).map(Stage::KeepClasses),
+                    "remove_classes" => classify::parse_classes(&texts).map(Stage::RemoveClasses),
+                    _ => {
+                        let classes = classify::parse_classes(&texts[1..])?;
+                        Ok(Stage::RouteClasses(ClassRoute::new(Path::new(texts[0]), classes)))
+                    }
+                }
+            }
+            ("prefix", [Argument::Text(prefix)]) => Ok(Stage::Prefix(prefix.clone())),
+            ("template", [Argument::Text(text), rest @ ..]) => {
+                let texts: Option<Vec<&str>> = rest
+                    .iter()
+                    .map(|arg| match arg {
+                        Argument::Text(text) => Some(text.as_str()),
+                        Argument::Number(_) => None,
+                    })
+                    .collect();
+                let (mode, lists) = match texts.as_deref() {
+                    None => return Err(format!("{} expects {}", long, expected_arguments(long))),
+                    // Lists come in pairs of a name and a file, so an odd one out is the mode.
+                    Some(texts) if texts.len() % 2 == 1 => (texts[0], &texts[1..]),
+                    Some(texts) => ("full", texts),
+                };
+
+                let mode = match TemplateMode::from_name(mode) {
+                    None => {
+                        return Err(format!(
+                            "unknown template mode \"{}\", expected full, prefix, suffix or wrap",
+                            mode
+                        ))
+                    }
+                    Some(mode) => mode,
+                };
+                let lists = lists
+                    .chunks(2)
+                    .map(|pair| (String::from(pair[0]), PathBuf::from(pair[1])))
+                    .collect();
+                Template::load(text, mode, lists).map(Stage::Template)
+            }
+            ("apply_rules", [Argument::Text(path)]) => {
+                Rules::load(Path::new(path), Variants::All).map(Stage::ApplyRules)
+            }
+            ("apply_rules", [Argument::Text(path), Argument::Text(variants)]) => {
+                match Variants::from_name(variants) {
+                    None => Err(format!(
+                        "unknown apply_rules mode \"{}\", expected all or unique",
+                        variants
+                    )),
+                    Some(variants) => Rules::load(Path::new(path), variants).map(Stage::ApplyRules),
+                }
+            }
+            ("apply_john_rules", [Argument::Text(path), rest @ ..]) if rest.len() <= 2 => {
+                let (section, variants) = match rest {
+                    [] => (None, Variants::All),
+                    [Argument::Text(last)] => match Variants::from_name(last) {
+                        None => (Some(last.as_str()), Variants::All),
+                        Some(variants) => (None, variants),
+                    },
+                    [Argument::Text(section), Argument::Text(variants)] => {
+                        match Variants::from_name(variants) {
+                            None => {
+                                return Err(format!(
+                                    "unknown apply_john_rules mode \"{}\", expected all or unique",
+                                    variants
+                                ))
+                            }
+                            Some(variants) => (Some(section.as_str()), variants),
+                        }
+                    }
+                    _ => return Err(format!("{} expects {}", long, expected_arguments(long))),
+                };
+
+                Rules::load_john(Path::new(path), section, variants).map(Stage::ApplyRules)
+            }
+            ("leetspeak", [Argument::Text(path), rest @ ..]) if rest.len() <= 2 => {
+                let (mode, max_variants) = match rest {
+                    [] => ("all", None),
+                    [Argument::Text(mode)] => (mode.as_str(), None),
+                    [Argument::Text(mode), Argument::Number(max)] => (mode.as_str(), Some(*max)),
+                    _ => return Err(format!("{} expects {}", long, expected_arguments(long))),
+                };
+
+                if max_variants == Some(0) {
+                    return Err(format!("{} needs to make at least 1 variant of each word", long));
+                }
+                match LeetMode::from_name(mode) {
+                    None => Err(format!("unknown leetspeak mode \"{}\", expected all or single", mode)),
+                    Some(mode) => Leetspeak::load(Path::new(path), mode, max_variants).map(Stage::Leetspeak),
+                }
+            }
+            (long, _) => Err(format!("{} expects {}", long, expected_arguments(long))),
+        }
+    }
+
+    /// Runs the processor this stage stands for.
+    ///
+    /// # Arguments
+    ///
+    /// * `words` - A vector of all the words to process, one word per string.
+    pub fn apply(&self, words: Vec<Word>) -> Vec<Word> {
+        match self {
+            Stage::TrimWhitespaces => processors::trim_whitespaces(words),
+            Stage::RemoveCounts => processors::remove_counts(words),
+            Stage::Deduplicate(mode, None) => processors::deduplicate_with(words, *mode),
+            Stage::Deduplicate(mode, Some(normalization)) => {
+                processors::deduplicate_normalized(words, *mode, *normalization)
+            }
+            Stage::DeduplicateCaseless => processors::deduplicate_caseless(words),
+            Stage::RemoveOutsideLengths(min, max, unit) => {
+                processors::remove_outside_lengths(words, *min, *max, *unit)
+            }
+            Stage::RemoveContainsSymbols => processors::remove_contains_symbols(words),
+            Stage::RemoveLackingSymbols => processors::remove_lacking_symbols(words),
+            Stage::RequirePolicy(policy) => processors::require_policy(words, policy),
+            Stage::Lowercase => processors::lowercase(words),
+            Stage::Uppercase => processors::uppercase(words),
+            Stage::TitleCase => processors::title_case(words),
+            Stage::ToggleCase(position) => processors::toggle_case(words, *position),
+            Stage::PermuteCase(max) => processors::permute_case(words, *max),
+            Stage::Normalize(form) => processors::normalize(words, *form),
+            Stage::KeepMatching(patterns) => processors::keep_matching(words, patterns),
+            Stage::RemoveMatching(patterns) => processors::remove_matching(words, patterns),
+            Stage::Replace(substitution) => processors::replace(words, substitution),
+            Stage::KeepClasses(classes) => processors::keep_classes(words, classes),
+            Stage::RemoveClasses(classes) => processors::remove_classes(words, classes),
+            Stage::RouteClasses(route) => processors::route_classes(words, route),
+            Stage::Prefix(prefix) => processors::prefix(prefix, words),
+            Stage::Template(template) => processors::template(template, words),
+            Stage::ApplyRules(rules) => processors::apply_rules(rules, words),
+            Stage::Leetspeak(leetspeak) => processors::leetspeak(leetspeak, words),
+        }
+    }
+
+    /// Returns true if the stage looks at each word on its own, so words can be processed in any order.
+    pub fn is_per_word(&self) -> bool {
+        match self {
+            Stage::Deduplicate(_, _) | Stage::DeduplicateCaseless => false,
+            // Routed words are written in the order they come, so the file is the same on every run.
+            Stage::RouteClasses(_) => false,
+            // The index of each word depends on the words before it.
+            Stage::Template(template) => !template.uses_index(),
+            _ => true,
+        }
+    }
+
+    /// Returns true if the stage can turn one word into several, so it has to be run with expand_word.
+    pub fn expands(&self) -> bool {
+        matches!(
+            self,
+            Stage::ApplyRules(_) | Stage::Leetspeak(_) | Stage::PermuteCase(_) | Stage::Template(_)
+        )
+    }
+
+    /// The most words the stage can turn one word into.
+    pub fn expansion(&self) -> usize {
+        match self {
+            Stage::ApplyRules(rules) => rules.count(),
+            Stage::Leetspeak(leetspeak) => leetspeak.expansion(),
+            Stage::Template(template) => template.expansion(),
+            Stage::PermuteCase(max) => max.unwrap_or(processors::DEFAULT_MAX_PERMUTATIONS),
+            _ => 1,
+        }
+    }
+
+    /// Runs this stage on a single word, returning every word that comes out of it.
+    /// Panics if the stage doesn't work one word at a time, see is_per_word.
+    ///
+    /// # Arguments
+    ///
+    /// * `word` - The word to process.
+    pub fn expand_word(&self, word: Word) -> Vec<Word> {
+        match self {
+            Stage::ApplyRules(rules) => rules.apply(&word),
+            Stage::Leetspeak(leetspeak) => leetspeak.apply(&word),
+            Stage::PermuteCase(max) => processors::case_permutations(&word, *max),
+            Stage::Template(template) if template.uses_index() => {
+                panic!("{} can't be run one word at a time", self)
+            }
+            Stage::Template(template) => template.render(&word, 0),
+            stage => stage.process_word(word).into_iter().collect(),
+        }
+    }
+
+    /// Runs this stage on a single word, returning None if the word is removed.
+    /// Panics if the stage doesn't work one word at a time, see is_per_word, or can turn a word into several,
+    /// see expands.
+    ///
+    /// # Arguments
+    ///
+    /// * `word` - The word to process.
+    pub fn process_word(&self, word: Word) -> Option<Word> {
+        match self {
+            Stage::TrimWhitespaces => Some(processors::trim_whitespace(word)),
+            Stage::RemoveCounts => Some(processors::remove_count(word)),
+            Stage::RemoveOutsideLengths(min, max, unit) => {
+                Some(word).filter(|w| processors::is_within_lengths(w, *min, *max, *unit))
+            }
+            Stage::RemoveContainsSymbols => Some(word).filter(processors::is_letters),
+            Stage::RemoveLackingSymbols => Some(word).filter(|w| !processors::is_letters(w)),
+            Stage::RequirePolicy(policy) => Some(word).filter(|w| processors::meets_policy(w, policy)),
+            Stage::Lowercase => Some(processors::lowercase_word(word)),
+            Stage::Uppercase => Some(processors::uppercase_word(word)),
+            Stage::TitleCase => Some(processors::title_case_word(word)),
+            Stage::ToggleCase(position) => Some(processors::toggle_case_at(word, *position)),
+            Stage::Normalize(form) => Some(processors::normalize_word(word, *form)),
+            Stage::KeepMatching(patterns) => Some(word).filter(|w| patterns.is_match(w)),
+            Stage::RemoveMatching(patterns) => Some(word).filter(|w| !patterns.is_match(w)),
+            Stage::Replace(substitution) => Some(substitution.apply(word)),
+            Stage::KeepClasses(classes) => Some(word).filter(|w| classes.contains(&classify::classify(w))),
+            Stage::RemoveClasses(classes) => Some(word).filter(|w| !classes.contains(&classify::classify(w))),
+            Stage::RouteClasses(route) => route.route(word),
+            Stage::Prefix(prefix) => Some(processors::add_prefix(prefix, word)),
+            Stage::Deduplicate(_, _) | Stage::DeduplicateCaseless => {
+                panic!("{} can't be run one word at a time", self)
+            }
+            Stage::ApplyRules(_) | Stage::Leetspeak(_) | Stage::PermuteCase(_) | Stage::Template(_) => {
+                panic!("{} can turn a word into several, use expand_word", self)
+            }
+        }
+    }
+
+    /// Adds the processor this stage stands for to the end of a stream of words.
+    ///
+    /// # Arguments
+    ///
+    /// * `words` - The stream of words to process.
+    /// * `settings` - Settings shared by every stage.
+    pub fn stream<'a>(&'a self, words: WordStream<'a>, settings: &Settings) -> WordStream<'a> {
+        match self {
+            Stage::TrimWhitespaces => Box::new(words.trim_whitespaces()),
+            Stage::RemoveCounts => Box::new(words.remove_counts()),
+            Stage::Deduplicate(mode, None) => deduplicate(words, *mode, settings),
+            Stage::Deduplicate(mode, Some(normalization)) => {
+                let form = normalization.form;
+                match normalization.spelling {
+                    Spelling::Normalized => deduplicate(Box::new(words.normalize(form)), *mode, settings),
+                    // The spellings are kept along with the normal forms, even in the runs spilled past the limit.
+                    Spelling::Original => external::deduplicate_grouped(
+                        words,
+                        move |w: &Word| processors::normalize_word(w.clone(), form),
+                        *mode,
+                        Pick::First,
+                        settings.memory_limit.unwrap_or(usize::MAX),
+                        &settings.temp_dir,
+                    ),
+                }
+            }
+            Stage::DeduplicateCaseless => external::deduplicate_grouped(
+                words,
+                |w: &Word| processors::lowercase_word(w.clone()),
+                DedupMode::First,
+                Pick::MostCommon,
+                settings.memory_limit.unwrap_or(usize::MAX),
+                &settings.temp_dir,
+            ),
+            Stage::RemoveOutsideLengths(min, max, unit) => {
+                Box::new(words.remove_outside_lengths(*min, *max, *unit))
+            }
+            Stage::RemoveContainsSymbols => Box::new(words.remove_contains_symbols()),
+            Stage::RemoveLackingSymbols => Box::new(words.remove_lacking_symbols()),
+            Stage::RequirePolicy(policy) => Box::new(words.require_policy(*policy)),
+            Stage::Lowercase => Box::new(words.lowercase()),
+            Stage::Uppercase => Box::new(words.uppercase()),
+            Stage::TitleCase => Box::new(words.title_case()),
+            Stage::ToggleCase(position) => Box::new(words.toggle_case(*position)),
+            Stage::PermuteCase(max) => Box::new(words.permute_case(*max)),
+            Stage::Normalize(form) => Box::new(words.normalize(*form)),
+            Stage::KeepMatching(patterns) => Box::new(words.keep_matching(patterns)),
+            Stage::RemoveMatching(patterns) => Box::new(words.remove_matching(patterns)),
+            Stage::Replace(substitution) => Box::new(words.replace(substitution)),
+            Stage::KeepClasses(classes) => Box::new(words.keep_classes(classes)),
+            Stage::RemoveClasses(classes) => Box::new(words.remove_classes(classes)),
+            Stage::RouteClasses(route) => Box::new(words.route_classes(route)),
+            Stage::Prefix(prefix) => Box::new(words.prefix(prefix)),
+            Stage::Template(template) => Box::new(words.template(template)),
+            Stage::ApplyRules(rules) => Box::new(words.apply_rules(rules)),
+            Stage::Leetspeak(leetspeak) => Box::new(words.leetspeak(leetspeak)),
+        }
+    }
+
+    /// Adds this stage to the end of a stream of counted words. Stages that work one word at a time leave the
+    /// counts alone, words made from another word share its count, and deduplicate adds together the counts of
+    /// every copy of a word.
+    ///
+    /// # Arguments
+    ///
+    /// * `words` - The stream of counted words to process.
+    pub fn stream_counted<'a>(&'a self, words: CountedStream<'a>) -> CountedStream<'a> {
+        match self {
+            Stage::Deduplicate(mode, normalization) => {
+                let (mode, normalization) = (*mode, *normalization);
+                // Nothing is merged until the first word is asked for.
+                Box::new(iter::once(words).flat_map(move |words| match normalization {
+                    None => processors::merge_counts(words, mode),
+                    Some(normalization) => processors::merge_normalized(words, mode, normalization),
+                }))
+            }
+            Stage::DeduplicateCaseless => {
+                Box::new(iter::once(words).flat_map(processors::merge_caseless))
+            }
+            Stage::Template(template) => Box::new(words.enumerate().flat_map(move |(i, (word, count))| {
+                template.render(&word, i).into_iter().map(move |w| (w, count))
+            })),
+            stage if stage.expands() => Box::new(words.flat_map(move |(word, count)| {
+                stage
+                    .expand_word(word)
+                    .into_iter()
+                    .map(move |w| (w, count))
+            })),
+            stage => Box::new(
+                words.filter_map(move |(word, count)| stage.process_word(word).map(|w| (w, count))),
+            ),
+        }
+    }
+}
+
+/// Adds deduplicate to the end of a stream of words, spilling to disk past the memory limit and spreading the
+/// words over the threads when it can.
+fn deduplicate<'a>(words: WordStream<'a>, mode: DedupMode, settings: &Settings) -> WordStream<'a> {
+    match (settings.memory_limit, mode) {
+        (Some(limit), _) => Box::new(external::deduplicate(words, mode, limit, &settings.temp_dir)),
+        (None, DedupMode::First) if settings.threads > 1 => Box::new(parallel::deduplicate(words)),
+        (None, DedupMode::First) => Box::new(words.deduplicate()),
+        (None, DedupMode::Last) => Box::new(words.deduplicate_keep_last()),
+        (None, DedupMode::Sorted) => Box::new(words.deduplicate_sorted()),
+        (None, DedupMode::Frequency) => Box::new(words.deduplicate_by_frequency()),
+    }
+}
+
+/// Passes a stream of words through every stage, in order.
+/// Words are processed one at a time as the returned stream is used. With more than one thread, runs of
+/// stages that look at each word on their own are handed chunks of words to spread over the threads.
+///
+/// # Arguments
+///
+/// * `stages` - The stages to run.
+/// * `words` - The stream of words to process.
+/// * `settings` - Settings shared by every stage.
+pub fn stream<'a>(
+    stages: &'a [Stage],
+    words: WordStream<'a>,
+    settings: &Settings,
+) -> WordStream<'a> {
+    if settings.threads <= 1 {
+        return stages
+            .iter()
+            .fold(words, |words, stage| stage.stream(words, settings));
+    }
+
+    let mut words = words;
+    let mut start = 0;
+    while start < stages.len() {
+        let per_word = stages[start..]
+            .iter()
+            .take_while(|stage| stage.is_per_word())
+            .count();
+
+        if per_word > 0 {
+            words = Box::new(parallel::stages(words, &stages[start..start + per_word]));
+            start += per_word;
+        } else {
+            words = stages[start].stream(words, settings);
+            start += 1;
+        }
+    }
+
+    words
+}
+
+/// Passes a stream of counted words through every stage, in order. This runs on one thread, and deduplicate
+/// holds every unique word in memory no matter the memory limit.
+///
+/// # Arguments
+///
+/// * `stages` - The stages to run.
+/// * `words` - The stream of words to process, along with how many times each one was seen.
+pub fn stream_counted<'a>(stages: &'a [Stage], words: CountedStream<'a>) -> CountedStream<'a> {
+    stages
+        .iter()
+        .fold(words, |words, stage| stage.stream_counted(words))
+}
+
+/// Makes stages that write files of their own, like route_classes, write them the same way as the outputs.
+///
+/// # Arguments
+///
+/// * `stages` - The stages of the pipeline.
+/// * `options` - How the outputs are written.
+pub fn set_write_options(stages: &[Stage], options: &WriteOptions) {
+    for stage in stages {
+        if let Stage::RouteClasses(route) = stage {
+            route.set_write_options(options);
+        }
+    }
+}
+
+/// Stages display as their long form, which parses back into the same stage.
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stage::TrimWhitespaces => write!(f, "trim_whitespaces"),
+            Stage::RemoveCounts => write!(f, "remove_counts"),
+            Stage::Deduplicate(mode, None) => write!(f, "deduplicate({})", mode),
+            Stage::Deduplicate(mode, Some(normalization)) => write!(
+                f,
+                "deduplicate({}, {}, {})",
+                mode, normalization.form, normalization.spelling
+            ),
+            Stage::DeduplicateCaseless => write!(f, "deduplicate_caseless"),
+            Stage::RemoveOutsideLengths(min, max, unit) => {
+                write!(f, "remove_outside_lengths({}, {}, {})", min, max, unit)
+            }
+            Stage::RemoveContainsSymbols => write!(f, "remove_contains_symbols"),
+            Stage::RemoveLackingSymbols => write!(f, "remove_lacking_symbols"),
+            Stage::RequirePolicy(policy) => match policy.max_repeats {
+                None => write!(f, "require_policy({}, {})", policy.min_length, policy.min_classes),
+                Some(repeats) => write!(
+                    f,
+                    "require_policy({}, {}, {})",
+                    policy.min_length, policy.min_classes, repeats
+                ),
+            },
+            Stage::Lowercase => write!(f, "lowercase"),
+            Stage::Uppercase => write!(f, "uppercase"),
+            Stage::TitleCase => write!(f, "title_case"),
+            Stage::ToggleCase(position) => write!(f, "toggle_case({})", position),
+            Stage::PermuteCase(None) => write!(f, "permute_case"),
+            Stage::PermuteCase(Some(max)) => write!(f, "permute_case({})", max),
+            Stage::Normalize(form) => write!(f, "normalize({})", form),
+            Stage::KeepMatching(patterns) | Stage::RemoveMatching(patterns) => {
+                let name = if let Stage::KeepMatching(_) = self { "keep_matching" } else { "remove_matching" };
+                let sources: Vec<String> = patterns
+                    .sources
+                    .iter()
+                    .map(|source| Argument::Text(source.clone()).to_string())
+                    .collect();
+                write!(f, "{}({})", name, sources.join(", "))
+            }
+            Stage::Replace(substitution) => {
+                write!(f, "replace({})", Argument::Text(substitution.to_string()))
+            }
+            Stage::KeepClasses(classes) | Stage::RemoveClasses(classes) => {
+                let name = if let Stage::KeepClasses(_) = self { "keep_classes" } else { "remove_classes" };
+                let names: Vec<String> = classes.iter().map(Class::to_string).collect();
+                write!(f, "{}({})", name, names.join(", "))
+            }
+            Stage::RouteClasses(route) => {
+                let names: Vec<String> = route.classes.iter().map(Class::to_string).collect();
+                write!(
+                    f,
+                    "route_classes({}, {})",
+                    Argument::Text(route.path.display().to_string()),
+                    names.join(", ")
+                )
+            }
+            Stage::Prefix(prefix) => write!(f, "prefix({})", Argument::Text(prefix.clone())),
+            Stage::Template(template) => {
+                write!(f, "template({}, {}", Argument::Text(template.text.clone()), template.mode)?;
+                for (name, path) in &template.lists {
+                    write!(f, ", {}, {}", name, Argument::Text(path.display().to_string()))?;
+                }
+                write!(f, ")")
+            }
+            Stage::ApplyRules(rules) => {
+                let path = Argument::Text(rules.path.display().to_string());
+                match (rules.dialect, &rules.section) {
+                    (Dialect::Hashcat, _) => write!(f, "apply_rules({}, {})", path, rules.variants),
+                    (Dialect::John, None) => write!(f, "apply_john_rules({}, {})", path, rules.variants),
+                    (Dialect::John, Some(section)) => write!(
+                        f,
+                        "apply_john_rules({}, {}, {})",
+                        path,
+                        Argument::Text(section.clone()),
+                        rules.variants
+                    ),
+                }
+            }
+            Stage::Leetspeak(leetspeak) => {
+                let path = Argument::Text(leetspeak.path.display().to_string());
+                match leetspeak.max_variants {
+                    None => write!(f, "leetspeak({}, {})", path, leetspeak.mode),
+                    Some(max) => write!(f, "leetspeak({}, {}, {})", path, leetspeak.mode, max),
+                }
+            }
+        }
+    }
+}
+
+/// Returns the long name of a stage given either its long or short name.
+fn resolve_name(name: &str) -> Option<&'static str> {
+    let mut chars = name.chars();
+    let short = match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    };
+
+    STAGE_NAMES
+        .iter()
+        .find(|(long, s)| *long == name || (s.is_some() && *s == short))
+        .map(|(long, _)| *long)
+}
+
+/// Describes why a normal form wasn't recognized, for the stages that take one.
+fn unknown_normal_form(form: &str) -> String {
+    format!("unknown normal form \"{}\", expected nfc, nfd, nfkc or nfkd", form)
+}
+
+/// Describes the arguments a stage takes, for error messages.
+fn expected_arguments(long: &str) -> &'static str {
+    match long {
+        "remove_outside_lengths" => "a minimum and maximum length and optionally a unit of bytes, chars, graphemes or width, like l10:20 or remove_outside_lengths(8, 64, chars)",
+        "deduplicate" => "nothing, or a mode of first, last, sorted or frequency, optionally followed by a normal form of nfc, nfd, nfkc or nfkd and original or normalized, like deduplicate(sorted) or deduplicate(first, nfc, original)",
+        "require_policy" => "a minimum length in characters, optionally how many of the 4 character classes are needed and optionally the most times a character may repeat, like y8:3:2",
+        "keep_matching" | "remove_matching" => "one or more quoted regexes, like g\"^\\d+$\" or remove_matching(\"@\", \"^http\")",
+        "replace" => "one quoted sed substitution, like e\"s/(\\w+)@.*/\\1/\"",
+        "keep_classes" | "remove_classes" => "one or more of email, url, markup, hash, ip, uuid, combo or plain, like remove_classes(email, url)",
+        "route_classes" => "the path of a file and one or more classes to write to it instead, like route_classes(\"emails.txt\", email)",
+        "prefix" => "one quoted string, like p\"root \"",
+        "template" => "a quoted template, optionally full, prefix, suffix or wrap, and the name and path of each list it uses, like template(\"{word}{year}!\", year, \"years.txt\")",
+        "apply_rules" => "the path of a .rule file and optionally all or unique, like h\"best64.rule\"",
+        "apply_john_rules" => "the path of a rule file, optionally a section and optionally all or unique, like apply_john_rules(\"john.conf\", \"Wordlist\")",
+        "toggle_case" => "the position of the character to toggle, starting from 0, like toggle_case(0)",
+        "permute_case" => "nothing, or the most variants per word, like permute_case(64)",
+        "normalize" => "a normal form of nfc, nfd, nfkc or nfkd, like normalize(nfc)",
+        "leetspeak" => "the path of a substitution table, optionally all or single and optionally the most variants per word, like leetspeak(\"leet.txt\", single, 100)",
+        _ => "no arguments",
+    }
+}
+
+/// An error found while parsing a pipeline, with the location of the offending text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    /// Character offset of the offending text within the pipeline.
+    pub position: usize,
+    /// Number of characters the offending text covers.
+    pub length: usize,
+}
+
+impl ParseError {
+    fn new(message: String, position: usize, length: usize) -> ParseError {
+        ParseError {
+            message,
+            position,
+            length: length.max(1),
+        }
+    }
+
+    /// Returns the error followed by the pipeline with the offending text underlined.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The pipeline text that was parsed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let error = parse("t #").unwrap_err();
+    ///
+    /// assert_eq!(error.annotate("t #"), "expected a stage name, found '#' at column 3\n  t #\n    ^");
+    /// ```
+    pub fn annotate(&self, source: &str) -> String {
+        format!(
+            "{}\n  {}\n  {}{}",
+            self,
+            source,
+            " ".repeat(self.position),
+            "^".repeat(self.length)
+        )
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.position + 1)
+    }
+}
+
+impl Error for ParseError {}
+
+/// Walks the characters of a pipeline, producing stages.
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_separators(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || c == ',' {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Reads a run of characters that may make up a stage name.
+    fn name_run(&mut self) -> String {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_lowercase() || c == '_' {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    /// Parses a number or quoted string. In brackets, a bare word like first is also a string.
+    fn literal(&mut self, bracketed: bool) -> Result<Argument, ParseError> {
+        let start = self.pos;
+
+        match self.peek() {
+            Some(c) if bracketed && (c.is_alphabetic() || c == '_') => {
+                while let Some(c) = self.peek() {
+                    if c.is_alphanumeric() || c == '_' || c == '-' {
+                        self.pos += 1;
+                    } else {
+                        break;
+                    }
+                }
+                Ok(Argument::Text(self.chars[start..self.pos].iter().collect()))
+            }
+            Some(c) if c.is_ascii_digit() => {
+                while let Some(c) = self.peek() {
+                    if c.is_ascii_digit() {
+                        self.pos += 1;
+                    } else {
+                        break;
+                    }
+                }
+                let digits: String = self.chars[start..self.pos].iter().collect();
+                match digits.parse::<usize>() {
+                    Err(_) => Err(ParseError::new(
+                        format!("number {} is too large", digits),
+                        start,
+                        self.pos - start,
+                    )),
+                    Ok(n) => Ok(Argument::Number(n)),
+                }
+            }
+            Some(quote) if quote == '"' || quote == '\'' => {
+                self.pos += 1;
+                let mut text = String::new();
+                loop {
+                    match self.peek() {
+                        None => {
+                            return Err(ParseError::new(
+                                String::from("unterminated string"),
+                                start,
+                                self.pos - start,
+                            ))
+                        }
+                        Some('\\') => {
+                            self.pos += 1;
+                            match self.peek() {
+                                Some('n') => text.push('\n'),
+                                Some('r') => text.push('\r'),
+                                Some('t') => text.push('\t'),
+                                Some(c) if c == quote || c == '\\' => text.push(c),
+                                // Anything else keeps its backslash, so regexes like "\d+" can be written as they are.
+                                Some(c) => {
+                                    text.push('\\');
+                                    text.push(c);
+                                }
+                                None => continue,
+                            }
+                        }
+                        Some(c) if c == quote => {
+                            self.pos += 1;
+                            return Ok(Argument::Text(text));
+                        }
+                        Some(c) => text.push(c),
+                    }
+                    self.pos += 1;
+                }
+            }
+            Some(c) => Err(ParseError::new(
+                format!("expected a number or quoted string, found '{}'", c),
+                start,
+                1,
+            )),
+            None => Err(ParseError::new(
+                String::from("expected a number or quoted string, found the end of the pipeline"),
+                start,
+                1,
+            )),
+        }
+    }
+
+    /// Parses arguments written directly after a stage name, like the 10:20 in l10:20.
+    fn compact_arguments(&mut self) -> Result<Vec<Argument>, ParseError> {
+        let mut args = vec![];
+
+        match self.peek() {
+            Some(c) if c.is_ascii_digit() || c == '"' || c == '\'' => (),
+            _ => return Ok(args),
+        }
+
+        args.push(self.literal(false)?);
+        while self.peek() == Some(':') {
+            self.pos += 1;
+            args.push(self.literal(false)?);
+        }
+
+        Ok(args)
+    }
+
+    /// Parses arguments written in brackets after a stage name, like the (10, 20) in l(10, 20).
+    fn bracketed_arguments(&mut self) -> Result<Vec<Argument>, ParseError> {
+        let open = self.pos;
+        let mut args = vec![];
+        self.pos += 1;
+
+        loop {
+            while let Some(c) = self.peek() {
+                if c.is_whitespace() {
+                    self.pos += 1;
+                } else {
+                    break;
+                }
+            }
+
+            match self.peek() {
+                None => {
+                    return Err(ParseError::new(
+                        String::from("unclosed bracket"),
+                        open,
+                        self.pos - open,
+                    ))
+                }
+                Some(')') if args.is_empty() => {
+                    self.pos += 1;
+                    return Ok(args);
+                }
+                _ => args.push(self.literal(true)?),
+            }
+
+            while let Some(c) = self.peek() {
+                if c.is_whitespace() {
+                    self.pos += 1;
+                } else {
+                    break;
+                }
+            }
+
+            match self.peek() {
+                Some(',') | Some(':') => self.pos += 1,
+                Some(')') => {
+                    self.pos += 1;
+                    return Ok(args);
+                }
+                Some(c) => {
+                    return Err(ParseError::new(
+                        format!("expected ',' or ')', found '{}'", c),
+                        self.pos,
+                        1,
+                    ))
+                }
+                None => {
+                    return Err(ParseError::new(
+                        String::from("unclosed bracket"),
+                        open,
+                        self.pos - open,
+                    ))
+                }
+            }
+        }
+    }
+
+    fn arguments(&mut self) -> Result<Vec<Argument>, ParseError> {
+        if self.peek() == Some('(') {
+            self.bracketed_arguments()
+        } else {
+            self.compact_arguments()
+        }
+    }
+
+    /// Parses the stages starting at the current position.
+    /// A run of letters is one stage if it's a long name, otherwise every letter is a short name.
+    fn stages(&mut self) -> Result<Vec<Stage>, ParseError> {
+        let start = self.pos;
+        let run = self.name_run();
+
+        if run.is_empty() {
+            let found = self.peek().unwrap_or(' ');
+            return Err(ParseError::new(
+                format!("expected a stage name, found '{}'", found),
+                start,
+                1,
+            ));
+        }
+
+        let names: Vec<String> = if run.len() > 1 && resolve_name(&run).is_some() {
+            vec![run.clone()]
+        } else {
+            run.chars().map(String::from).collect()
+        };
+
+        if names.len() > 1 {
+            if let Some(unknown) = names.iter().find(|n| resolve_name(n).is_none()) {
+                return Err(ParseError::new(
+                    format!("unknown stage \"{}\" in \"{}\"", unknown, run),
+                    start,
+                    self.pos - start,
+                ));
+            }
+        }
+
+        let mut stages = vec![];
+        let last = names.len() - 1;
+        for (i, name) in names.iter().enumerate() {
+            let stage_start = if names.len() > 1 { start + i } else { start };
+            let args = if i == last { self.arguments()? } else { vec![] };
+            let length = if i == last { self.pos - stage_start } else { 1 };
+
+            match Stage::from_parts(name, &args) {
+                Err(message) => return Err(ParseError::new(message, stage_start, length)),
+                Ok(stage) => stages.push(stage),
+            }
+        }
+
+        Ok(stages)
+    }
+}
+
+/// Parses a pipeline into the stages it describes, in the order they should run.
+///
+/// Stages are written as their long name (like `remove_outside_lengths(10, 20)`) or their short name
+/// (like `l10:20`). Short stages can be written back to back, so `sl10:20` keeps words containing symbols
+/// that are between 10 and 20 long. Stages may also be separated by whitespace or commas.
+///
+/// # Arguments
+///
+/// * `source` - The pipeline text.
+///
+/// # Example
+///
+/// ```
+/// let stages = parse("tcd l0:50 prefix(\"root \")").unwrap();
+///
+/// assert_eq!(stages.len(), 5);
+/// assert_eq!(stages[3], Stage::RemoveOutsideLengths(0, 50, LengthUnit::Bytes));
+/// ```
+pub fn parse(source: &str) -> Result<Vec<Stage>, ParseError> {
+    let mut parser = Parser {
+        chars: source.chars().collect(),
+        pos: 0,
+    };
+    let mut stages = vec![];
+
+    parser.skip_separators();
+    while parser.peek().is_some() {
+        stages.extend(parser.stages()?);
+        parser.skip_separators();
+    }
+
+    if stages.is_empty() {
+        return Err(ParseError::new(String::from("pipeline is empty"), 0, 1));
+    }
+
+    Ok(stages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_short() {
+        let result = parse("sl10:20").unwrap();
+        let correct = vec![
+            Stage::RemoveLackingSymbols,
+            Stage::RemoveOutsideLengths(10, 20, LengthUnit::Bytes),
+        ];
+
+        assert_eq!(result, correct);
+    }
+
+    #[test]
+    fn test_parse_long() {
+        let result = parse("trim_whitespaces, remove_counts deduplicate remove_outside_lengths(0, 50) prefix(\"root \")").unwrap();
+        let correct = vec![
+            Stage::TrimWhitespaces,
+            Stage::RemoveCounts,
+            Stage::Deduplicate(DedupMode::First, None),
+            Stage::RemoveOutsideLengths(0, 50, LengthUnit::Bytes),
+            Stage::Prefix(String::from("root ")),
+        ];
+
+        assert_eq!(result, correct);
+    }
+
+    #[test]
+    fn test_parse_mixed() {
+        let result = parse("tc deduplicate a p'it\\'s '").unwrap();
+        let correct = vec![
+            Stage::TrimWhitespaces,
+            Stage::RemoveCounts,
+            Stage::Deduplicate(DedupMode::First, None),
+            Stage::RemoveContainsSymbols,
+            Stage::Prefix(String::from("it's ")),
+        ];
+
+        assert_eq!(result, correct);
+    }
+
+    #[test]
+    fn test_parse_deduplicate_modes() {
+        let result = parse("d deduplicate(last) d(sorted) d'first'").unwrap();
+        let correct = vec![
+            Stage::Deduplicate(DedupMode::First, None),
+            Stage::Deduplicate(DedupMode::Last, None),
+            Stage::Deduplicate(DedupMode::Sorted, None),
+            Stage::Deduplicate(DedupMode::First, None),
+        ];
+
+        assert_eq!(result, correct);
+
+        let error = parse("t d(random)").unwrap_err();
+        assert_eq!(
+            error.message,
+            "unknown deduplicate mode \"random\", expected first, last, sorted or frequency"
+        );
+        assert_eq!((error.position, error.length), (2, 9));
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let source = "tcdl0:50asp\"root \" d(last) d(sorted) d(frequency) y8 y8:3:2 \
+            lowercase uppercase title_case toggle_case(2) permute_case permute_case(8) deduplicate_caseless \
+            remove_outside_lengths(8, 64, chars) l(1, 20, graphemes) remove_outside_lengths(0, 10, width) \
+            normalize(nfkc) d(last, nfd) deduplicate(frequency, nfc, normalized)";
+        let stages = parse(source).unwrap();
+        let displayed: Vec<String> = stages.iter().map(Stage::to_string).collect();
+
+        let result = parse(&displayed.join(" ")).unwrap();
+
+        assert_eq!(result, stages);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = parse("tc x").unwrap_err();
+        assert_eq!(error.message, "unknown stage \"x\"");
+        assert_eq!((error.position, error.length), (3, 1));
+
+        let error = parse("trimm").unwrap_err();
+        assert_eq!(error.message, "unknown stage \"r\" in \"trimm\"");
+        assert_eq!((error.position, error.length), (0, 5));
+
+        let error = parse("t l10").unwrap_err();
+        assert_eq!((error.position, error.length), (2, 3));
+
+        let error = parse("t l20:10").unwrap_err();
+        assert_eq!((error.position, error.length), (2, 6));
+
+        let error = parse("lt").unwrap_err();
+        assert_eq!((error.position, error.length), (0, 1));
+
+        let error = parse("p\"root").unwrap_err();
+        assert_eq!(error.message, "unterminated string");
+        assert_eq!((error.position, error.length), (1, 5));
+
+        let error = parse("l(10 20)").unwrap_err();
+        assert_eq!((error.position, error.length), (5, 1));
+
+        let error = parse("l(8, 64, runes)").unwrap_err();
+        assert_eq!(
+            error.message,
+            "unknown length unit \"runes\", expected bytes, chars, graphemes or width"
+        );
+
+        let error = parse("y8:5").unwrap_err();
+        assert_eq!(error.message, "require_policy needs at most 4 character classes, got 5");
+
+        let error = parse("require_policy(8, 3, 0)").unwrap_err();
+        assert_eq!(error.message, "require_policy needs characters to be allowed at least once in a row");
+
+        let error = parse("t#").unwrap_err();
+        assert_eq!((error.position, error.length), (1, 1));
+
+        assert!(parse("  ").is_err());
+    }
+
+    #[test]
+    fn test_annotate() {
+        let source = "tc l5";
+        let error = parse(source).unwrap_err();
+
+        assert_eq!(
+            error.annotate(source),
+            "remove_outside_lengths expects a minimum and maximum length and optionally a unit of bytes, chars, graphemes or width, like l10:20 or remove_outside_lengths(8, 64, chars) at column 4\n  tc l5\n     ^^"
+        );
+    }
+
+    #[test]
+    fn test_apply() {
+        let words: Vec<Word> = vec![" 4 Hello", "Hello", "2 Hi", "Passwords"]
+            .into_iter()
+            .map(Word::from)
+            .collect();
+        let correct: Vec<Word> = vec!["root Hello"].into_iter().map(Word::from).collect();
+
+        let result = parse("tcdl5:5p'root '")
+            .unwrap()
+            .iter()
+            .fold(words, |words, stage| stage.apply(words));
+
+        assert_eq!(result, correct);
+    }
+
+    #[test]
+    fn test_stream() {
+        let words = vec![" 4 Hello", "Hello", "2 Hi", "Passwords", "World"]
+            .into_iter()
+            .map(Word::from);
+        let correct: Vec<Word> = vec!["root Hello", "root World"]
+            .into_iter()
+            .map(Word::from)
+            .collect();
+        let stages = parse("tcdl5:5p'root '").unwrap();
+
+        let single = Settings {
+            threads: 1,
+            ..Settings::default()
+        };
+        let multiple = Settings {
+            threads: 4,
+            ..Settings::default()
+        };
+
+        let result: Vec<Word> = stream(&stages, Box::new(words.clone()), &single).collect();
+        let parallel_result: Vec<Word> = stream(&stages, Box::new(words), &multiple).collect();
+
+        assert_eq!(result, correct);
+        assert_eq!(parallel_result, correct);
+    }
+
+    #[test]
+    fn test_stream_counted() {
+        let words = vec![" 4 Hello", "World\t2", "Hello", "3 Hi", "5 World"]
+            .into_iter()
+            .map(Word::from)
+            .map(processors::split_count);
+        let correct: Vec<(Word, u64)> = vec![("root World", 7), ("root Hello", 5)]
+            .into_iter()
+            .map(|(w, c)| (Word::from(w), c))
+            .collect();
+        let stages = parse("t d(frequency) l5:5 p'root '").unwrap();
+
+        let result: Vec<(Word, u64)> = stream_counted(&stages, Box::new(words)).collect();
+
+        assert_eq!(result, correct);
+    }
+
+    #[test]
+    fn test_regex_stages() {
+        let source = r#"g"^\w+$" remove_matching('^\d+$', "^admin") e"s/(\w+?)(\d+)$/\2\1/""#;
+        let stages = parse(source).unwrap();
+        assert_eq!(
+            stages[0],
+            Stage::KeepMatching(Patterns::new(&[String::from(r"^\w+$")]).unwrap())
+        );
+
+        let displayed: Vec<String> = stages.iter().map(Stage::to_string).collect();
+        assert_eq!(parse(&displayed.join(" ")).unwrap(), stages);
+
+        let words = vec!["summer2024", "123456", "admin1", "pass word", "monkey"]
+            .into_iter()
+            .map(Word::from);
+        let correct: Vec<Word> = vec!["2024summer", "monkey"].into_iter().map(Word::from).collect();
+        let multiple = Settings {
+            threads: 4,
+            ..Settings::default()
+        };
+
+        let result = stages.iter().fold(words.clone().collect(), |words, stage| stage.apply(words));
+        let streamed: Vec<Word> = stream(&stages, Box::new(words), &multiple).collect();
+
+        assert_eq!(result, correct);
+        assert_eq!(streamed, correct);
+
+        let error = parse("g(1)").unwrap_err();
+        assert_eq!(
+            error.message,
+            "keep_matching expects one or more quoted regexes, like g\"^\\d+$\" or remove_matching(\"@\", \"^http\")"
+        );
+        let error = parse("v'(a'").unwrap_err();
+        assert_eq!(error.message, "invalid regex \"(a\": unclosed group");
+    }
+
+    #[test]
+    fn test_class_stages() {
+        let path = PathBuf::from("test_pipeline_route_classes.txt");
+        let source = "remove_classes(markup, hash) route_classes(\"test_pipeline_route_classes.txt\", email, combo) \
+            keep_classes(plain)";
+        let stages = parse(source).unwrap();
+        assert_eq!(stages[0], Stage::RemoveClasses(vec![Class::Markup, Class::Hash]));
+        assert!(!stages[1].is_per_word());
+
+        let displayed: Vec<String> = stages.iter().map(Stage::to_string).collect();
+        assert_eq!(displayed.join(" "), source);
+
+        let words = vec!["hunter2", "admin@example.com", "<br>", "admin1:hunter2", "192.168.0.1", "letmein"]
+            .into_iter()
+            .map(Word::from);
+        let correct: Vec<Word> = vec!["hunter2", "letmein"].into_iter().map(Word::from).collect();
+
+        let result = stages.iter().fold(words.clone().collect(), |words, stage| stage.apply(words));
+        let streamed: Vec<Word> = stream(&stages, Box::new(words), &Settings::default()).collect();
+        // The routed file is finished once the stage is dropped.
+        drop(stages);
+        let routed = std::fs::read_to_string(&path);
+
+        if let Err(why) = std::fs::remove_file(&path) {
+            eprintln!("couldn't remove testing file {}: {}", path.display(), why)
+        }
+
+        assert_eq!(result, correct);
+        assert_eq!(streamed, correct);
+        assert_eq!(routed.unwrap(), "admin@example.com\nadmin1:hunter2\n".repeat(2));
+
+        let error = parse("remove_classes(junk)").unwrap_err();
+        assert_eq!(
+            error.message,
+            "unknown class \"junk\", expected email, url, markup, hash, ip, uuid, combo or plain"
+        );
+        let error = parse("route_classes(\"junk.txt\")").unwrap_err();
+        assert_eq!(
+            error.message,
+            "route_classes expects the path of a file and one or more classes to write to it instead, like route_classes(\"emails.txt\", email)"
+        );
+    }
+
+    #[test]
+    fn test_template() {
+        let path = "test_pipeline_template.txt";
+        std::fs::write(path, "2023\n2024\n").unwrap();
+
+        let source = "template(\"{word}{year}!\", year, \"test_pipeline_template.txt\") \
+            template(\"{index}:\", prefix) template(\"*\", wrap)";
+        let stages = parse(source);
+        let reparsed = stages
+            .as_ref()
+            .ok()
+            .map(|stages| parse(&stages.iter().map(Stage::to_string).collect::<Vec<String>>().join(" ")));
+        let error = parse("template(\"{word}\", sideways)").unwrap_err();
+
+        if let Err(why) = std::fs::remove_file(path) {
+            eprintln!("couldn't remove testing file {}: {}", path, why)
+        }
+
+        let stages = stages.unwrap();
+        assert_eq!(reparsed.unwrap().unwrap(), stages);
+        assert_eq!(
+            stages[0].to_string(),
+            "template(\"{word}{year}!\", full, year, \"test_pipeline_template.txt\")"
+        );
+        assert!(stages[0].is_per_word() && !stages[1].is_per_word());
+
+        let words = vec!["summer", "winter"].into_iter().map(Word::from);
+        let correct: Vec<Word> = vec!["*0:summer2023!*", "*1:summer2024!*", "*2:winter2023!*", "*3:winter2024!*"]
+            .into_iter()
+            .map(Word::from)
+            .collect();
+        let multiple = Settings {
+            threads: 4,
+            ..Settings::default()
+        };
+
+        let result = stages.iter().fold(words.clone().collect(), |words, stage| stage.apply(words));
+        let streamed: Vec<Word> = stream(&stages, Box::new(words.clone()), &multiple).collect();
+        let counted: Vec<(Word, u64)> = stream_counted(&stages, Box::new(words.map(|w| (w, 2)))).collect();
+
+        assert_eq!(result, correct);
+        assert_eq!(streamed, correct);
+        assert_eq!(counted, correct.into_iter().map(|w| (w, 2)).collect::<Vec<(Word, u64)>>());
+        assert_eq!(
+            error.message,
+            "unknown template mode \"sideways\", expected full, prefix, suffix or wrap"
+        );
+    }
+
+    #[test]
+    fn test_normalize_stages() {
+        let stages = parse("deduplicate(first, nfc) normalize(nfd)").unwrap();
+        let displayed: Vec<String> = stages.iter().map(Stage::to_string).collect();
+        assert_eq!(displayed, vec!["deduplicate(first, nfc, original)", "normalize(nfd)"]);
+        assert_eq!(parse(&displayed.join(" ")).unwrap(), stages);
+
+        let words = vec!["caf\u{e9}", "cafe\u{301}", "\u{fb01}ne", "fine"].into_iter().map(Word::from);
+        let correct: Vec<Word> = vec!["cafe\u{301}", "\u{fb01}ne", "fine"].into_iter().map(Word::from).collect();
+        let multiple = Settings {
+            threads: 4,
+            ..Settings::default()
+        };
+        let limited = Settings {
+            memory_limit: Some(1),
+            ..Settings::default()
+        };
+
+        let result = stages.iter().fold(words.clone().collect(), |words, stage| stage.apply(words));
+        let streamed: Vec<Word> = stream(&stages, Box::new(words.clone()), &multiple).collect();
+        let spilled: Vec<Word> = stream(&stages, Box::new(words.clone()), &limited).collect();
+        let counted: Vec<(Word, u64)> = stream_counted(&stages, Box::new(words.map(|w| (w, 1)))).collect();
+
+        assert_eq!(result, correct);
+        assert_eq!(streamed, correct);
+        assert_eq!(spilled, correct);
+        assert_eq!(counted[0], (Word::from("cafe\u{301}"), 2));
+
+        // Every mode works in the normal form, keeping the spelling each word was first seen in.
+        let words = vec!["cafe\u{301}", "b", "caf\u{e9}", "a", "caf\u{e9}"].into_iter().map(Word::from);
+        for (source, correct) in [
+            ("d(last, nfc)", vec!["b", "a", "cafe\u{301}"]),
+            ("d(sorted, nfc)", vec!["a", "b", "cafe\u{301}"]),
+            ("d(frequency, nfc)", vec!["cafe\u{301}", "a", "b"]),
+            ("d(frequency, nfc, normalized)", vec!["caf\u{e9}", "a", "b"]),
+        ] {
+            let stages = parse(source).unwrap();
+            let result = stages.iter().fold(words.clone().collect(), |words, stage| stage.apply(words));
+            let streamed: Vec<Word> = stream(&stages, Box::new(words.clone()), &multiple).collect();
+            let spilled: Vec<Word> = stream(&stages, Box::new(words.clone()), &limited).collect();
+            assert_eq!(result, correct, "{}", source);
+            assert_eq!(streamed, correct, "{}", source);
+            assert_eq!(spilled, correct, "{}", source);
+        }
+
+        let stages = parse("deduplicate(first, nfkc, normalized)").unwrap();
+        let words = vec!["\u{fb01}ne", "fine", "cafe\u{301}"].into_iter().map(Word::from);
+        let result: Vec<Word> = stream(&stages, Box::new(words), &multiple).collect();
+        assert_eq!(result, vec!["fine", "caf\u{e9}"]);
+
+        let error = parse("normalize(nfx)").unwrap_err();
+        assert_eq!(error.message, "unknown normal form \"nfx\", expected nfc, nfd, nfkc or nfkd");
+        let error = parse("deduplicate(first, nfx)").unwrap_err();
+        assert_eq!(error.message, "unknown normal form \"nfx\", expected nfc, nfd, nfkc or nfkd");
+        let error = parse("deduplicate(first, nfc, both)").unwrap_err();
+        assert_eq!(
+            error.message,
+            "unknown deduplicate spelling \"both\", expected original or normalized"
+        );
+    }
+
+    #[test]
+    fn test_case_stages() {
+        let stages = parse("lowercase deduplicate_caseless title_case permute_case(2)").unwrap();
+        let words = vec!["hello world", "Hello World", "ADMIN", "admin"].into_iter().map(Word::from);
+        let correct: Vec<Word> = vec!["hello world", "hello worlD", "admin", "admiN"]
+            .into_iter()
+            .map(Word::from)
+            .collect();
+        let multiple = Settings {
+            threads: 4,
+            ..Settings::default()
+        };
+
+        let limited = Settings {
+            memory_limit: Some(1),
+            ..Settings::default()
+        };
+
+        let result = stages.iter().fold(words.clone().collect(), |words, stage| stage.apply(words));
+        let streamed: Vec<Word> = stream(&stages, Box::new(words.clone()), &multiple).collect();
+        let spilled: Vec<Word> = stream(&stages, Box::new(words), &limited).collect();
+
+        assert_eq!(result, correct);
+        assert_eq!(streamed, correct);
+        assert_eq!(spilled, correct);
+
+        let words = vec![("admin", 2), ("Admin", 5), ("ADMIN", 1)]
+            .into_iter()
+            .map(|(w, c)| (Word::from(w), c));
+        let stages = parse("deduplicate_caseless toggle_case(1)").unwrap();
+        let counted: Vec<(Word, u64)> = stream_counted(&stages, Box::new(words)).collect();
+        assert_eq!(counted, vec![(Word::from("ADmin"), 8)]);
+
+        let error = parse("permute_case(0)").unwrap_err();
+        assert_eq!(error.message, "permute_case needs to make at least 1 variant of each word");
+        let error = parse("toggle_case").unwrap_err();
+        assert_eq!(
+            error.message,
+            "toggle_case expects the position of the character to toggle, starting from 0, like toggle_case(0)"
+        );
+    }
+
+    #[test]
+    fn test_apply_rules() {
+        let path = "test_pipeline_apply_rules.rule";
+        std::fs::write(path, ":\nc $1\nl\n").unwrap();
+
+        let source = format!("h'{}' apply_rules(\"{}\", unique) l5:6", path, path);
+        let stages = parse(&source);
+        let unknown_mode = parse(&format!("apply_rules('{}', some)", path));
+        let missing = parse("h'test_pipeline_missing.rule'");
+
+        if let Err(why) = std::fs::remove_file(path) {
+            eprintln!("couldn't remove testing file {}: {}", path, why)
+        }
+
+        let stages = stages.unwrap();
+        assert_eq!(stages[0].to_string(), format!("apply_rules(\"{}\", all)", path));
+        assert_eq!(stages[1].to_string(), format!("apply_rules(\"{}\", unique)", path));
+        assert_eq!(
+            unknown_mode.unwrap_err().message,
+            "unknown apply_rules mode \"some\", expected all or unique"
+        );
+        assert!(missing.unwrap_err().message.starts_with("couldn't read test_pipeline_missing.rule"));
+
+        let words = vec!["hello", "World"].into_iter().map(Word::from);
+        // hello becomes hello, Hello1 and hello, then each of those becomes its unique variants 5 or 6 long.
+        let correct = vec![
+            "hello", "Hello1", "Hello1", "hello1", "hello", "Hello1", "World", "World1", "world", "World1", "world1",
+            "world", "World1",
+        ];
+        let single = Settings {
+            threads: 1,
+            ..Settings::default()
+        };
+        let multiple = Settings {
+            threads: 4,
+            ..Settings::default()
+        };
+
+        let result: Vec<Word> = stream(&stages, Box::new(words.clone()), &single).collect();
+        let parallel_result: Vec<Word> = stream(&stages, Box::new(words.clone()), &multiple).collect();
+        let applied = stages
+            .iter()
+            .fold(words.collect::<Vec<Word>>(), |words, stage| stage.apply(words));
+
+        assert_eq!(result, correct);
+        assert_eq!(parallel_result, correct);
+        assert_eq!(applied, correct);
+    }
+
+    #[test]
+    fn test_apply_john_rules() {
+        let path = "test_pipeline_apply_john_rules.conf";
+        std::fs::write(path, "[List.Rules:Digits]\n$[0-1]\n").unwrap();
+
+        let source = format!("j'{}' apply_john_rules('{}', unique) apply_john_rules('{}', 'Digits')", path, path, path);
+        let stages = parse(&source);
+        let missing_section = parse(&format!("apply_john_rules('{}', 'Letters')", path));
+
+        if let Err(why) = std::fs::remove_file(path) {
+            eprintln!("couldn't remove testing file {}: {}", path, why)
+        }
+
+        let stages = stages.unwrap();
+        let displayed: Vec<String> = stages.iter().map(Stage::to_string).collect();
+        assert_eq!(
+            displayed,
+            vec![
+                format!("apply_john_rules(\"{}\", all)", path),
+                format!("apply_john_rules(\"{}\", unique)", path),
+                format!("apply_john_rules(\"{}\", \"Digits\", all)", path),
+            ]
+        );
+        assert_eq!(
+            missing_section.unwrap_err().message,
+            format!("{} has no [List.Rules:Letters] section", path)
+        );
+
+        let words: Vec<Word> = vec![Word::from("a")];
+        let result = stages[2].apply(words);
+        assert_eq!(result, vec!["a0", "a1"]);
+    }
+
+    #[test]
+    fn test_leetspeak() {
+        let path = "test_pipeline_leetspeak.txt";
+        std::fs::write(path, "a 4 @\ns 5\n").unwrap();
+
+        let source = format!("k'{}' leetspeak('{}', single, 2)", path, path);
+        let stages = parse(&source);
+        let bad_mode = parse(&format!("leetspeak('{}', every)", path));
+
+        if let Err(why) = std::fs::remove_file(path) {
+            eprintln!("couldn't remove testing file {}: {}", path, why)
+        }
+
+        let stages = stages.unwrap();
+        let displayed: Vec<String> = stages.iter().map(Stage::to_string).collect();
+        assert_eq!(
+            displayed,
+            vec![
+                format!("leetspeak(\"{}\", all)", path),
+                format!("leetspeak(\"{}\", single, 2)", path),
+            ]
+        );
+        assert_eq!(
+            bad_mode.unwrap_err().message,
+            "unknown leetspeak mode \"every\", expected all or single"
+        );
+
+        let words = vec!["as", "b"].into_iter().map(Word::from);
+        let correct: Vec<Word> = vec!["as", "a5", "4s", "45", "@s", "@5", "b"]
+            .into_iter()
+            .map(Word::from)
+            .collect();
+        let multiple = Settings {
+            threads: 4,
+            ..Settings::default()
+        };
+
+        let result = stages[0].apply(words.clone().collect());
+        let streamed: Vec<Word> = stream(&stages[..1], Box::new(words.clone()), &multiple).collect();
+        let counted: Vec<(Word, u64)> =
+            stream_counted(&stages[1..], Box::new(words.map(|w| (w, 3)))).collect();
+
+        assert_eq!(result, correct);
+        assert_eq!(streamed, correct);
+        assert_eq!(counted, vec![(Word::from("as"), 3), (Word::from("4s"), 3), (Word::from("b"), 3)]);
+    }
+}
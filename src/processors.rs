@@ -1,6 +1,21 @@
 use std::string::String;
+use std::vec;
 use std::vec::Vec;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::mem;
+use std::str;
+
+use crate::classify::{classify, Class, ClassRoute};
+use crate::leet::Leetspeak;
+use crate::patterns::{Patterns, Substitution};
+use crate::rules::Rules;
+use crate::template::Template;
+use crate::word::Word;
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 ///  Returns a list of words, in random order, with all duplicates removed.
 ///
@@ -12,22 +27,231 @@ use std::collections::HashSet;
 ///
 /// ```
 /// // Create an input list of words.
-/// let input_words: Vec<String> = vec!["Hello", "World", "World"]
-///     .into_iter().map(String::from).collect();
+/// let input_words: Vec<Word> = vec!["Hello", "World", "World"]
+///     .into_iter().map(Word::from).collect();
 ///
 /// let output_words = asd(input_words);
 /// output_words.sort();
 ///
 /// assert!(output_words.len() == 2);
-/// assert!(output_words[0].as_str() == "Hello");
-/// assert!(output_words[1].as_str() == "World");
+/// assert!(output_words[0] == "Hello");
+/// assert!(output_words[1] == "World");
 /// ```
-pub fn deduplicate(mut words: Vec<String>) -> Vec<String> {
+pub fn deduplicate(mut words: Vec<Word>) -> Vec<Word> {
     let set: HashSet<_> = words.drain(..).collect(); // dedup
     words = set.into_iter().collect();
     words
 }
 
+/// Which copy of a word deduplicating keeps, which decides the order of the output.
+/// Every mode gives the same output every time it's run on the same words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupMode {
+    /// Keep the first copy of each word, so words stay in the order they first appear.
+    First,
+    /// Keep the last copy of each word, so words are in the order they last appear.
+    Last,
+    /// Sort the words by their bytes.
+    Sorted,
+    /// Most common words first, counting every copy of a word. Words that are as common as each other are
+    /// sorted by their bytes.
+    Frequency,
+}
+
+impl DedupMode {
+    /// Returns the mode with the given name (first, last, sorted or frequency).
+    pub fn from_name(name: &str) -> Option<DedupMode> {
+        match name {
+            "first" => Some(DedupMode::First),
+            "last" => Some(DedupMode::Last),
+            "sorted" => Some(DedupMode::Sorted),
+            "frequency" => Some(DedupMode::Frequency),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DedupMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DedupMode::First => write!(f, "first"),
+            DedupMode::Last => write!(f, "last"),
+            DedupMode::Sorted => write!(f, "sorted"),
+            DedupMode::Frequency => write!(f, "frequency"),
+        }
+    }
+}
+
+/// Returns a list of words with all duplicates removed, in an order decided by the mode.
+///
+/// # Arguments
+///
+/// * `words` - A vector of all the words to process, one word per string.
+/// * `mode` - Which copy of each word to keep.
+///
+/// # Example
+///
+/// ```
+/// // Create an input list of words.
+/// let input_words: Vec<Word> = vec!["World", "Hello", "World"]
+///     .into_iter().map(Word::from).collect();
+///
+/// let output_words = deduplicate_with(input_words, DedupMode::Last);
+///
+/// assert!(output_words == vec!["Hello", "World"]);
+/// ```
+pub fn deduplicate_with(words: Vec<Word>, mode: DedupMode) -> Vec<Word> {
+    match mode {
+        DedupMode::First => words.into_iter().deduplicate().collect(),
+        DedupMode::Last => {
+            let mut seen = HashSet::new();
+            let mut kept: Vec<Word> = words
+                .into_iter()
+                .rev()
+                .filter(|w| seen.insert(w.clone()))
+                .collect();
+            kept.reverse();
+            kept
+        }
+        DedupMode::Sorted => {
+            let mut words = deduplicate(words);
+            words.sort_unstable();
+            words
+        }
+        DedupMode::Frequency => merge_counts(words.into_iter().map(|w| (w, 1)), mode)
+            .into_iter()
+            .map(|(word, _)| word)
+            .collect(),
+    }
+}
+
+/// How a word and its count are written out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CountFormat {
+    /// The count, a space, then the word, like `1230 World`.
+    CountWord,
+    /// The word, a tab, then the count, like `World\t1230`.
+    WordTabCount,
+}
+
+impl CountFormat {
+    /// Returns the format with the given name (count-word or word-tab-count).
+    pub fn from_name(name: &str) -> Option<CountFormat> {
+        match name {
+            "count-word" => Some(CountFormat::CountWord),
+            "word-tab-count" => Some(CountFormat::WordTabCount),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CountFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CountFormat::CountWord => write!(f, "count-word"),
+            CountFormat::WordTabCount => write!(f, "word-tab-count"),
+        }
+    }
+}
+
+/// Returns the word along with its count, written in the given format.
+pub fn format_count(word: &Word, count: u64, format: CountFormat) -> Word {
+    let count = count.to_string();
+    let (before, separator, after) = match format {
+        CountFormat::CountWord => (count.as_bytes(), b' ', word.as_bytes()),
+        CountFormat::WordTabCount => (word.as_bytes(), b'\t', count.as_bytes()),
+    };
+
+    let mut bytes = Vec::with_capacity(before.len() + 1 + after.len());
+    bytes.extend_from_slice(before);
+    bytes.push(separator);
+    bytes.extend_from_slice(after);
+    Word::new(bytes)
+}
+
+/// Splits a word into the word and how many times it was seen. Leading counts are removed like remove_count does,
+/// and a count after a tab at the end of the word (the way word-tab-count writes them) is also understood.
+/// Words without a count were seen once.
+///
+/// # Arguments
+///
+/// * `word` - The word to split.
+///
+/// # Example
+///
+/// ```
+/// assert!(split_count(Word::from("1230    World")) == (Word::from("World"), 1230));
+/// assert!(split_count(Word::from("World\t1230")) == (Word::from("World"), 1230));
+/// assert!(split_count(Word::from("World")) == (Word::from("World"), 1));
+/// ```
+pub fn split_count(word: Word) -> (Word, u64) {
+    let trimmed = trim(word.as_bytes());
+
+    let leading = trimmed
+        .iter()
+        .position(|b| *b == b' ')
+        .map(|i| (&trimmed[i + 1..], &trimmed[..i]));
+    let trailing = trimmed
+        .iter()
+        .rposition(|b| *b == b'\t')
+        .map(|i| (&trimmed[..i], &trimmed[i + 1..]));
+    let split = leading
+        .filter(|(_, count)| is_number(*count))
+        .or_else(|| trailing.filter(|(_, count)| is_number(*count)));
+
+    if let Some((rest, count)) = split {
+        let rest = trim(rest);
+        let count = str::from_utf8(count).ok().and_then(|c| c.parse::<u64>().ok());
+        if let (false, Some(count)) = (rest.is_empty(), count) {
+            return (Word::from(rest), count);
+        }
+    }
+
+    (word, 1)
+}
+
+/// Returns a list of counted words with all duplicates removed, in an order decided by the mode.
+/// The counts of every copy of a word are added together.
+///
+/// # Arguments
+///
+/// * `words` - Every word along with how many times it was seen.
+/// * `mode` - Which copy of each word decides where it goes in the output.
+///
+/// # Example
+///
+/// ```
+/// let input_words = vec![("World", 4), ("Hello", 1), ("World", 2)]
+///     .into_iter().map(|(w, c)| (Word::from(w), c));
+///
+/// let output_words = merge_counts(input_words, DedupMode::First);
+///
+/// assert!(output_words == vec![(Word::from("World"), 6), (Word::from("Hello"), 1)]);
+/// ```
+pub fn merge_counts<I: IntoIterator<Item = (Word, u64)>>(words: I, mode: DedupMode) -> Vec<(Word, u64)> {
+    // The position of the copy that's kept and the total count of every copy.
+    let mut merged: HashMap<Word, (usize, u64)> = HashMap::new();
+    for (position, (word, count)) in words.into_iter().enumerate() {
+        let kept = merged.entry(word).or_insert((position, 0));
+        if mode == DedupMode::Last {
+            kept.0 = position;
+        }
+        kept.1 = kept.1.saturating_add(count);
+    }
+
+    let mut merged: Vec<(usize, Word, u64)> = merged
+        .into_iter()
+        .map(|(word, (position, count))| (position, word, count))
+        .collect();
+    match mode {
+        DedupMode::First | DedupMode::Last => merged.sort_unstable_by_key(|(position, _, _)| *position),
+        DedupMode::Sorted => merged.sort_unstable_by(|a, b| a.1.cmp(&b.1)),
+        DedupMode::Frequency => merged.sort_unstable_by(|a, b| b.2.cmp(&a.2).then_with(|| a.1.cmp(&b.1))),
+    }
+
+    merged.into_iter().map(|(_, word, count)| (word, count)).collect()
+}
+
 ///  Returns a list of words, in an order reliant on .filter, with all words where char.is_alphabetic() is true for all characters.
 ///
 /// # Arguments
@@ -38,62 +262,958 @@ pub fn deduplicate(mut words: Vec<String>) -> Vec<String> {
 ///
 /// ```
 /// // Create an input list of words.
-/// let input_words: Vec<String> = vec!["Hello", "World", "Worl!d"]
-///     .into_iter().map(String::from).collect();
+/// let input_words: Vec<Word> = vec!["Hello", "World", "Worl!d"]
+///     .into_iter().map(Word::from).collect();
 ///
 /// let output_words = asd(input_words);
 ///
 /// assert!(output_words.len() == 2);
-/// assert!(output_words[0].as_str() == "Hello");
-/// assert!(output_words[1].as_str() == "World");
-/// ```
-pub fn remove_contains_symbols(words: Vec<String>) -> Vec<String> {
-    fn is_letters(string: &String) -> bool {
-        for char in string.chars() {
-            if !char.is_alphabetic() {
-                return false;
+/// assert!(output_words[0] == "Hello");
+/// assert!(output_words[1] == "World");
+/// ```
+pub fn remove_contains_symbols(words: Vec<Word>) -> Vec<Word> {
+    words.into_iter().filter(is_letters).collect()
+}
+
+/// Returns true if char.is_alphabetic() is true for all characters of the word.
+/// Words that aren't valid UTF-8 don't have characters to check, so they're never all letters.
+pub fn is_letters(word: &Word) -> bool {
+    word.to_str()
+        .is_some_and(|text| text.chars().all(char::is_alphabetic))
+}
+
+/// Returns a list of words, in an order reliant on .filter, with all words where char.is_alphabetic() is false for all characters..
+///
+/// # Arguments
+///
+/// * `words` - A vector of all the words to process, one word per string.
+///
+/// # Example
+///
+/// ```
+/// // Create an input list of words.
+/// let input_words: Vec<Word> = vec!["Hell-o", "Wo@rld", "World"]
+///     .into_iter().map(Word::from).collect();
+///
+/// let output_words = remove_lacking_symbols(input_words);
+///
+/// assert!(output_words.len() == 2);
+/// assert!(output_words[0] == "Hello");
+/// assert!(output_words[1] == "World");
+/// ```
+pub fn remove_lacking_symbols(words: Vec<Word>) -> Vec<Word> {
+    words.into_iter().filter(|w| !is_letters(w)).collect()
+}
+
+/// What the length of a word is counted in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthUnit {
+    Bytes,
+    /// Unicode scalar values, so ä is one written as one character, but two written as a and a combining accent.
+    Chars,
+    /// Extended grapheme clusters, what a reader would take for one character, so a and a combining accent is one.
+    Graphemes,
+    /// Columns taken up in a terminal, so wide characters like 漢 are two and combining accents are none.
+    Width,
+}
+
+impl LengthUnit {
+    /// Returns the unit with the given name (bytes, chars, graphemes or width).
+    pub fn from_name(name: &str) -> Option<LengthUnit> {
+        match name {
+            "bytes" => Some(LengthUnit::Bytes),
+            "chars" => Some(LengthUnit::Chars),
+            "graphemes" => Some(LengthUnit::Graphemes),
+            "width" => Some(LengthUnit::Width),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LengthUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LengthUnit::Bytes => write!(f, "bytes"),
+            LengthUnit::Chars => write!(f, "chars"),
+            LengthUnit::Graphemes => write!(f, "graphemes"),
+            LengthUnit::Width => write!(f, "width"),
+        }
+    }
+}
+
+/// Returns the length of the word in the unit. For every unit but bytes, a word that isn't valid UTF-8 is measured
+/// like String::from_utf8_lossy shows it, with a replacement character for each invalid sequence, so \xff\xff is
+/// two characters.
+///
+/// # Arguments
+///
+/// * `word` - The word to measure.
+/// * `unit` - What the length is counted in.
+///
+/// # Example
+///
+/// ```
+/// let word = Word::from("pässwörd");
+///
+/// assert_eq!(length_in(&word, LengthUnit::Bytes), 10);
+/// assert_eq!(length_in(&word, LengthUnit::Chars), 8);
+/// ```
+pub fn length_in(word: &Word, unit: LengthUnit) -> usize {
+    let text = || String::from_utf8_lossy(word.as_bytes());
+    match unit {
+        LengthUnit::Bytes => word.len(),
+        LengthUnit::Chars => text().chars().count(),
+        LengthUnit::Graphemes => text().graphemes(true).count(),
+        LengthUnit::Width => text().width(),
+    }
+}
+
+/// Returns a list of words, in an order reliant on .filter, where all words outside the given lengths are removed,
+/// counting lengths in the given unit. Both ends of the range are inclusive.
+///
+/// # Arguments
+///
+/// * `words` - A vector of all the words to process, one word per string.
+/// * `min` - The shortest length kept.
+/// * `max` - The longest length kept.
+/// * `unit` - What lengths are counted in.
+///
+/// # Example
+///
+/// ```
+/// // Create an input list of words.
+/// let input_words: Vec<Word> = vec!["pässwörd", "password1"]
+///     .into_iter().map(Word::from).collect();
+///
+/// let output_words = remove_outside_lengths(input_words, 8, 8, LengthUnit::Chars);
+///
+/// assert!(output_words == vec!["pässwörd"]);
+/// ```
+pub fn remove_outside_lengths(words: Vec<Word>, min: usize, max: usize, unit: LengthUnit) -> Vec<Word> {
+    words
+        .into_iter()
+        .filter(|w| is_within_lengths(w, min, max, unit))
+        .collect()
+}
+
+/// Returns true if the length of the word in the unit is between min and max, inclusive.
+pub fn is_within_lengths(word: &Word, min: usize, max: usize, unit: LengthUnit) -> bool {
+    let length = length_in(word, unit);
+    length >= min && length <= max
+}
+
+/// A password policy words have to meet, like at least 8 long with 3 of the 4 character classes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Policy {
+    /// The shortest a word may be, in chars like the classes and repeats, see LengthUnit::Chars.
+    pub min_length: usize,
+    /// How many of the classes lowercase letters, uppercase letters, digits and symbols a word needs.
+    pub min_classes: usize,
+    /// The most times the same character may appear in a row, or None for any number of times.
+    pub max_repeats: Option<usize>,
+}
+
+/// Returns a list of words, in an order reliant on .filter, with all words that don't meet the policy removed.
+///
+/// # Arguments
+///
+/// * `words` - A vector of all the words to process, one word per string.
+/// * `policy` - The length, character classes and repeats every word needs.
+///
+/// # Example
+///
+/// ```
+/// // Create an input list of words.
+/// let input_words: Vec<Word> = vec!["password", "Passw0rd", "Paaassw0rd"]
+///     .into_iter().map(Word::from).collect();
+/// let policy = Policy { min_length: 8, min_classes: 3, max_repeats: Some(2) };
+///
+/// let output_words = require_policy(input_words, &policy);
+///
+/// assert!(output_words == vec!["Passw0rd"]);
+/// ```
+pub fn require_policy(words: Vec<Word>, policy: &Policy) -> Vec<Word> {
+    words
+        .into_iter()
+        .filter(|w| meets_policy(w, policy))
+        .collect()
+}
+
+/// Returns true if the word meets the policy, see require_policy.
+pub fn meets_policy(word: &Word, policy: &Policy) -> bool {
+    length_in(word, LengthUnit::Chars) >= policy.min_length
+        && character_classes(word) >= policy.min_classes
+        && policy.max_repeats.is_none_or(|max| longest_repeat(word) <= max)
+}
+
+/// Returns how many of the classes lowercase letters, uppercase letters, digits and symbols the word has.
+/// Letters and digits are decided by char.is_lowercase(), char.is_uppercase() and char.is_numeric(), and anything
+/// else is a symbol. Words that aren't valid UTF-8 are checked a byte at a time, with bytes that aren't ASCII
+/// counting as symbols.
+pub fn character_classes(word: &Word) -> usize {
+    let class = |c: char| {
+        if c.is_lowercase() {
+            1
+        } else if c.is_uppercase() {
+            2
+        } else if c.is_numeric() {
+            4
+        } else {
+            8
+        }
+    };
+
+    let classes: u8 = match word.to_str() {
+        Some(text) => text.chars().map(class).fold(0, |a, b| a | b),
+        None => word
+            .as_bytes()
+            .iter()
+            .map(|b| if b.is_ascii() { class(*b as char) } else { 8 })
+            .fold(0, |a, b| a | b),
+    };
+    classes.count_ones() as usize
+}
+
+/// Returns the most times the same character appears in a row in the word, counting bytes for words that aren't
+/// valid UTF-8.
+pub fn longest_repeat(word: &Word) -> usize {
+    fn longest<T: PartialEq, I: Iterator<Item = T>>(items: I) -> usize {
+        let mut longest = 0;
+        let mut run = 0;
+        let mut last = None;
+        for item in items {
+            run = if last.as_ref() == Some(&item) { run + 1 } else { 1 };
+            longest = longest.max(run);
+            last = Some(item);
+        }
+        longest
+    }
+
+    match word.to_str() {
+        Some(text) => longest(text.chars()),
+        None => longest(word.as_bytes().iter()),
+    }
+}
+
+/// Returns a list of words, in an order reliant on .filter, with only the words that match one of the patterns.
+///
+/// # Arguments
+///
+/// * `words` - A vector of all the words to process, one word per string.
+/// * `patterns` - The regexes to look for, anywhere in the word unless they're anchored.
+///
+/// # Example
+///
+/// ```
+/// // Create an input list of words.
+/// let input_words: Vec<Word> = vec!["123456", "password", "admin@example.com"]
+///     .into_iter().map(Word::from).collect();
+/// let patterns = Patterns::new(&[String::from(r"^\d+$"), String::from("@")]).unwrap();
+///
+/// let output_words = keep_matching(input_words, &patterns);
+///
+/// assert!(output_words == vec!["123456", "admin@example.com"]);
+/// ```
+pub fn keep_matching(words: Vec<Word>, patterns: &Patterns) -> Vec<Word> {
+    words.into_iter().filter(|w| patterns.is_match(w)).collect()
+}
+
+/// Returns a list of words, in an order reliant on .filter, with every word that matches one of the patterns removed.
+///
+/// # Arguments
+///
+/// * `words` - A vector of all the words to process, one word per string.
+/// * `patterns` - The regexes to look for, anywhere in the word unless they're anchored.
+///
+/// # Example
+///
+/// ```
+/// // Create an input list of words.
+/// let input_words: Vec<Word> = vec!["123456", "password", "admin@example.com"]
+///     .into_iter().map(Word::from).collect();
+/// let patterns = Patterns::new(&[String::from(r"^\d+$"), String::from("@")]).unwrap();
+///
+/// let output_words = remove_matching(input_words, &patterns);
+///
+/// assert!(output_words == vec!["password"]);
+/// ```
+pub fn remove_matching(words: Vec<Word>, patterns: &Patterns) -> Vec<Word> {
+    words.into_iter().filter(|w| !patterns.is_match(w)).collect()
+}
+
+/// Returns a list of words, in an order reliant on .filter, with only the words of the given classes, see
+/// classify::classify.
+///
+/// # Arguments
+///
+/// * `words` - A vector of all the words to process, one word per string.
+/// * `classes` - The classes of words to keep.
+///
+/// # Example
+///
+/// ```
+/// // Create an input list of words.
+/// let input_words: Vec<Word> = vec!["hunter2", "admin@example.com", "<br>"]
+///     .into_iter().map(Word::from).collect();
+///
+/// let output_words = keep_classes(input_words, &[Class::Plain]);
+///
+/// assert!(output_words == vec!["hunter2"]);
+/// ```
+pub fn keep_classes(words: Vec<Word>, classes: &[Class]) -> Vec<Word> {
+    words.into_iter().filter(|w| classes.contains(&classify(w))).collect()
+}
+
+/// Returns a list of words, in an order reliant on .filter, with every word of the given classes removed, see
+/// classify::classify.
+///
+/// # Arguments
+///
+/// * `words` - A vector of all the words to process, one word per string.
+/// * `classes` - The classes of words to remove.
+///
+/// # Example
+///
+/// ```
+/// // Create an input list of words.
+/// let input_words: Vec<Word> = vec!["hunter2", "admin@example.com", "<br>"]
+///     .into_iter().map(Word::from).collect();
+///
+/// let output_words = remove_classes(input_words, &[Class::Email, Class::Markup]);
+///
+/// assert!(output_words == vec!["hunter2"]);
+/// ```
+pub fn remove_classes(words: Vec<Word>, classes: &[Class]) -> Vec<Word> {
+    words.into_iter().filter(|w| !classes.contains(&classify(w))).collect()
+}
+
+/// Returns a list of words, in an order reliant on .filter_map, with the words of the route's classes taken out
+/// and written to the route's file instead.
+///
+/// # Arguments
+///
+/// * `words` - A vector of all the words to process, one word per string.
+/// * `route` - Which classes to take out and the file they're written to.
+pub fn route_classes(words: Vec<Word>, route: &ClassRoute) -> Vec<Word> {
+    words.into_iter().filter_map(|w| route.route(w)).collect()
+}
+
+/// Returns a list of words, in an order reliant on .map, with the substitution run on every word, like sed does.
+///
+/// # Arguments
+///
+/// * `words` - A vector of all the words to process, one word per string.
+/// * `substitution` - The sed-like substitution, see Substitution::parse.
+///
+/// # Example
+///
+/// ```
+/// // Create an input list of words.
+/// let input_words: Vec<Word> = vec!["admin@example.com", "password"]
+///     .into_iter().map(Word::from).collect();
+/// let substitution = Substitution::parse(r"s/(\w+)@.*/\1/").unwrap();
+///
+/// let output_words = replace(input_words, &substitution);
+///
+/// assert!(output_words == vec!["admin", "password"]);
+/// ```
+pub fn replace(words: Vec<Word>, substitution: &Substitution) -> Vec<Word> {
+    words.into_iter().map(|w| substitution.apply(w)).collect()
+}
+
+/// Returns a list of words, in an order reliant on .map, with every letter made lowercase.
+/// Letters are changed by str.to_lowercase(), or only ASCII letters for words that aren't valid UTF-8.
+///
+/// # Arguments
+///
+/// * `words` - A vector of all the words to process, one word per string.
+///
+/// # Example
+///
+/// ```
+/// // Create an input list of words.
+/// let input_words: Vec<Word> = vec!["Hello", "WORLD"]
+///     .into_iter().map(Word::from).collect();
+///
+/// let output_words = lowercase(input_words);
+///
+/// assert!(output_words == vec!["hello", "world"]);
+/// ```
+pub fn lowercase(words: Vec<Word>) -> Vec<Word> {
+    words.into_iter().map(lowercase_word).collect()
+}
+
+/// Returns the word with every letter made lowercase, see lowercase.
+pub fn lowercase_word(word: Word) -> Word {
+    match word.to_str() {
+        Some(text) => Word::from(text.to_lowercase()),
+        None => Word::new(word.as_bytes().to_ascii_lowercase()),
+    }
+}
+
+/// Returns a list of words, in an order reliant on .map, with every letter made uppercase.
+/// Letters are changed by str.to_uppercase(), or only ASCII letters for words that aren't valid UTF-8.
+///
+/// # Arguments
+///
+/// * `words` - A vector of all the words to process, one word per string.
+///
+/// # Example
+///
+/// ```
+/// // Create an input list of words.
+/// let input_words: Vec<Word> = vec!["Hello", "world"]
+///     .into_iter().map(Word::from).collect();
+///
+/// let output_words = uppercase(input_words);
+///
+/// assert!(output_words == vec!["HELLO", "WORLD"]);
+/// ```
+pub fn uppercase(words: Vec<Word>) -> Vec<Word> {
+    words.into_iter().map(uppercase_word).collect()
+}
+
+/// Returns the word with every letter made uppercase, see uppercase.
+pub fn uppercase_word(word: Word) -> Word {
+    match word.to_str() {
+        Some(text) => Word::from(text.to_uppercase()),
+        None => Word::new(word.as_bytes().to_ascii_uppercase()),
+    }
+}
+
+/// Returns a list of words, in an order reliant on .map, with the first letter after the start of the word or any
+/// whitespace made uppercase and every other letter made lowercase.
+///
+/// # Arguments
+///
+/// * `words` - A vector of all the words to process, one word per string.
+///
+/// # Example
+///
+/// ```
+/// // Create an input list of words.
+/// let input_words: Vec<Word> = vec!["hello WORLD", "o'neil"]
+///     .into_iter().map(Word::from).collect();
+///
+/// let output_words = title_case(input_words);
+///
+/// assert!(output_words == vec!["Hello World", "O'neil"]);
+/// ```
+pub fn title_case(words: Vec<Word>) -> Vec<Word> {
+    words.into_iter().map(title_case_word).collect()
+}
+
+/// Returns the word in title case, see title_case.
+pub fn title_case_word(word: Word) -> Word {
+    match word.to_str() {
+        Some(text) => {
+            let mut titled = String::with_capacity(text.len());
+            let mut start = true;
+            for c in text.chars() {
+                if start {
+                    titled.extend(c.to_uppercase());
+                } else {
+                    titled.extend(c.to_lowercase());
+                }
+                start = c.is_whitespace();
+            }
+            Word::from(titled)
+        }
+        None => {
+            let mut start = true;
+            let titled = word
+                .as_bytes()
+                .iter()
+                .map(|b| {
+                    let titled = if start { b.to_ascii_uppercase() } else { b.to_ascii_lowercase() };
+                    start = b.is_ascii_whitespace();
+                    titled
+                })
+                .collect();
+            Word::new(titled)
+        }
+    }
+}
+
+/// Returns a list of words, in an order reliant on .map, with the case of the character at the position swapped.
+/// Positions count characters from 0, or bytes for words that aren't valid UTF-8. Words too short to have the
+/// position are left alone.
+///
+/// # Arguments
+///
+/// * `words` - A vector of all the words to process, one word per string.
+/// * `position` - Which character to change, starting from 0.
+///
+/// # Example
+///
+/// ```
+/// // Create an input list of words.
+/// let input_words: Vec<Word> = vec!["hello", "WORLD", "a"]
+///     .into_iter().map(Word::from).collect();
+///
+/// let output_words = toggle_case(input_words, 1);
+///
+/// assert!(output_words == vec!["hEllo", "WoRLD", "a"]);
+/// ```
+pub fn toggle_case(words: Vec<Word>, position: usize) -> Vec<Word> {
+    words.into_iter().map(|w| toggle_case_at(w, position)).collect()
+}
+
+/// Returns the word with the case of the character at the position swapped, see toggle_case.
+pub fn toggle_case_at(word: Word, position: usize) -> Word {
+    match word.to_str() {
+        Some(text) => match text.char_indices().nth(position) {
+            None => word,
+            Some((i, c)) => {
+                let mut toggled = String::with_capacity(text.len());
+                toggled.push_str(&text[..i]);
+                if c.is_lowercase() {
+                    toggled.extend(c.to_uppercase());
+                } else {
+                    toggled.extend(c.to_lowercase());
+                }
+                toggled.push_str(&text[i + c.len_utf8()..]);
+                Word::from(toggled)
+            }
+        },
+        None => {
+            let mut bytes = word.as_bytes().to_vec();
+            if let Some(b) = bytes.get_mut(position) {
+                *b = if b.is_ascii_lowercase() { b.to_ascii_uppercase() } else { b.to_ascii_lowercase() };
+            }
+            Word::new(bytes)
+        }
+    }
+}
+
+/// The most case variants made from a word when no cap is given, every variant of a word with 10 letters. Longer
+/// words would make 2^n of them, more than fit in memory.
+pub const DEFAULT_MAX_PERMUTATIONS: usize = 1024;
+
+/// Returns every way of writing each word with its letters in uppercase or lowercase, in the order of the words.
+/// Each word's variants start with every letter lowercase, and the last letter changes fastest.
+///
+/// # Arguments
+///
+/// * `words` - A vector of all the words to process, one word per string.
+/// * `max_variants` - The most variants made from each word, or None for DEFAULT_MAX_PERMUTATIONS.
+///
+/// # Example
+///
+/// ```
+/// // Create an input list of words.
+/// let input_words: Vec<Word> = vec!["Hi!"]
+///     .into_iter().map(Word::from).collect();
+///
+/// let output_words = permute_case(input_words, None);
+///
+/// assert!(output_words == vec!["hi!", "hI!", "Hi!", "HI!"]);
+/// ```
+pub fn permute_case(words: Vec<Word>, max_variants: Option<usize>) -> Vec<Word> {
+    words.iter()
+        .flat_map(|word| case_permutations(word, max_variants))
+        .collect()
+}
+
+/// Returns every way of writing the word with its letters in uppercase or lowercase, see permute_case.
+/// Letters are decided like lowercase and uppercase do.
+pub fn case_permutations(word: &Word, max_variants: Option<usize>) -> Vec<Word> {
+    // Each part of the word is either something without case, or the lowercase and uppercase of a letter.
+    let parts: Vec<Vec<Vec<u8>>> = match word.to_str() {
+        Some(text) => text
+            .chars()
+            .map(|c| {
+                let lower: String = c.to_lowercase().collect();
+                let upper: String = c.to_uppercase().collect();
+                if lower == upper {
+                    vec![lower.into_bytes()]
+                } else {
+                    vec![lower.into_bytes(), upper.into_bytes()]
+                }
+            })
+            .collect(),
+        None => word
+            .as_bytes()
+            .iter()
+            .map(|b| {
+                if b.is_ascii_alphabetic() {
+                    vec![vec![b.to_ascii_lowercase()], vec![b.to_ascii_uppercase()]]
+                } else {
+                    vec![vec![*b]]
+                }
+            })
+            .collect(),
+    };
+
+    let max = max_variants.unwrap_or(DEFAULT_MAX_PERMUTATIONS);
+    let mut indexes = vec![0; parts.len()];
+    let mut made = vec![];
+    'variants: while made.len() < max {
+        made.push(Word::new(
+            parts.iter().zip(&indexes).flat_map(|(part, i)| part[*i].iter().cloned()).collect(),
+        ));
+
+        let mut i = parts.len();
+        loop {
+            if i == 0 {
+                break 'variants;
+            }
+            i -= 1;
+            indexes[i] += 1;
+            if indexes[i] < parts[i].len() {
+                break;
             }
+            indexes[i] = 0;
         }
-        return true;
     }
 
-    words.into_iter().filter(is_letters).collect()
+    made
+}
+
+/// Returns a list of counted words with words that only differ in case merged together. Each group of words is
+/// written the way it was seen most often, ties going to the one seen first, with the counts of every casing
+/// added together. Groups are in the order they were first seen.
+///
+/// # Arguments
+///
+/// * `words` - Every word along with how many times it was seen.
+///
+/// # Example
+///
+/// ```
+/// let input_words = vec![("password", 1), ("Password", 3), ("PASSWORD", 1), ("hello", 1)]
+///     .into_iter().map(|(w, c)| (Word::from(w), c));
+///
+/// let output_words = merge_caseless(input_words);
+///
+/// assert!(output_words == vec![(Word::from("Password"), 5), (Word::from("hello"), 1)]);
+/// ```
+pub fn merge_caseless<I: IntoIterator<Item = (Word, u64)>>(words: I) -> Vec<(Word, u64)> {
+    // Where each casing was first seen and how often it was seen.
+    let mut casings: HashMap<Word, (usize, u64)> = HashMap::new();
+    for (position, (word, count)) in words.into_iter().enumerate() {
+        let casing = casings.entry(word).or_insert((position, 0));
+        casing.1 = casing.1.saturating_add(count);
+    }
+    let mut casings: Vec<(usize, Word, u64)> = casings
+        .into_iter()
+        .map(|(word, (position, count))| (position, word, count))
+        .collect();
+    casings.sort_unstable_by_key(|(position, _, _)| *position);
+
+    // For each lowercase word, where it was first seen, the casing seen most often with its count, and the total.
+    // Casings are gone through in the order they were first seen, so a tie keeps the earlier one.
+    let mut groups: HashMap<Word, (usize, Word, u64, u64)> = HashMap::new();
+    for (position, word, count) in casings {
+        let lowercase = lowercase_word(word.clone());
+        match groups.get_mut(&lowercase) {
+            None => {
+                groups.insert(lowercase, (position, word, count, count));
+            }
+            Some(group) => {
+                if count > group.2 {
+                    group.1 = word;
+                    group.2 = count;
+                }
+                group.3 = group.3.saturating_add(count);
+            }
+        }
+    }
+
+    let mut merged: Vec<(usize, Word, u64)> = groups
+        .into_values()
+        .map(|(position, word, _, total)| (position, word, total))
+        .collect();
+    merged.sort_unstable_by_key(|(position, _, _)| *position);
+
+    merged.into_iter().map(|(_, word, count)| (word, count)).collect()
+}
+
+/// Returns a list of words with words that only differ in case removed, keeping the casing seen most often.
+/// See merge_caseless.
+///
+/// # Arguments
+///
+/// * `words` - A vector of all the words to process, one word per string.
+///
+/// # Example
+///
+/// ```
+/// // Create an input list of words.
+/// let input_words: Vec<Word> = vec!["password", "Password", "Password", "hello"]
+///     .into_iter().map(Word::from).collect();
+///
+/// let output_words = deduplicate_caseless(input_words);
+///
+/// assert!(output_words == vec!["Password", "hello"]);
+/// ```
+pub fn deduplicate_caseless(words: Vec<Word>) -> Vec<Word> {
+    merge_caseless(words.into_iter().map(|w| (w, 1)))
+        .into_iter()
+        .map(|(word, _)| word)
+        .collect()
+}
+
+/// A Unicode normalization form, which decides how characters that can be written more than one way are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalForm {
+    /// Canonical composition, so e and a combining acute accent become é.
+    Nfc,
+    /// Canonical decomposition, so é becomes e and a combining acute accent.
+    Nfd,
+    /// Compatibility composition, which also turns characters like ﬁ and full width letters into their plain forms.
+    Nfkc,
+    /// Compatibility decomposition.
+    Nfkd,
+}
+
+impl NormalForm {
+    /// Returns the form with the given name (nfc, nfd, nfkc or nfkd).
+    pub fn from_name(name: &str) -> Option<NormalForm> {
+        match name {
+            "nfc" => Some(NormalForm::Nfc),
+            "nfd" => Some(NormalForm::Nfd),
+            "nfkc" => Some(NormalForm::Nfkc),
+            "nfkd" => Some(NormalForm::Nfkd),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for NormalForm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NormalForm::Nfc => write!(f, "nfc"),
+            NormalForm::Nfd => write!(f, "nfd"),
+            NormalForm::Nfkc => write!(f, "nfkc"),
+            NormalForm::Nfkd => write!(f, "nfkd"),
+        }
+    }
+}
+
+/// Which spelling of a word deduplicating by normal form keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Spelling {
+    /// The word as it was first seen.
+    Original,
+    /// The word in the normal form it was compared in.
+    Normalized,
+}
+
+impl Spelling {
+    /// Returns the spelling with the given name (original or normalized).
+    pub fn from_name(name: &str) -> Option<Spelling> {
+        match name {
+            "original" => Some(Spelling::Original),
+            "normalized" => Some(Spelling::Normalized),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Spelling {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Spelling::Original => write!(f, "original"),
+            Spelling::Normalized => write!(f, "normalized"),
+        }
+    }
+}
+
+/// How deduplicate compares words by their normal form, and which spelling of each word it keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Normalization {
+    pub form: NormalForm,
+    pub spelling: Spelling,
+}
+
+/// Returns a list of words, in an order reliant on .map, with every word in the normal form.
+/// Words that aren't valid UTF-8 are left as they are.
+///
+/// # Arguments
+///
+/// * `words` - A vector of all the words to process, one word per string.
+/// * `form` - The normal form to write words in.
+///
+/// # Example
+///
+/// ```
+/// // Create an input list of words, the first with a combining accent.
+/// let input_words: Vec<Word> = vec!["cafe\u{301}", "café"]
+///     .into_iter().map(Word::from).collect();
+///
+/// let output_words = normalize(input_words, NormalForm::Nfc);
+///
+/// assert!(output_words == vec!["café", "café"]);
+/// ```
+pub fn normalize(words: Vec<Word>, form: NormalForm) -> Vec<Word> {
+    words.into_iter().map(|w| normalize_word(w, form)).collect()
+}
+
+/// Returns the word in the normal form, see normalize.
+pub fn normalize_word(word: Word, form: NormalForm) -> Word {
+    let text = match word.to_str() {
+        None => return word,
+        Some(text) => text,
+    };
+
+    let normalized: String = match form {
+        NormalForm::Nfc => text.nfc().collect(),
+        NormalForm::Nfd => text.nfd().collect(),
+        NormalForm::Nfkc => text.nfkc().collect(),
+        NormalForm::Nfkd => text.nfkd().collect(),
+    };
+    Word::from(normalized)
+}
+
+/// Returns a list of counted words with words that are the same in the normal form merged together and their
+/// counts added up, in an order decided by the mode like merge_counts. Each word is written as it was first seen or
+/// in the normal form, and words seen as often as each other are sorted by how they're written.
+///
+/// # Arguments
+///
+/// * `words` - Every word along with how many times it was seen.
+/// * `mode` - Which copy of each word decides where it goes.
+/// * `normalization` - The normal form words are compared in, and which spelling is kept.
+///
+/// # Example
+///
+/// ```
+/// let input_words = vec![("cafe\u{301}", 1), ("café", 3)]
+///     .into_iter().map(|(w, c)| (Word::from(w), c));
+/// let normalization = Normalization { form: NormalForm::Nfc, spelling: Spelling::Original };
+///
+/// let output_words = merge_normalized(input_words, DedupMode::First, normalization);
+///
+/// assert!(output_words == vec![(Word::from("cafe\u{301}"), 4)]);
+/// ```
+pub fn merge_normalized<I: IntoIterator<Item = (Word, u64)>>(
+    words: I,
+    mode: DedupMode,
+    normalization: Normalization,
+) -> Vec<(Word, u64)> {
+    let form = normalization.form;
+    match normalization.spelling {
+        Spelling::Normalized => merge_counts(words.into_iter().map(|(w, c)| (normalize_word(w, form), c)), mode),
+        Spelling::Original => {
+            // The spelling each normal form was first seen in.
+            let mut originals: HashMap<Word, Word> = HashMap::new();
+            let normalized: Vec<(Word, u64)> = words
+                .into_iter()
+                .map(|(word, count)| {
+                    let normalized = normalize_word(word.clone(), form);
+                    originals.entry(normalized.clone()).or_insert(word);
+                    (normalized, count)
+                })
+                .collect();
+
+            let mut merged: Vec<(Word, u64)> = merge_counts(normalized, mode)
+                .into_iter()
+                .map(|(word, count)| (originals.remove(&word).unwrap_or(word), count))
+                .collect();
+            if mode == DedupMode::Frequency {
+                merged.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            }
+            merged
+        }
+    }
+}
+
+/// Returns a list of words with words that are the same in the normal form removed, in an order decided by the
+/// mode like deduplicate_with. Each word is written as it was first seen or in the normal form.
+///
+/// # Arguments
+///
+/// * `words` - A vector of all the words to process, one word per string.
+/// * `mode` - Which copy of each word to keep.
+/// * `normalization` - The normal form words are compared in, and which spelling is kept.
+///
+/// # Example
+///
+/// ```
+/// // Create an input list of words, the first with a combining accent.
+/// let input_words: Vec<Word> = vec!["cafe\u{301}", "café", "hello"]
+///     .into_iter().map(Word::from).collect();
+/// let normalization = Normalization { form: NormalForm::Nfc, spelling: Spelling::Normalized };
+///
+/// let output_words = deduplicate_normalized(input_words, DedupMode::First, normalization);
+///
+/// assert!(output_words == vec!["café", "hello"]);
+/// ```
+pub fn deduplicate_normalized(words: Vec<Word>, mode: DedupMode, normalization: Normalization) -> Vec<Word> {
+    let form = normalization.form;
+    match normalization.spelling {
+        Spelling::Normalized => deduplicate_with(normalize(words, form), mode),
+        Spelling::Original => merge_normalized(words.into_iter().map(|w| (w, 1)), mode, normalization)
+            .into_iter()
+            .map(|(word, _)| word)
+            .collect(),
+    }
+}
+
+/// Returns a list of words, in an order reliant on .map, with leading and trailing whitespace removed from all words.
+/// Whitespace is determined by str.trim(), or by u8.is_ascii_whitespace() for words that aren't valid UTF-8.
+///
+/// # Arguments
+///
+/// * `words` - A vector of all the words to process, one word per string.
+///
+/// # Example
+///
+/// ```
+/// // Create an input list of words.
+/// let input_words: Vec<Word> = vec![" Hello ", "\nWorld  "]
+///     .into_iter().map(Word::from).collect();
+///
+/// let output_words = trim_whitespaces(input_words);
+///
+/// assert!(output_words.len() == 2);
+/// assert!(output_words[0] == "Hello");
+/// assert!(output_words[1] == "World");
+/// ```
+pub fn trim_whitespaces(words: Vec<Word>) -> Vec<Word> {
+    words
+        .into_iter()
+        .map(trim_whitespace)
+        .collect()
+}
+
+/// Returns the word with leading and trailing whitespace removed, see trim_whitespaces.
+pub fn trim_whitespace(word: Word) -> Word {
+    let trimmed = trim(word.as_bytes());
+    if trimmed.len() == word.len() {
+        return word;
+    }
+
+    Word::from(trimmed)
 }
 
-/// Returns a list of words, in an order reliant on .filter, with all words where char.is_alphabetic() is false for all characters..
+/// Trims whitespace from the ends of some bytes, the way trim_whitespace does.
+fn trim(bytes: &[u8]) -> &[u8] {
+    match str::from_utf8(bytes) {
+        Ok(text) => text.trim().as_bytes(),
+        Err(_) => bytes.trim_ascii(),
+    }
+}
+
+/// Returns true if a word is all ASCII digits, false otherwise.
 ///
 /// # Arguments
 ///
-/// * `words` - A vector of all the words to process, one word per string.
+/// * `word` - The word to check.
 ///
 /// # Example
 ///
 /// ```
-/// // Create an input list of words.
-/// let input_words: Vec<String> = vec!["Hell-o", "Wo@rld", "World"]
-///     .into_iter().map(String::from).collect();
-///
-/// let output_words = remove_lacking_symbols(input_words);
-///
-/// assert!(output_words.len() == 2);
-/// assert!(output_words[0].as_str() == "Hello");
-/// assert!(output_words[1].as_str() == "World");
-/// ```
-pub fn remove_lacking_symbols(words: Vec<String>) -> Vec<String> {
-    fn is_letters(string: &String) -> bool {
-        for char in string.chars() {
-            if !char.is_alphabetic() {
-                return true;
-            }
-        }
-        return false;
-    }
-
-    words.into_iter().filter(is_letters).collect()
+/// assert!( ! is_number("Hello"));
+/// assert!(is_number("2134"));
+/// ```
+pub fn is_number<W: AsRef<[u8]> + ?Sized>(word: &W) -> bool {
+    word.as_ref().iter().all(u8::is_ascii_digit)
 }
 
-/// Returns a list of words, in an order reliant on .map, where all words outside the given lengths are removed.
-/// Both ends of the range are inclusive.
+/// Returns a list of words, in an order reliant on .map, with a leading number followed by a password; the two seperated by a any number of spaces.
+/// Words that are processed are trimmed after the count is removed. However, only words where the first split grouping is a number are processed.
 ///
 /// # Arguments
 ///
@@ -103,160 +1223,432 @@ pub fn remove_lacking_symbols(words: Vec<String>) -> Vec<String> {
 ///
 /// ```
 /// // Create an input list of words.
-/// let input_words: Vec<String> = vec!["Hello", "World", "Password"]
-///     .into_iter().map(String::from).collect();
+/// let input_words: Vec<Word> = vec!["4 Hello ", "1230      World"]
+///     .into_iter().map(Word::from).collect();
 ///
-/// let output_words = remove_outside_lengths(input_words, 5, 5);
+/// let output_words = remove_counts(input_words);
 ///
 /// assert!(output_words.len() == 2);
-/// assert!(output_words[0].as_str() == "Hello");
-/// assert!(output_words[1].as_str() == "World");
+/// assert!(output_words[0] == "Hello");
+/// assert!(output_words[1] == "World");
 /// ```
-pub fn remove_outside_lengths(words: Vec<String>, min: usize, max: usize) -> Vec<String> {
-    words
-        .into_iter()
-        .filter(|i| (i.len() >= min) & (i.len() <= max))
+pub fn remove_counts(words: Vec<Word>) -> Vec<Word> {
+    words.into_iter()
+        .map(remove_count)
         .collect()
 }
 
-/// Returns a list of words, in an order reliant on .map, with leading and trailing whitespace removed from all words.
-/// Whitespace is determined by String.trim().
+/// Returns the word with its leading count removed, see remove_counts.
+/// Counts and the spaces after them are ASCII, so words that aren't valid UTF-8 can have their counts removed too.
+pub fn remove_count(word: Word) -> Word {
+    if word.len() < 3 { return word; } // it can't have a count and a password if it's too short
+
+    let bytes = word.as_bytes();
+    let space = match bytes.iter().position(|b| *b == b' ') {
+        None => return word,
+        Some(space) => space,
+    };
+
+    if is_number(&bytes[..space]) {
+        Word::from(trim(&bytes[space + 1..]))
+    } else {
+        trim_whitespace(word)
+    }
+}
+
+/// Returns a list of words, in an order reliant on .map, where the prefix is inserted to every word.
 ///
 /// # Arguments
 ///
+/// * `prefix` - A string to insert before the word.
 /// * `words` - A vector of all the words to process, one word per string.
 ///
 /// # Example
 ///
 /// ```
 /// // Create an input list of words.
-/// let input_words: Vec<String> = vec![" Hello ", "\nWorld  "]
-///     .into_iter().map(String::from).collect();
+/// let input_words: Vec<Word> = vec!["Hello ", "World"]
+///     .into_iter().map(Word::from).collect();
 ///
-/// let output_words = trim_whitespaces(input_words);
+/// let output_words = prefix("Jorge ", input_words);
 ///
 /// assert!(output_words.len() == 2);
-/// assert!(output_words[0].as_str() == "Hello");
-/// assert!(output_words[1].as_str() == "World");
+/// assert!(output_words[0] == "Jorge Hello");
+/// assert!(output_words[1] == "Jorge World");
 /// ```
-pub fn trim_whitespaces(words: Vec<String>) -> Vec<String> {
-    fn trim_whitespace(word: String) -> String {
-        String::from(word.trim())
-    }
+pub fn prefix(prefix: &str, words: Vec<Word>) -> Vec<Word> {
+    let prefixed = |word: Word| -> Word {
+        add_prefix(prefix, word)
+    };
 
-    words
-        .into_iter()
-        .map(trim_whitespace)
+    words.into_iter()
+        .map(prefixed)
         .collect()
 }
 
-/// Returns true if a String is all digits, false otherwise.
+/// Returns the word with the prefix inserted before it, see prefix.
+pub fn add_prefix(prefix: &str, word: Word) -> Word {
+    let mut bytes = Vec::with_capacity(prefix.len() + word.len());
+    bytes.extend_from_slice(prefix.as_bytes());
+    bytes.extend_from_slice(word.as_bytes());
+    Word::new(bytes)
+}
+
+/// Returns every word made by running each rule on each word, in the order of the words and then the rules.
+/// Words a rule rejects are left out, see Rules::apply.
 ///
 /// # Arguments
 ///
-/// * `word` - The word to check.
+/// * `rules` - The rules to run, as read from a .rule file.
+/// * `words` - A vector of all the words to process, one word per string.
 ///
 /// # Example
 ///
 /// ```
-/// assert!( ! is_number(String::from("Hello")));
-/// assert!(is_number(String::from("2134")));
+/// // Using a .rule file with the rules ':' and 'c $1'.
+/// let rules = Rules::load(Path::new("simple.rule"), Variants::All).unwrap();
+/// let input_words: Vec<Word> = vec!["hello", "world"]
+///     .into_iter().map(Word::from).collect();
+///
+/// let output_words = apply_rules(&rules, input_words);
+///
+/// assert!(output_words == vec!["hello", "Hello1", "world", "World1"]);
 /// ```
-pub fn is_number(word: &String) -> bool {
-    for c in word.chars() {
-        if !c.is_ascii_digit() {
-            return false;
-        }
-    }
-    return true;
+pub fn apply_rules(rules: &Rules, words: Vec<Word>) -> Vec<Word> {
+    words.iter()
+        .flat_map(|word| rules.apply(word))
+        .collect()
 }
 
-/// Returns a list of words, in an order reliant on .map, with a leading number followed by a password; the two seperated by a any number of spaces.
-/// Words that are processed are trimmed after the count is removed. However, only words where the first split grouping is a number are processed.
+/// Returns every leetspeak variant of each word, in the order of the words, with each word followed by its variants.
+/// See Leetspeak::apply.
 ///
 /// # Arguments
 ///
+/// * `leetspeak` - The substitution table, along with which variants to make.
 /// * `words` - A vector of all the words to process, one word per string.
 ///
 /// # Example
 ///
 /// ```
-/// // Create an input list of words.
-/// let input_words: Vec<String> = vec!["4 Hello ", "1230      World"]
-///     .into_iter().map(String::from).collect();
+/// // Using a table with the line 'e 3', in all mode.
+/// let leetspeak = Leetspeak::load(Path::new("leet.txt"), LeetMode::All, None).unwrap();
+/// let input_words: Vec<Word> = vec!["hello", "world"]
+///     .into_iter().map(Word::from).collect();
 ///
-/// let output_words = remove_counts(input_words);
+/// let output_words = leetspeak(&leetspeak, input_words);
 ///
-/// assert!(output_words.len() == 2);
-/// assert!(output_words[0].as_str() == "Hello");
-/// assert!(output_words[1].as_str() == "World");
+/// assert!(output_words == vec!["hello", "h3llo", "world"]);
 /// ```
-pub fn remove_counts(words: Vec<String>) -> Vec<String> {
-    let remove_count = |mut word: String| -> String {
-        if word.len() < 3 { return word; } // it can't have a count and a password if it's too short
-        if !word.contains(' ') {return word; }
-
-        let splits: Vec<String> = word.split(' ')
-            .into_iter()
-            .map(String::from)
-            .collect();
-        if is_number(&splits[0]) && splits.len() > 1 {
-            word = splits[1..].join(" ");
-        }
-
-        word = String::from(word.trim());
-        return word;
-    };
-   
-    words.into_iter()
-        .map(remove_count)
+pub fn leetspeak(leetspeak: &Leetspeak, words: Vec<Word>) -> Vec<Word> {
+    words.iter()
+        .flat_map(|word| leetspeak.apply(word))
         .collect()
 }
 
-/// Returns a list of words, in an order reliant on .map, where the prefix is inserted to every word.
+/// Returns every word written into the template, in the order of the words, with each word's index being where it
+/// is in the list. See Template::render.
 ///
 /// # Arguments
 ///
-/// * `prefix` - A string to insert before the word.
+/// * `template` - The template, along with the values of its lists.
 /// * `words` - A vector of all the words to process, one word per string.
 ///
 /// # Example
 ///
 /// ```
-/// // Create an input list of words.
-/// let input_words: Vec<String> = vec!["Hello ", "World"]
-///     .into_iter().map(String::from).collect();
+/// let template = Template::load("{index}:{word}", TemplateMode::Full, vec![]).unwrap();
+/// let input_words: Vec<Word> = vec!["hello", "world"]
+///     .into_iter().map(Word::from).collect();
 ///
-/// let output_words = prefix("Jorge ", input_words);
+/// let output_words = template(&template, input_words);
 ///
-/// assert!(output_words.len() == 2);
-/// assert!(output_words[0].as_str() == "Jorge Hello");
-/// assert!(output_words[1].as_str() == "Jorge World");
+/// assert!(output_words == vec!["0:hello", "1:world"]);
 /// ```
-pub fn prefix(prefix: &str, words: Vec<String>) -> Vec<String> {
-    let prefixed = |word: String| -> String {
-        let prefixed_word: String = format!("{}{}", prefix, word);
-        return prefixed_word;
-    };
-   
-    words.into_iter()
-        .map(prefixed)
+pub fn template(template: &Template, words: Vec<Word>) -> Vec<Word> {
+    words.iter()
+        .enumerate()
+        .flat_map(|(i, word)| template.render(word, i))
         .collect()
 }
 
+/// Every processor above as an iterator adapter, so words can be streamed through them one at a time
+/// instead of holding the whole list in memory. All of them keep the order of the words they're given.
+///
+/// # Example
+///
+/// ```
+/// let input_words = vec![" 4 Hello", "Hello", "World"].into_iter().map(Word::from);
+///
+/// let output_words: Vec<Word> = input_words
+///     .trim_whitespaces()
+///     .remove_counts()
+///     .deduplicate()
+///     .collect();
+///
+/// assert!(output_words == vec!["Hello", "World"]);
+/// ```
+pub trait WordIterator: Iterator<Item = Word> + Sized {
+    /// Streaming version of trim_whitespaces.
+    fn trim_whitespaces(self) -> impl Iterator<Item = Word> {
+        self.map(trim_whitespace)
+    }
+
+    /// Streaming version of remove_counts.
+    fn remove_counts(self) -> impl Iterator<Item = Word> {
+        self.map(remove_count)
+    }
+
+    /// Streaming version of remove_contains_symbols.
+    fn remove_contains_symbols(self) -> impl Iterator<Item = Word> {
+        self.filter(is_letters)
+    }
+
+    /// Streaming version of remove_lacking_symbols.
+    fn remove_lacking_symbols(self) -> impl Iterator<Item = Word> {
+        self.filter(|w| !is_letters(w))
+    }
+
+    /// Streaming version of remove_outside_lengths.
+    fn remove_outside_lengths(self, min: usize, max: usize, unit: LengthUnit) -> impl Iterator<Item = Word> {
+        self.filter(move |w| is_within_lengths(w, min, max, unit))
+    }
+
+    /// Streaming version of require_policy.
+    fn require_policy(self, policy: Policy) -> impl Iterator<Item = Word> {
+        self.filter(move |w| meets_policy(w, &policy))
+    }
+
+    /// Streaming version of keep_matching.
+    fn keep_matching<'a>(self, patterns: &'a Patterns) -> impl Iterator<Item = Word> + 'a
+    where
+        Self: 'a,
+    {
+        self.filter(move |w| patterns.is_match(w))
+    }
+
+    /// Streaming version of keep_classes.
+    fn keep_classes<'a>(self, classes: &'a [Class]) -> impl Iterator<Item = Word> + 'a
+    where
+        Self: 'a,
+    {
+        self.filter(move |w| classes.contains(&classify(w)))
+    }
+
+    /// Streaming version of remove_classes.
+    fn remove_classes<'a>(self, classes: &'a [Class]) -> impl Iterator<Item = Word> + 'a
+    where
+        Self: 'a,
+    {
+        self.filter(move |w| !classes.contains(&classify(w)))
+    }
+
+    /// Streaming version of route_classes.
+    fn route_classes<'a>(self, route: &'a ClassRoute) -> impl Iterator<Item = Word> + 'a
+    where
+        Self: 'a,
+    {
+        self.filter_map(move |w| route.route(w))
+    }
+
+    /// Streaming version of remove_matching.
+    fn remove_matching<'a>(self, patterns: &'a Patterns) -> impl Iterator<Item = Word> + 'a
+    where
+        Self: 'a,
+    {
+        self.filter(move |w| !patterns.is_match(w))
+    }
+
+    /// Streaming version of replace.
+    fn replace<'a>(self, substitution: &'a Substitution) -> impl Iterator<Item = Word> + 'a
+    where
+        Self: 'a,
+    {
+        self.map(move |w| substitution.apply(w))
+    }
+
+    /// Streaming version of lowercase.
+    fn lowercase(self) -> impl Iterator<Item = Word> {
+        self.map(lowercase_word)
+    }
+
+    /// Streaming version of uppercase.
+    fn uppercase(self) -> impl Iterator<Item = Word> {
+        self.map(uppercase_word)
+    }
+
+    /// Streaming version of title_case.
+    fn title_case(self) -> impl Iterator<Item = Word> {
+        self.map(title_case_word)
+    }
+
+    /// Streaming version of toggle_case.
+    fn toggle_case(self, position: usize) -> impl Iterator<Item = Word> {
+        self.map(move |w| toggle_case_at(w, position))
+    }
+
+    /// Streaming version of permute_case. Only the variants of one word are held at a time.
+    fn permute_case(self, max_variants: Option<usize>) -> impl Iterator<Item = Word> {
+        self.flat_map(move |word| case_permutations(&word, max_variants))
+    }
+
+    /// Streaming version of normalize.
+    fn normalize(self, form: NormalForm) -> impl Iterator<Item = Word> {
+        self.map(move |w| normalize_word(w, form))
+    }
+
+    /// Streaming version of prefix.
+    fn prefix(self, prefix: &str) -> impl Iterator<Item = Word> {
+        let prefix = String::from(prefix);
+        self.map(move |word| add_prefix(&prefix, word))
+    }
+
+    /// Streaming version of apply_rules. Only the words made from one word are held at a time.
+    fn apply_rules<'a>(self, rules: &'a Rules) -> impl Iterator<Item = Word> + 'a
+    where
+        Self: 'a,
+    {
+        self.flat_map(move |word| rules.apply(&word))
+    }
+
+    /// Streaming version of leetspeak. Only the variants of one word are held at a time.
+    fn leetspeak<'a>(self, leetspeak: &'a Leetspeak) -> impl Iterator<Item = Word> + 'a
+    where
+        Self: 'a,
+    {
+        self.flat_map(move |word| leetspeak.apply(&word))
+    }
+
+    /// Streaming version of template. Only the words made from one word are held at a time.
+    fn template<'a>(self, template: &'a Template) -> impl Iterator<Item = Word> + 'a
+    where
+        Self: 'a,
+    {
+        self.enumerate().flat_map(move |(i, word)| template.render(&word, i))
+    }
+
+    /// Streaming version of deduplicate. Words are passed on the first time they're seen, so unlike
+    /// deduplicate the order is kept. Every unique word is remembered until the stream ends.
+    fn deduplicate(self) -> Deduplicate<Self> {
+        Deduplicate {
+            words: self,
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Streaming version of deduplicate_with(words, DedupMode::Last).
+    /// Words are only passed on once the stream has ended.
+    fn deduplicate_keep_last(self) -> DeduplicateBuffered<Self> {
+        DeduplicateBuffered {
+            words: Some(self),
+            mode: DedupMode::Last,
+            unique: vec![].into_iter(),
+        }
+    }
+
+    /// Streaming version of deduplicate_with(words, DedupMode::Sorted).
+    /// Words are only passed on once the stream has ended.
+    fn deduplicate_sorted(self) -> DeduplicateBuffered<Self> {
+        DeduplicateBuffered {
+            words: Some(self),
+            mode: DedupMode::Sorted,
+            unique: vec![].into_iter(),
+        }
+    }
+
+    /// Streaming version of deduplicate_with(words, DedupMode::Frequency).
+    /// Words are only passed on once the stream has ended.
+    fn deduplicate_by_frequency(self) -> DeduplicateBuffered<Self> {
+        DeduplicateBuffered {
+            words: Some(self),
+            mode: DedupMode::Frequency,
+            unique: vec![].into_iter(),
+        }
+    }
+}
+
+impl<I: Iterator<Item = Word>> WordIterator for I {}
+
+/// Iterator returned by WordIterator::deduplicate_keep_last, WordIterator::deduplicate_sorted and
+/// WordIterator::deduplicate_by_frequency.
+/// Nothing is passed on until every word has been seen.
+pub struct DeduplicateBuffered<I> {
+    words: Option<I>,
+    mode: DedupMode,
+    unique: vec::IntoIter<Word>,
+}
+
+impl<I: Iterator<Item = Word>> Iterator for DeduplicateBuffered<I> {
+    type Item = Word;
+
+    fn next(&mut self) -> Option<Word> {
+        if let Some(words) = self.words.take() {
+            let unique: Vec<Word> = match self.mode {
+                DedupMode::Last => {
+                    let mut last: HashMap<Word, usize> = HashMap::new();
+                    for (i, word) in words.enumerate() {
+                        last.insert(word, i);
+                    }
+
+                    let mut ordered: Vec<(usize, Word)> =
+                        last.into_iter().map(|(word, i)| (i, word)).collect();
+                    ordered.sort_unstable_by_key(|(i, _)| *i);
+                    ordered.into_iter().map(|(_, word)| word).collect()
+                }
+                DedupMode::Frequency => merge_counts(words.map(|w| (w, 1)), DedupMode::Frequency)
+                    .into_iter()
+                    .map(|(word, _)| word)
+                    .collect(),
+                _ => {
+                    let mut unique: Vec<Word> = words.collect::<HashSet<Word>>().into_iter().collect();
+                    unique.sort_unstable();
+                    unique
+                }
+            };
+            self.unique = unique.into_iter();
+        }
+
+        self.unique.next()
+    }
+}
+
+/// Iterator returned by WordIterator::deduplicate.
+pub struct Deduplicate<I> {
+    words: I,
+    seen: HashSet<Word>,
+}
+
+impl<I: Iterator<Item = Word>> Iterator for Deduplicate<I> {
+    type Item = Word;
+
+    fn next(&mut self) -> Option<Word> {
+        for word in &mut self.words {
+            if !self.seen.contains(&word) {
+                self.seen.insert(word.clone());
+                return Some(word);
+            }
+        }
+
+        // Nothing else will be passed on, so let go of the words early.
+        mem::take(&mut self.seen);
+        None
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::template::TemplateMode;
 
     #[test]
     fn test_deduplicate() {
-        let words: Vec<String> = vec!["Hello", "Goodbye", "Hello", "aa", "aa", "aa", "bb", "aa"]
+        let words: Vec<Word> = vec!["Hello", "Goodbye", "Hello", "aa", "aa", "aa", "bb", "aa"]
             .into_iter()
-            .map(String::from)
+            .map(Word::from)
             .collect();
-        let correct: Vec<String> = vec!["Goodbye", "Hello", "aa", "bb"]
+        let correct: Vec<Word> = vec!["Goodbye", "Hello", "aa", "bb"]
             .into_iter()
-            .map(String::from)
+            .map(Word::from)
             .collect();
 
         let mut result = deduplicate(words);
@@ -265,13 +1657,36 @@ mod test {
         assert_eq!(result, correct);
     }
 
+    #[test]
+    fn test_deduplicate_with() {
+        let words: Vec<Word> = vec!["bb", "aa", "cc", "bb", "aa", "dd"]
+            .into_iter()
+            .map(Word::from)
+            .collect();
+        let first: Vec<Word> = vec!["bb", "aa", "cc", "dd"].into_iter().map(Word::from).collect();
+        let last: Vec<Word> = vec!["cc", "bb", "aa", "dd"].into_iter().map(Word::from).collect();
+        let sorted: Vec<Word> = vec!["aa", "bb", "cc", "dd"].into_iter().map(Word::from).collect();
+
+        assert_eq!(deduplicate_with(words.clone(), DedupMode::First), first);
+        assert_eq!(deduplicate_with(words.clone(), DedupMode::Last), last);
+        assert_eq!(deduplicate_with(words.clone(), DedupMode::Sorted), sorted);
+
+        assert_eq!(words.clone().into_iter().deduplicate().collect::<Vec<Word>>(), first);
+        assert_eq!(words.clone().into_iter().deduplicate_keep_last().collect::<Vec<Word>>(), last);
+        assert_eq!(words.clone().into_iter().deduplicate_sorted().collect::<Vec<Word>>(), sorted);
+
+        let frequency: Vec<Word> = vec!["aa", "bb", "cc", "dd"].into_iter().map(Word::from).collect();
+        assert_eq!(deduplicate_with(words.clone(), DedupMode::Frequency), frequency);
+        assert_eq!(words.into_iter().deduplicate_by_frequency().collect::<Vec<Word>>(), frequency);
+    }
+
     #[test]
     fn test_remove_contains_symbols() {
-        let words: Vec<String> = vec!["aaaa-", "bbbb", "cccc+", "dddd"]
+        let words: Vec<Word> = vec!["aaaa-", "bbbb", "cccc+", "dddd"]
             .into_iter()
-            .map(String::from)
+            .map(Word::from)
             .collect();
-        let correct: Vec<String> = vec!["bbbb", "dddd"].into_iter().map(String::from).collect();
+        let correct: Vec<Word> = vec!["bbbb", "dddd"].into_iter().map(Word::from).collect();
 
         let result = remove_contains_symbols(words);
 
@@ -280,13 +1695,13 @@ mod test {
 
     #[test]
     fn test_remove_lacking_symbols() {
-        let words: Vec<String> = vec!["aaaa-", "bbbb", "cccc+", "dddd"]
+        let words: Vec<Word> = vec!["aaaa-", "bbbb", "cccc+", "dddd"]
             .into_iter()
-            .map(String::from)
+            .map(Word::from)
             .collect();
-        let correct: Vec<String> = vec!["aaaa-", "cccc+"]
+        let correct: Vec<Word> = vec!["aaaa-", "cccc+"]
             .into_iter()
-            .map(String::from)
+            .map(Word::from)
             .collect();
 
         let result = remove_lacking_symbols(words);
@@ -296,30 +1711,223 @@ mod test {
 
     #[test]
     fn test_remove_outside_length() {
-        let words: Vec<String> = vec!["a", "ab", "abc", "abcd", "abcde", "abcdef"]
+        let words: Vec<Word> = vec!["a", "ab", "abc", "abcd", "abcde", "abcdef"]
             .into_iter()
-            .map(String::from)
+            .map(Word::from)
             .collect();
 
-        let correct: Vec<String> = vec!["abc", "abcd", "abcde"]
+        let correct: Vec<Word> = vec!["abc", "abcd", "abcde"]
             .into_iter()
-            .map(String::from)
+            .map(Word::from)
             .collect();
 
-        let result = remove_outside_lengths(words, 3, 5);
+        let result = remove_outside_lengths(words, 3, 5, LengthUnit::Bytes);
 
         assert_eq!(result, correct);
     }
 
+    #[test]
+    fn test_require_policy() {
+        let words: Vec<Word> = vec![
+            "password", "Password", "Passw0rd", "Passw0r", "P@ssw0rd", "Paaassw0rd", "Paassw0rd", "12345!ab",
+        ]
+            .into_iter()
+            .map(Word::from)
+            .collect();
+        let policy = Policy { min_length: 8, min_classes: 3, max_repeats: Some(2) };
+        let correct: Vec<Word> = vec!["Passw0rd", "P@ssw0rd", "Paassw0rd", "12345!ab"]
+            .into_iter()
+            .map(Word::from)
+            .collect();
+
+        assert_eq!(require_policy(words.clone(), &policy), correct);
+        assert_eq!(words.into_iter().require_policy(policy).collect::<Vec<Word>>(), correct);
+
+        let accented = Word::from("pässwör");
+        assert!(!meets_policy(&accented, &Policy { min_length: 8, min_classes: 0, max_repeats: None }));
+        assert!(meets_policy(&accented, &Policy { min_length: 7, min_classes: 0, max_repeats: None }));
+
+        assert_eq!(character_classes(&Word::from("Ünïcode1")), 3);
+        assert_eq!(character_classes(&Word::new(b"Passw\xf6rd".to_vec())), 3);
+        assert_eq!(longest_repeat(&Word::from("aßßßb")), 3);
+        assert_eq!(longest_repeat(&Word::from("")), 0);
+    }
+
+    #[test]
+    fn test_matching() {
+        let words: Vec<Word> = vec!["123456", "password", "admin@example.com", "Summer2024"]
+            .into_iter()
+            .map(Word::from)
+            .collect();
+        let patterns = Patterns::new(&[String::from(r"^\d+$"), String::from("@")]).unwrap();
+        let substitution = Substitution::parse(r"s/\d+$//").unwrap();
+
+        let kept: Vec<Word> = vec!["123456", "admin@example.com"].into_iter().map(Word::from).collect();
+        let removed: Vec<Word> = vec!["password", "Summer2024"].into_iter().map(Word::from).collect();
+        let replaced: Vec<Word> = vec!["", "password", "admin@example.com", "Summer"]
+            .into_iter()
+            .map(Word::from)
+            .collect();
+
+        assert_eq!(keep_matching(words.clone(), &patterns), kept);
+        assert_eq!(remove_matching(words.clone(), &patterns), removed);
+        assert_eq!(replace(words.clone(), &substitution), replaced);
+        assert_eq!(words.clone().into_iter().keep_matching(&patterns).collect::<Vec<Word>>(), kept);
+        assert_eq!(words.clone().into_iter().remove_matching(&patterns).collect::<Vec<Word>>(), removed);
+        assert_eq!(words.into_iter().replace(&substitution).collect::<Vec<Word>>(), replaced);
+    }
+
+    #[test]
+    fn test_classes() {
+        let words: Vec<Word> = vec!["hunter2", "admin@example.com", "<br>", "admin1:hunter2", "letmein"]
+            .into_iter()
+            .map(Word::from)
+            .collect();
+
+        let plain: Vec<Word> = vec!["hunter2", "letmein"].into_iter().map(Word::from).collect();
+        let classes = [Class::Email, Class::Markup, Class::Combo];
+
+        assert_eq!(keep_classes(words.clone(), &[Class::Plain]), plain);
+        assert_eq!(remove_classes(words.clone(), &classes), plain);
+        assert_eq!(words.clone().into_iter().keep_classes(&[Class::Plain]).collect::<Vec<Word>>(), plain);
+        assert_eq!(words.into_iter().remove_classes(&classes).collect::<Vec<Word>>(), plain);
+    }
+
+    #[test]
+    fn test_length_units() {
+        // pä, a with a combining diaeresis, and a wide character.
+        let words: Vec<Word> = vec!["pässwörd", "pa\u{308}ss", "漢字", "password"]
+            .into_iter()
+            .map(Word::from)
+            .collect();
+        let lengths = |unit| words.iter().map(|w| length_in(w, unit)).collect::<Vec<usize>>();
+
+        assert_eq!(lengths(LengthUnit::Bytes), vec![10, 6, 6, 8]);
+        assert_eq!(lengths(LengthUnit::Chars), vec![8, 5, 2, 8]);
+        assert_eq!(lengths(LengthUnit::Graphemes), vec![8, 4, 2, 8]);
+        assert_eq!(lengths(LengthUnit::Width), vec![8, 4, 4, 8]);
+        assert_eq!(length_in(&Word::new(b"caf\xe9".to_vec()), LengthUnit::Chars), 4);
+        assert_eq!(length_in(&Word::new(b"\xff\xff".to_vec()), LengthUnit::Chars), 2);
+
+        let kept: Vec<Word> = vec!["pässwörd", "password"].into_iter().map(Word::from).collect();
+        assert_eq!(remove_outside_lengths(words.clone(), 8, 8, LengthUnit::Chars), kept);
+        assert_eq!(
+            words.into_iter().remove_outside_lengths(8, 8, LengthUnit::Width).collect::<Vec<Word>>(),
+            kept
+        );
+        assert_eq!(LengthUnit::from_name("graphemes"), Some(LengthUnit::Graphemes));
+    }
+
+    #[test]
+    fn test_normalize() {
+        // Composed and decomposed é, the ﬁ ligature, and bytes that aren't UTF-8.
+        let words: Vec<Word> = vec![
+            Word::from("caf\u{e9}"),
+            Word::from("cafe\u{301}"),
+            Word::from("\u{fb01}ne"),
+            Word::new(b"caf\xe9".to_vec()),
+        ];
+        let nfd: Vec<Word> = vec![
+            Word::from("cafe\u{301}"),
+            Word::from("cafe\u{301}"),
+            Word::from("\u{fb01}ne"),
+            Word::new(b"caf\xe9".to_vec()),
+        ];
+
+        assert_eq!(normalize(words.clone(), NormalForm::Nfd), nfd);
+        assert_eq!(words.clone().into_iter().normalize(NormalForm::Nfd).collect::<Vec<Word>>(), nfd);
+        assert_eq!(normalize_word(words[1].clone(), NormalForm::Nfc), words[0]);
+        assert_eq!(normalize_word(words[2].clone(), NormalForm::Nfkc), "fine");
+        assert_eq!(normalize_word(words[2].clone(), NormalForm::Nfc), words[2]);
+
+        let original = Normalization {
+            form: NormalForm::Nfc,
+            spelling: Spelling::Original,
+        };
+        let normalized = Normalization {
+            form: NormalForm::Nfkc,
+            spelling: Spelling::Normalized,
+        };
+        assert_eq!(
+            deduplicate_normalized(words.clone(), DedupMode::First, original),
+            vec![words[0].clone(), words[2].clone(), words[3].clone()]
+        );
+        // The word is kept where its last copy was, but written as it was first seen.
+        assert_eq!(
+            deduplicate_normalized(vec![words[1].clone(), Word::from("x"), words[0].clone()], DedupMode::Last, original),
+            vec![Word::from("x"), words[1].clone()]
+        );
+        assert_eq!(
+            deduplicate_normalized(words[1..].to_vec(), DedupMode::First, normalized),
+            vec![words[0].clone(), Word::from("fine"), words[3].clone()]
+        );
+        assert_eq!(
+            merge_normalized(words.clone().into_iter().map(|w| (w, 2)), DedupMode::Frequency, original),
+            vec![(words[0].clone(), 4), (words[3].clone(), 2), (words[2].clone(), 2)]
+        );
+        assert_eq!(
+            merge_normalized(words.into_iter().map(|w| (w, 2)), DedupMode::First, original)
+                .into_iter()
+                .map(|(_, count)| count)
+                .collect::<Vec<u64>>(),
+            vec![4, 2, 2]
+        );
+    }
+
+    #[test]
+    fn test_template() {
+        let words: Vec<Word> = vec!["hello", "world"].into_iter().map(Word::from).collect();
+        let template = Template::load("{index}:{word}", TemplateMode::Full, vec![]).unwrap();
+        let correct: Vec<Word> = vec!["0:hello", "1:world"].into_iter().map(Word::from).collect();
+
+        assert_eq!(self::template(&template, words.clone()), correct);
+        assert_eq!(words.into_iter().template(&template).collect::<Vec<Word>>(), correct);
+    }
+
+    #[test]
+    fn test_case() {
+        let words: Vec<Word> = vec!["hello WORLD", "Straße", "émile", "a1b"]
+            .into_iter()
+            .map(Word::from)
+            .collect();
+        let latin1 = Word::new(b"passw\xf6rd".to_vec());
+
+        assert_eq!(lowercase(words.clone()), vec!["hello world", "straße", "émile", "a1b"]);
+        assert_eq!(uppercase(words.clone()), vec!["HELLO WORLD", "STRASSE", "ÉMILE", "A1B"]);
+        assert_eq!(title_case(words.clone()), vec!["Hello World", "Straße", "Émile", "A1b"]);
+        assert_eq!(toggle_case(words.clone(), 0), vec!["Hello WORLD", "straße", "Émile", "A1b"]);
+        assert_eq!(toggle_case(words.clone(), 4), vec!["hellO WORLD", "StraSSe", "émilE", "a1b"]);
+        assert_eq!(uppercase_word(latin1.clone()), Word::new(b"PASSW\xf6RD".to_vec()));
+        assert_eq!(title_case_word(latin1.clone()), Word::new(b"Passw\xf6rd".to_vec()));
+
+        assert_eq!(permute_case(vec![Word::from("a1B")], None), vec!["a1b", "a1B", "A1b", "A1B"]);
+        assert_eq!(permute_case(vec![Word::from("ab"), Word::from("c")], Some(3)), vec!["ab", "aB", "Ab", "c", "C"]);
+        assert_eq!(case_permutations(&Word::from("a".repeat(64).as_str()), None).len(), DEFAULT_MAX_PERMUTATIONS);
+        assert_eq!(case_permutations(&Word::from("é"), None), vec!["é", "É"]);
+        assert_eq!(case_permutations(&Word::from("12"), None), vec!["12"]);
+        assert_eq!(case_permutations(&latin1, None).len(), 128);
+
+        let words: Vec<Word> = vec!["password", "Password", "hello", "PASSWORD", "Password", "HELLO"]
+            .into_iter()
+            .map(Word::from)
+            .collect();
+        let correct: Vec<Word> = vec!["Password", "hello"].into_iter().map(Word::from).collect();
+        assert_eq!(deduplicate_caseless(words), correct);
+        assert_eq!(
+            merge_caseless(vec![(Word::from("a"), 2), (Word::from("A"), 3), (Word::from("a"), 2)]),
+            vec![(Word::from("a"), 7)]
+        );
+    }
+
     #[test]
    fn test_trim_whitespaces() {
-        let words: Vec<String> = vec![" Hello", "  Hello  ", "Hello", "\nHello\t", "\n\nHello  "]
+        let words: Vec<Word> = vec![" Hello", "  Hello  ", "Hello", "\nHello\t", "\n\nHello  "]
             .into_iter()
-            .map(String::from)
+            .map(Word::from)
             .collect();
-        let correct: Vec<String> = vec!["Hello", "Hello", "Hello", "Hello", "Hello"]
+        let correct: Vec<Word> = vec!["Hello", "Hello", "Hello", "Hello", "Hello"]
             .into_iter()
-            .map(String::from)
+            .map(Word::from)
             .collect();
         let result = trim_whitespaces(words);
         assert_eq!(result, correct);
@@ -327,40 +1935,127 @@ mod test {
 
     #[test]
     fn test_remove_counts() {
-        let words: Vec<String> = vec!["4 Hello", "120321 Password", "23124     PASSWORD", "Password", "123Password", "123 123Password1"]
+        let words: Vec<Word> = vec!["4 Hello", "120321 Password", "23124     PASSWORD", "Password", "123Password", "123 123Password1"]
             .into_iter()
-            .map(String::from)
+            .map(Word::from)
             .collect();
-        let correct: Vec<String> = vec!["Hello", "Password", "PASSWORD", "Password", "123Password", "123Password1"]
+        let correct: Vec<Word> = vec!["Hello", "Password", "PASSWORD", "Password", "123Password", "123Password1"]
             .into_iter()
-            .map(String::from)
+            .map(Word::from)
             .collect();
         let result = remove_counts(words);
         assert_eq!(result, correct);
     }
 
+    #[test]
+    fn test_split_count() {
+        let words: Vec<Word> = vec!["4 Hello", " 1230      World ", "World\t7", "Password", "123 123Password1", "12 ", "a\tb"]
+            .into_iter()
+            .map(Word::from)
+            .collect();
+        let correct: Vec<(Word, u64)> = vec![("Hello", 4), ("World", 1230), ("World", 7), ("Password", 1), ("123Password1", 123), ("12 ", 1), ("a\tb", 1)]
+            .into_iter()
+            .map(|(w, c)| (Word::from(w), c))
+            .collect();
+
+        let result: Vec<(Word, u64)> = words.into_iter().map(split_count).collect();
+
+        assert_eq!(result, correct);
+    }
+
+    #[test]
+    fn test_merge_counts() {
+        let counted = |words: Vec<(&str, u64)>| -> Vec<(Word, u64)> {
+            words.into_iter().map(|(w, c)| (Word::from(w), c)).collect()
+        };
+        let words = counted(vec![("bb", 2), ("aa", 1), ("cc", 5), ("bb", 2), ("aa", 3), ("dd", 4)]);
+
+        assert_eq!(merge_counts(words.clone(), DedupMode::First), counted(vec![("bb", 4), ("aa", 4), ("cc", 5), ("dd", 4)]));
+        assert_eq!(merge_counts(words.clone(), DedupMode::Last), counted(vec![("cc", 5), ("bb", 4), ("aa", 4), ("dd", 4)]));
+        assert_eq!(merge_counts(words.clone(), DedupMode::Sorted), counted(vec![("aa", 4), ("bb", 4), ("cc", 5), ("dd", 4)]));
+        assert_eq!(merge_counts(words, DedupMode::Frequency), counted(vec![("cc", 5), ("aa", 4), ("bb", 4), ("dd", 4)]));
+
+        assert_eq!(format_count(&Word::from("World"), 1230, CountFormat::CountWord), "1230 World");
+        assert_eq!(format_count(&Word::from("World"), 1230, CountFormat::WordTabCount), "World\t1230");
+    }
+
     #[test]
     fn test_prefix() {
-        let words: Vec<String> = vec!["Hello", "Password", "PASSWORD"]
+        let words: Vec<Word> = vec!["Hello", "Password", "PASSWORD"]
             .into_iter()
-            .map(String::from)
+            .map(Word::from)
             .collect();
-        let correct: Vec<String> = vec!["John Hello", "John Password", "John PASSWORD"]
+        let correct: Vec<Word> = vec!["John Hello", "John Password", "John PASSWORD"]
             .into_iter()
-            .map(String::from)
+            .map(Word::from)
             .collect();
-        let prefix: str = "John ";
-        let result = prefix(prefix, words);
+        let prefix_text: &str = "John ";
+        let result = prefix(prefix_text, words);
         assert_eq!(result, correct);
     }
     
+    #[test]
+    fn test_word_iterator() {
+        let words: Vec<Word> = vec!["  4 Hello", "Hello ", "2 Hi", "aa-a", "Passwords", "World"]
+            .into_iter()
+            .map(Word::from)
+            .collect();
+        let correct: Vec<Word> = vec!["root Hello", "root World"]
+            .into_iter()
+            .map(Word::from)
+            .collect();
+
+        let result: Vec<Word> = words
+            .into_iter()
+            .trim_whitespaces()
+            .remove_counts()
+            .deduplicate()
+            .remove_contains_symbols()
+            .remove_outside_lengths(5, 5, LengthUnit::Bytes)
+            .prefix("root ")
+            .collect();
+
+        assert_eq!(result, correct);
+    }
+
+    #[test]
+    fn test_stream_deduplicate_order() {
+        let words = vec!["bb", "aa", "bb", "cc", "aa"].into_iter().map(Word::from);
+        let correct: Vec<Word> = vec!["bb", "aa", "cc"]
+            .into_iter()
+            .map(Word::from)
+            .collect();
+
+        let result: Vec<Word> = words.deduplicate().collect();
+
+        assert_eq!(result, correct);
+    }
+
+    #[test]
+    fn test_non_utf8_words() {
+        // "Passwörd" in Latin-1, which isn't valid UTF-8.
+        let latin1 = Word::new(b"Passw\xf6rd".to_vec());
+        let words = [
+            Word::new(b"  Passw\xf6rd\t".to_vec()),
+            Word::new(b"12 Passw\xf6rd".to_vec()),
+            Word::new(b"Passw\xf6rd\t12".to_vec()),
+        ];
+
+        assert_eq!(trim_whitespace(words[0].clone()), latin1);
+        assert_eq!(remove_count(words[1].clone()), latin1);
+        assert_eq!(split_count(words[2].clone()), (latin1.clone(), 12));
+        assert!(remove_contains_symbols(vec![latin1.clone()]).is_empty());
+        assert_eq!(remove_lacking_symbols(vec![latin1.clone()]), vec![latin1.clone()]);
+        assert_eq!(prefix("root ", vec![latin1]), vec![Word::new(b"root Passw\xf6rd".to_vec())]);
+    }
+
     #[test]
     fn test_is_number() {
-        assert_eq!(is_number(&String::from("hello")), false);
-        assert_eq!(is_number(&String::from("1Helo")), false);
-        assert_eq!(is_number(&String::from("123")), true);
-        assert_eq!(is_number(&String::from("1 2")), false);
-        assert_eq!(is_number(&String::from("1helo2")), false);
-        assert_eq!(is_number(&String::from("039")), true);
+        assert!(!is_number(&String::from("hello")));
+        assert!(!is_number(&String::from("1Helo")));
+        assert!(is_number(&String::from("123")));
+        assert!(!is_number(&String::from("1 2")));
+        assert!(!is_number(&String::from("1helo2")));
+        assert!(is_number(&String::from("039")));
     }
 }
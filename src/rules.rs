@@ -0,0 +1,882 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::string::String;
+use std::vec::Vec;
+
+use crate::john;
+use crate::word::Word;
+
+/// The longest word a rule can make, like hashcat. Rules that make longer words reject them, which also stops
+/// rules like pZpZ from growing words exponentially.
+const MAX_LENGTH: usize = 256;
+
+/// Which of the words made by applying every rule to a word are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variants {
+    /// Every word a rule makes, even if another rule made it too.
+    All,
+    /// Only the first copy of each word made from the same word. Copies made from different words are kept.
+    Unique,
+}
+
+impl Variants {
+    /// Returns the mode with the given name, all or unique.
+    pub fn from_name(name: &str) -> Option<Variants> {
+        match name {
+            "all" => Some(Variants::All),
+            "unique" => Some(Variants::Unique),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Variants {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Variants::All => write!(f, "all"),
+            Variants::Unique => write!(f, "unique"),
+        }
+    }
+}
+
+/// The rule languages rules can be written in. Most functions mean the same thing in both, but a few differ,
+/// like < and > which are exclusive in John and inclusive in hashcat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    Hashcat,
+    John,
+}
+
+/// A set of bytes, like John's ?d for digits, with a bit for each byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Class([u64; 4]);
+
+impl Class {
+    /// Returns the class John writes as ?letter. Uppercase letters stand for every byte not in the lowercase class.
+    fn from_letter(letter: u8) -> Option<Class> {
+        let contains: fn(u8) -> bool = match letter.to_ascii_lowercase() {
+            b'v' => |c| b"aeiouAEIOU".contains(&c),
+            b'c' => |c| c.is_ascii_alphabetic() && !b"aeiouAEIOU".contains(&c),
+            b'w' => |c| c == b' ' || c == b'\t',
+            b'p' => |c| b".,:;'\"?!`".contains(&c),
+            b's' => |c| b"$%^&*()-_+=|\\<>[]{}#@/~".contains(&c),
+            b'l' => |c| c.is_ascii_lowercase(),
+            b'u' => |c| c.is_ascii_uppercase(),
+            b'd' => |c| c.is_ascii_digit(),
+            b'a' => |c| c.is_ascii_alphabetic(),
+            b'x' => |c| c.is_ascii_alphanumeric(),
+            b'o' => |c| c.is_ascii_control(),
+            b'b' => |c| c >= 0x80,
+            b'z' => |_| true,
+            _ => return None,
+        };
+
+        let complement = letter.is_ascii_uppercase();
+        let mut bits = [0u64; 4];
+        for c in 0..=255u8 {
+            if contains(c) != complement {
+                bits[c as usize / 64] |= 1 << (c % 64);
+            }
+        }
+
+        Some(Class(bits))
+    }
+
+    fn contains(&self, c: u8) -> bool {
+        self.0[c as usize / 64] & (1 << (c % 64)) != 0
+    }
+}
+
+/// What a function looks for in a word: one byte, or any byte of a class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Matcher {
+    Byte(u8),
+    Class(Class),
+}
+
+impl Matcher {
+    fn matches(&self, c: u8) -> bool {
+        match self {
+            Matcher::Byte(byte) => *byte == c,
+            Matcher::Class(class) => class.contains(c),
+        }
+    }
+}
+
+/// A position in a word. John can also give positions relative to the word the rule started with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Position {
+    At(usize),
+    /// Past the end of any word, written z.
+    End,
+    /// The length of the word, written l.
+    Length,
+    /// One less than the length of the word, written m.
+    LastIndex,
+}
+
+impl Position {
+    /// Turns the position into an index, given the length of the word the rule started with.
+    fn resolve(&self, initial_length: usize) -> usize {
+        match self {
+            Position::At(n) => *n,
+            Position::End => usize::MAX / 2,
+            Position::Length => initial_length,
+            Position::LastIndex => initial_length.saturating_sub(1),
+        }
+    }
+}
+
+/// One function of a rule, like $1 or sa@, with its arguments in the order they're written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Function {
+    name: u8,
+    positions: Vec<Position>,
+    bytes: Vec<u8>,
+    matcher: Option<Matcher>,
+    text: Vec<u8>,
+}
+
+/// The arguments each function takes, where N is a position (0-9 then A-Z for 10-35), X is any byte, C is a byte
+/// or (in John) a class like ?d, and S is a string between two copies of any byte, like "123".
+/// Returns None for functions that aren't supported.
+fn arguments(name: u8, dialect: Dialect) -> Option<&'static [u8]> {
+    match (name, dialect) {
+        (b'p' | b'P' | b'I' | b'M' | b'Q' | b'4' | b'6', Dialect::John) => Some(b""),
+        (b'X', Dialect::John) => Some(b"NNN"),
+        (b'A', Dialect::John) => Some(b"NS"),
+        (b'L' | b'R', Dialect::John) => None,
+        (b':' | b'l' | b'u' | b'c' | b'C' | b't' | b'r' | b'd' | b'f' | b'{' | b'}' | b'[' | b']', _)
+        | (b'q' | b'k' | b'K' | b'E', _) => Some(b""),
+        (b'T' | b'p' | b'D' | b'\'' | b'z' | b'Z' | b'L' | b'R' | b'+' | b'-' | b'.' | b',', _)
+        | (b'y' | b'Y' | b'<' | b'>' | b'_', _) => Some(b"N"),
+        (b'$' | b'^' | b'e', _) => Some(b"X"),
+        (b'@' | b'!' | b'/' | b'(' | b')', _) => Some(b"C"),
+        (b'x' | b'O' | b'*', _) => Some(b"NN"),
+        (b'i' | b'o' | b'3', _) => Some(b"NX"),
+        (b'=' | b'%', _) => Some(b"NC"),
+        (b's', _) => Some(b"CX"),
+        _ => None,
+    }
+}
+
+/// Turns a position character into the position it stands for, 0-9 then A-Z for 10-35.
+/// John also has z for past the end, l for the length of the word and m for one less.
+fn position(c: u8, dialect: Dialect) -> Option<Position> {
+    match (c, dialect) {
+        (b'0'..=b'9', _) => Some(Position::At((c - b'0') as usize)),
+        (b'A'..=b'Z', _) => Some(Position::At((c - b'A' + 10) as usize)),
+        (b'z', Dialect::John) => Some(Position::End),
+        (b'l', Dialect::John) => Some(Position::Length),
+        (b'm', Dialect::John) => Some(Position::LastIndex),
+        _ => None,
+    }
+}
+
+/// A rule: functions run one after another on a word, any of which may reject it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    dialect: Dialect,
+    functions: Vec<Function>,
+}
+
+impl Rule {
+    /// Parses a rule, like `c $1 $2` or `sa@ <8`.
+    /// Spaces between functions are ignored, but not where a function expects a character, so `$ ` adds a space.
+    /// John rules have to be run through the preprocessor first, see john::expand.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The rule, as one line of a .rule file.
+    /// * `dialect` - The rule language the rule is written in.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let rule = Rule::parse(b"c $1", Dialect::Hashcat).unwrap();
+    ///
+    /// assert_eq!(rule.apply(b"password"), Some(b"Password1".to_vec()));
+    /// ```
+    pub fn parse(text: &[u8], dialect: Dialect) -> Result<Rule, String> {
+        let mut functions = vec![];
+        let mut bytes = text.iter().copied();
+
+        while let Some(name) = bytes.next() {
+            if name == b' ' || name == b'\t' {
+                continue;
+            }
+
+            let kinds = match arguments(name, dialect) {
+                None => return Err(format!("unknown function '{}'", name as char)),
+                Some(kinds) => kinds,
+            };
+
+            let mut function = Function {
+                name,
+                positions: vec![],
+                bytes: vec![],
+                matcher: None,
+                text: vec![],
+            };
+            let mut next = || {
+                bytes.next().ok_or_else(|| {
+                    format!(
+                        "'{}' needs {} argument(s), found the end of the rule",
+                        name as char,
+                        kinds.len()
+                    )
+                })
+            };
+
+            for kind in kinds {
+                let found = next()?;
+
+                match kind {
+                    b'N' => match position(found, dialect) {
+                        None => {
+                            return Err(format!(
+                                "'{}' needs a position (0-9 or A-Z), found '{}'",
+                                name as char, found as char
+                            ))
+                        }
+                        // Past the end of the word isn't a count, and repeating a character that often can't be done.
+                        Some(Position::End) if b"zZyY".contains(&name) => {
+                            return Err(format!("'{}' needs a count, found the position 'z'", name as char))
+                        }
+                        Some(position) => function.positions.push(position),
+                    },
+                    b'C' if found == b'?' && dialect == Dialect::John => {
+                        let letter = next()?;
+                        function.matcher = Some(match letter {
+                            b'?' => Matcher::Byte(b'?'),
+                            _ => match Class::from_letter(letter) {
+                                None => {
+                                    return Err(format!("unknown character class '?{}'", letter as char))
+                                }
+                                Some(class) => Matcher::Class(class),
+                            },
+                        });
+                    }
+                    b'C' => function.matcher = Some(Matcher::Byte(found)),
+                    b'S' => loop {
+                        match next()? {
+                            c if c == found => break,
+                            c => function.text.push(c),
+                        }
+                    },
+                    _ => function.bytes.push(found),
+                }
+            }
+
+            functions.push(function);
+        }
+
+        Ok(Rule { dialect, functions })
+    }
+
+    /// Runs the rule on a word, returning the new word or None if the rule rejects it or makes a word longer than
+    /// 256 bytes.
+    /// Like hashcat and John, functions work on bytes and only change the case of ASCII letters.
+    ///
+    /// # Arguments
+    ///
+    /// * `word` - The word to run the rule on.
+    pub fn apply(&self, word: &[u8]) -> Option<Vec<u8>> {
+        let initial_length = word.len();
+        let mut memory = word.to_vec();
+        let mut word = word.to_vec();
+
+        for function in &self.functions {
+            if !run(function, self.dialect, &mut word, &mut memory, initial_length) || word.len() > MAX_LENGTH {
+                return None;
+            }
+        }
+
+        Some(word)
+    }
+}
+
+/// Flips the case of an ASCII letter.
+fn toggle(c: &mut u8) {
+    if c.is_ascii_lowercase() {
+        c.make_ascii_uppercase();
+    } else if c.is_ascii_uppercase() {
+        c.make_ascii_lowercase();
+    }
+}
+
+/// Lowercases a word, then uppercases its first letter and every letter after the separator.
+fn title(word: &mut [u8], separator: u8) {
+    word.make_ascii_lowercase();
+
+    let mut start = true;
+    for c in word.iter_mut() {
+        if start {
+            c.make_ascii_uppercase();
+        }
+        start = *c == separator;
+    }
+}
+
+/// Returns true if a byte is a lowercase vowel.
+fn is_vowel(c: u8) -> bool {
+    b"aeiou".contains(&c)
+}
+
+/// Doubles the last letter of a word that ends in b, g or p after something else, like John does before adding
+/// -ed or -ing, so "stop" becomes "stopp".
+fn double_last(word: &mut Vec<u8>) {
+    let length = word.len();
+    if b"bgp".contains(&word[length - 1]) && !b"bgp".contains(&word[length - 2]) {
+        word.push(word[length - 1]);
+    }
+}
+
+/// John's p: turns a lowercase English noun into its plural, like "crack" into "cracks".
+fn pluralize(word: &mut Vec<u8>) {
+    let length = word.len();
+    if length < 2 {
+        return;
+    }
+
+    let (last, before) = (word[length - 1], word[length - 2]);
+    if b"sxz".contains(&last) || (last == b'h' && (before == b'c' || before == b's')) {
+        word.extend_from_slice(b"es");
+    } else if last == b'f' && before != b'f' {
+        word.truncate(length - 1);
+        word.extend_from_slice(b"ves");
+    } else if last == b'e' && before == b'f' {
+        word.truncate(length - 2);
+        word.extend_from_slice(b"ves");
+    } else if last == b'y' && !is_vowel(before) {
+        word.truncate(length - 1);
+        word.extend_from_slice(b"ies");
+    } else {
+        word.push(b's');
+    }
+}
+
+/// John's P: turns a lowercase English verb into its past tense, like "crack" into "cracked".
+fn past_tense(word: &mut Vec<u8>) {
+    let length = word.len();
+    if length < 2 || word.ends_with(b"ed") {
+        return;
+    }
+
+    if word[length - 1] == b'y' {
+        word[length - 1] = b'i';
+    } else {
+        double_last(word);
+    }
+    if word.ends_with(b"e") {
+        word.push(b'd');
+    } else {
+        word.extend_from_slice(b"ed");
+    }
+}
+
+/// John's I: turns a lowercase English verb into its -ing form, like "crack" into "cracking".
+fn progressive(word: &mut Vec<u8>) {
+    let length = word.len();
+    if length < 2 || word.ends_with(b"ing") {
+        return;
+    }
+
+    if is_vowel(word[length - 1]) {
+        word.pop();
+    } else {
+        double_last(word);
+    }
+    word.extend_from_slice(b"ing");
+}
+
+/// Runs one function on a word. Returns false if the function rejects the word.
+/// Functions given a position past the end of the word leave it unchanged, like hashcat, except where John
+/// does something else.
+///
+/// # Arguments
+///
+/// * `function` - The function to run.
+/// * `dialect` - The rule language the function was written in.
+/// * `word` - The word to change.
+/// * `memory` - The word as it was when it was last memorized with M, starting as the word the rule was given.
+/// * `initial_length` - The length of the word the rule was given.
+fn run(
+    function: &Function,
+    dialect: Dialect,
+    word: &mut Vec<u8>,
+    memory: &mut Vec<u8>,
+    initial_length: usize,
+) -> bool {
+    let mut positions = function.positions.iter().map(|p| p.resolve(initial_length));
+    let (n, m, i) = (
+        positions.next().unwrap_or(0),
+        positions.next().unwrap_or(0),
+        positions.next().unwrap_or(0),
+    );
+    let x = function.bytes.first().copied().unwrap_or(0);
+    let matches = |c: u8| function.matcher.is_some_and(|matcher| matcher.matches(c));
+    let john = dialect == Dialect::John;
+    let length = word.len();
+
+    match function.name {
+        b':' => (),
+        b'l' => word.make_ascii_lowercase(),
+        b'u' => word.make_ascii_uppercase(),
+        b'c' | b'C' => {
+            let capitalize = function.name == b'c';
+            if capitalize {
+                word.make_ascii_lowercase();
+            } else {
+                word.make_ascii_uppercase();
+            }
+            if let Some(first) = word.first_mut() {
+                toggle(first);
+            }
+        }
+        b't' => word.iter_mut().for_each(toggle),
+        b'T' => {
+            if let Some(c) = word.get_mut(n) {
+                toggle(c);
+            }
+        }
+        b'r' => word.reverse(),
+        b'd' => word.extend_from_within(..),
+        b'p' if john => pluralize(word),
+        b'P' => past_tense(word),
+        b'I' => progressive(word),
+        b'p' => {
+            for _ in 0..n {
+                word.extend_from_within(..length);
+            }
+        }
+        b'f' => {
+            let reversed: Vec<u8> = word.iter().rev().copied().collect();
+            word.extend(reversed);
+        }
+        b'{' if length > 0 => word.rotate_left(1),
+        b'}' if length > 0 => word.rotate_right(1),
+        b'$' => word.push(x),
+        b'^' => word.insert(0, x),
+        b'A' => {
+            let n = n.min(length);
+            word.splice(n..n, function.text.iter().copied());
+        }
+        b'[' if length > 0 => {
+            word.remove(0);
+        }
+        b']' => {
+            word.pop();
+        }
+        b'D' if n < length => {
+            word.remove(n);
+        }
+        b'x' if john => {
+            word.drain(..n.min(length));
+            word.truncate(m);
+        }
+        b'x' if n < length && n + m <= length => {
+            word.truncate(n + m);
+            word.drain(..n);
+        }
+        b'O' if n < length && n + m <= length => {
+            word.drain(n..n + m);
+        }
+        b'i' if john => word.insert(n.min(length), x),
+        b'i' if n <= length => word.insert(n, x),
+        b'o' if n < length => word[n] = x,
+        b'\'' => word.truncate(n),
+        b's' => word.iter_mut().filter(|c| matches(**c)).for_each(|c| *c = x),
+        b'@' => word.retain(|c| !matches(*c)),
+        b'z' if length > 0 => {
+            let first = word[0];
+            word.splice(0..0, iter::repeat_n(first, n));
+        }
+        b'Z' if length > 0 => word.extend(iter::repeat_n(word[length - 1], n)),
+        b'q' => *word = word.iter().flat_map(|c| [*c, *c]).collect(),
+        b'k' if length >= 2 => word.swap(0, 1),
+        b'K' if length >= 2 => word.swap(length - 1, length - 2),
+        b'*' if n < length && m < length => word.swap(n, m),
+        b'L' if n < length => word[n] <<= 1,
+        b'R' if n < length => word[n] >>= 1,
+        b'+' if n < length => word[n] = word[n].wrapping_add(1),
+        b'-' if n < length => word[n] = word[n].wrapping_sub(1),
+        b'.' if n + 1 < length => word[n] = word[n + 1],
+        b',' if n >= 1 && n < length => word[n] = word[n - 1],
+        b'y' if n <= length => {
+            let start = word[..n].to_vec();
+            word.splice(0..0, start);
+        }
+        b'Y' if n <= length => word.extend_from_within(length - n..),
+        b'E' => title(word, b' '),
+        b'e' => title(word, x),
+        b'3' => {
+            let after = word
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| **c == x)
+                .nth(n)
+                .map(|(i, _)| i + 1);
+            if let Some(c) = after.and_then(|i| word.get_mut(i)) {
+                toggle(c);
+            }
+        }
+        b'M' => *memory = word.clone(),
+        b'Q' => return word != memory,
+        b'4' => word.extend_from_slice(memory),
+        b'6' => {
+            word.splice(0..0, memory.iter().copied());
+        }
+        b'X' if n < memory.len() && n + m <= memory.len() && i <= length => {
+            word.splice(i..i, memory[n..n + m].iter().copied());
+        }
+        b'<' if john => return length < n,
+        b'>' if john => return length > n,
+        b'<' => return length <= n,
+        b'>' => return length >= n,
+        b'_' => return length == n,
+        b'!' => return !word.iter().any(|c| matches(*c)),
+        b'/' => return word.iter().any(|c| matches(*c)),
+        b'(' => return word.first().is_some_and(|c| matches(*c)),
+        b')' => return word.last().is_some_and(|c| matches(*c)),
+        b'=' => return word.get(n).is_some_and(|c| matches(*c)),
+        b'%' => return word.iter().filter(|c| matches(**c)).count() >= n,
+        _ => (),
+    }
+
+    true
+}
+
+/// The rules of a rule file, which are all run on every word.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rules {
+    /// The file the rules were read from.
+    pub path: PathBuf,
+    pub dialect: Dialect,
+    /// For John, the [List.Rules:...] section of the file the rules were read from, if there was one.
+    pub section: Option<String>,
+    pub variants: Variants,
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    /// Reads a hashcat .rule file, one rule per line. Empty lines and lines starting with # are skipped.
+    /// Returns a human readable message if the file can't be read or has a rule that can't be parsed.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The .rule file to read.
+    /// * `variants` - Which of the words made from each word are kept.
+    pub fn load(path: &Path, variants: Variants) -> Result<Rules, String> {
+        let contents = read(path)?;
+
+        let mut rules = vec![];
+        for (i, line) in contents.split(|b| *b == b'\n').enumerate() {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            if line.is_empty() || line[0] == b'#' {
+                continue;
+            }
+
+            match Rule::parse(line, Dialect::Hashcat) {
+                Err(why) => return Err(format!("{} line {}: {}", path.display(), i + 1, why)),
+                Ok(rule) => rules.push(rule),
+            }
+        }
+
+        Ok(Rules {
+            path: path.to_path_buf(),
+            dialect: Dialect::Hashcat,
+            section: None,
+            variants,
+            rules,
+        })
+    }
+
+    /// Reads John the Ripper rules, either from a file of rules or from a section of a john.conf style file like
+    /// the KoreLogic or Jumbo rule sets. Each line is run through John's preprocessor, so one line can make many
+    /// rules, and rules whose flags don't apply are dropped, see john::rules.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file to read.
+    /// * `section` - The name of the [List.Rules:...] section to read, or None for every rule in the file.
+    /// * `variants` - Which of the words made from each word are kept.
+    pub fn load_john(path: &Path, section: Option<&str>, variants: Variants) -> Result<Rules, String> {
+        let contents = read(path)?;
+
+        let mut rules = vec![];
+        for (number, expanded) in john::rules(path, &contents, section)? {
+            for text in expanded {
+                match Rule::parse(&text, Dialect::John) {
+                    Err(why) => return Err(format!("{} line {}: {}", path.display(), number, why)),
+                    Ok(rule) => rules.push(rule),
+                }
+            }
+        }
+
+        Ok(Rules {
+            path: path.to_path_buf(),
+            dialect: Dialect::John,
+            section: section.map(String::from),
+            variants,
+            rules,
+        })
+    }
+
+    /// How many rules there are, which is the most words each word can be turned into.
+    pub fn count(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Runs every rule on a word, returning the words they make in the order of the rules.
+    /// Words that are rejected or left empty are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `word` - The word to run the rules on.
+    pub fn apply(&self, word: &Word) -> Vec<Word> {
+        let mut seen = HashSet::new();
+
+        self.rules
+            .iter()
+            .filter_map(|rule| rule.apply(word.as_bytes()))
+            .filter(|made| !made.is_empty())
+            .filter(|made| self.variants == Variants::All || seen.insert(made.clone()))
+            .map(Word::new)
+            .collect()
+    }
+}
+
+/// Reads a whole rule file.
+fn read(path: &Path) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|why| format!("couldn't read {}: {}", path.display(), why))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(rule: &str, word: &str) -> Option<String> {
+        run_rule(rule, word, Dialect::Hashcat)
+    }
+
+    fn run_rule(rule: &str, word: &str, dialect: Dialect) -> Option<String> {
+        Rule::parse(rule.as_bytes(), dialect)
+            .unwrap()
+            .apply(word.as_bytes())
+            .map(|made| String::from_utf8(made).unwrap())
+    }
+
+    #[test]
+    fn test_functions() {
+        let cases = [
+            (":", "p@ssW0rd", "p@ssW0rd"),
+            ("l", "p@ssW0rd", "p@ssw0rd"),
+            ("u", "p@ssW0rd", "P@SSW0RD"),
+            ("c", "p@ssW0rd", "P@ssw0rd"),
+            ("C", "p@ssW0rd", "p@SSW0RD"),
+            ("t", "p@ssW0rd", "P@SSw0RD"),
+            ("T3", "p@ssW0rd", "p@sSW0rd"),
+            ("r", "p@ssW0rd", "dr0Wss@p"),
+            ("d", "p@ssW0rd", "p@ssW0rdp@ssW0rd"),
+            ("p2", "abc", "abcabcabc"),
+            ("f", "abc", "abccba"),
+            ("{", "abc", "bca"),
+            ("}", "abc", "cab"),
+            ("$1", "abc", "abc1"),
+            ("^1", "abc", "1abc"),
+            ("[", "abc", "bc"),
+            ("]", "abc", "ab"),
+            ("D1", "abc", "ac"),
+            ("x12", "abcd", "bc"),
+            ("O12", "abcd", "ad"),
+            ("i1!", "abc", "a!bc"),
+            ("i3!", "abc", "abc!"),
+            ("o0!", "abc", "!bc"),
+            ("'2", "abc", "ab"),
+            ("ss$", "pass", "pa$$"),
+            ("@s", "pass", "pa"),
+            ("z2", "abc", "aaabc"),
+            ("Z2", "abc", "abccc"),
+            ("q", "abc", "aabbcc"),
+            ("k", "abc", "bac"),
+            ("K", "abc", "acb"),
+            ("*02", "abc", "cba"),
+            ("+0", "abc", "bbc"),
+            ("-1", "abc", "aac"),
+            (".0", "abc", "bbc"),
+            (",1", "abc", "aac"),
+            ("y2", "abc", "ababc"),
+            ("Y2", "abc", "abcbc"),
+            ("E", "hello WORLD", "Hello World"),
+            ("e-", "hello-WORLD", "Hello-World"),
+            ("30-", "pass-word-x", "pass-Word-x"),
+            ("c $1 $2", "password", "Password12"),
+            ("$ $!", "ab", "ab !"),
+        ];
+
+        for (rule, word, correct) in &cases {
+            assert_eq!(apply(rule, word).as_deref(), Some(*correct), "{} on {}", rule, word);
+        }
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        for rule in &["T5", "D5", "x42", "O22", "i4!", "o3!", "'5", "*05", ".2", ",0"] {
+            assert_eq!(apply(rule, "abc").as_deref(), Some("abc"), "{}", rule);
+        }
+    }
+
+    #[test]
+    fn test_rejections() {
+        let cases = [
+            ("<3", true),
+            ("<2", false),
+            (">3", true),
+            (">4", false),
+            ("_3", true),
+            ("_4", false),
+            ("!z", true),
+            ("!a", false),
+            ("/a", true),
+            ("/z", false),
+            ("(a", true),
+            ("(b", false),
+            (")c", true),
+            (")b", false),
+            ("=1b", true),
+            ("=1a", false),
+            ("%1a", true),
+            ("%2a", false),
+        ];
+
+        for (rule, kept) in &cases {
+            assert_eq!(apply(rule, "abc").is_some(), *kept, "{}", rule);
+        }
+        assert_eq!(apply("$1 <3", "abc"), None);
+
+        assert_eq!(apply("pZpZpZ", "abc"), None);
+        assert_eq!(apply("d", &"a".repeat(128)).map(|w| w.len()), Some(256));
+        assert_eq!(apply("d", &"a".repeat(129)), None);
+    }
+
+    #[test]
+    fn test_john() {
+        let john = |rule: &str, word: &str| run_rule(rule, word, Dialect::John);
+        let cases = [
+            ("<4", "abc", Some("abc")),
+            ("<3", "abc", None),
+            (">2", "abc", Some("abc")),
+            (">3", "abc", None),
+            ("x14", "abcd", Some("bcd")),
+            ("i9!", "abc", Some("abc!")),
+            ("Az\"12\"", "abc", Some("abc12")),
+            ("A0x12x", "abc", Some("12abc")),
+            ("Al/!/", "abc", Some("abc!")),
+            ("Dm", "abc", Some("ab")),
+            ("s?d#", "a1b2", Some("a#b#")),
+            ("@?D", "a1b2", Some("12")),
+            ("/?d", "abc", None),
+            ("!?u", "abc", Some("abc")),
+            ("(?l )?d", "a1", Some("a1")),
+            ("=1?v", "bad", Some("bad")),
+            ("%2?d", "a1b", None),
+            ("p", "box", Some("boxes")),
+            ("p", "party", Some("parties")),
+            ("p", "knife", Some("knives")),
+            ("p", "dog", Some("dogs")),
+            ("P", "crack", Some("cracked")),
+            ("P", "stop", Some("stopped")),
+            ("P", "bake", Some("baked")),
+            ("I", "crack", Some("cracking")),
+            ("I", "bake", Some("baking")),
+            ("c Q", "Abc", None),
+            ("c Q", "abc", Some("Abc")),
+            ("M $1 4", "ab", Some("ab1ab")),
+        ];
+
+        for (rule, word, correct) in &cases {
+            assert_eq!(john(rule, word).as_deref(), *correct, "{} on {}", rule, word);
+        }
+        assert_eq!(
+            Rule::parse(b"s?q1", Dialect::John).unwrap_err(),
+            "unknown character class '?q'"
+        );
+        assert_eq!(Rule::parse(b"L", Dialect::John).unwrap_err(), "unknown function 'L'");
+        assert_eq!(
+            Rule::parse(b"zz", Dialect::John).unwrap_err(),
+            "'z' needs a count, found the position 'z'"
+        );
+        assert_eq!(
+            Rule::parse(b"Zz", Dialect::John).unwrap_err(),
+            "'Z' needs a count, found the position 'z'"
+        );
+        assert_eq!(john("zl", "abc").as_deref(), Some("aaaabc"));
+        assert_eq!(Rule::parse(b"M", Dialect::Hashcat).unwrap_err(), "unknown function 'M'");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Rule::parse(b"c w", Dialect::Hashcat).unwrap_err(), "unknown function 'w'");
+        assert_eq!(
+            Rule::parse(b"$", Dialect::Hashcat).unwrap_err(),
+            "'$' needs 1 argument(s), found the end of the rule"
+        );
+        assert_eq!(
+            Rule::parse(b"Tz", Dialect::Hashcat).unwrap_err(),
+            "'T' needs a position (0-9 or A-Z), found 'z'"
+        );
+    }
+
+    #[test]
+    fn test_load_and_apply() {
+        let path = PathBuf::from("test_rules_load.rule");
+        fs::write(&path, "# comment\n:\r\nl\n\nc $1\n<3\n").unwrap();
+
+        let all = Rules::load(&path, Variants::All);
+        let unique = Rules::load(&path, Variants::Unique);
+        let bad_path = PathBuf::from("test_rules_load_bad.rule");
+        fs::write(&bad_path, ":\nc w\n").unwrap();
+        let bad = Rules::load(&bad_path, Variants::All);
+
+        for path in &[path, bad_path] {
+            if let Err(why) = fs::remove_file(path) {
+                eprintln!("couldn't remove testing file {}: {}", path.display(), why)
+            }
+        }
+
+        let all = all.unwrap();
+        assert_eq!(all.count(), 4);
+        assert_eq!(all.apply(&Word::from("hello")), vec!["hello", "hello", "Hello1"]);
+        assert_eq!(unique.unwrap().apply(&Word::from("hello")), vec!["hello", "Hello1"]);
+        assert_eq!(
+            bad.unwrap_err(),
+            "test_rules_load_bad.rule line 2: unknown function 'w'"
+        );
+    }
+
+    #[test]
+    fn test_load_john() {
+        let path = PathBuf::from("test_rules_load_john.conf");
+        fs::write(&path, "[List.Rules:Years]\nc Az\"19[5-9]\"\n-s u\n[List.Rules:Other]\nu\n").unwrap();
+
+        let years = Rules::load_john(&path, Some("Years"), Variants::All);
+        let every = Rules::load_john(&path, None, Variants::All);
+
+        if let Err(why) = fs::remove_file(&path) {
+            eprintln!("couldn't remove testing file {}: {}", path.display(), why)
+        }
+
+        let years = years.unwrap();
+        assert_eq!(years.count(), 5);
+        assert_eq!(years.section.as_deref(), Some("Years"));
+        assert_eq!(
+            years.apply(&Word::from("hello")),
+            vec!["Hello195", "Hello196", "Hello197", "Hello198", "Hello199"]
+        );
+        assert_eq!(every.unwrap().count(), 6);
+    }
+}
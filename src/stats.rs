@@ -0,0 +1,392 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::hash::Hash;
+use std::string::String;
+use std::vec::Vec;
+
+use crate::word::Word;
+
+/// The charset class of every combination of character classes, indexed by lower | upper << 1 | digit << 2 | special << 3.
+/// These are PACK's statsgen names, so reports can be compared.
+const CHARSETS: [&str; 16] = [
+    "empty",
+    "loweralpha",
+    "upperalpha",
+    "mixedalpha",
+    "numeric",
+    "loweralphanum",
+    "upperalphanum",
+    "mixedalphanum",
+    "special",
+    "loweralphaspecial",
+    "upperalphaspecial",
+    "mixedalphaspecial",
+    "specialnum",
+    "loweralphaspecialnum",
+    "upperalphaspecialnum",
+    "all",
+];
+
+/// Returns the charset class of a word, like loweralpha for "password" or mixedalphanum for "Passw0rd".
+/// Anything that isn't an ASCII letter or digit counts as special.
+///
+/// # Arguments
+///
+/// * `word` - The word to classify.
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(charset(b"hello123"), "loweralphanum");
+/// ```
+pub fn charset(word: &[u8]) -> &'static str {
+    let mut classes = 0;
+    for byte in word {
+        classes |= match byte {
+            b'a'..=b'z' => 1,
+            b'A'..=b'Z' => 2,
+            b'0'..=b'9' => 4,
+            _ => 8,
+        };
+    }
+    CHARSETS[classes]
+}
+
+/// Returns the hashcat mask that matches a word, like ?u?l?l?l?d for "Hello1".
+/// Printable ASCII symbols and spaces are ?s, and every other byte is ?b.
+///
+/// # Arguments
+///
+/// * `word` - The word to make a mask for.
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(mask(b"Hello1!"), "?u?l?l?l?l?d?s");
+/// ```
+pub fn mask(word: &[u8]) -> String {
+    word.iter()
+        .map(|byte| match byte {
+            b'a'..=b'z' => "?l",
+            b'A'..=b'Z' => "?u",
+            b'0'..=b'9' => "?d",
+            b' '..=b'~' => "?s",
+            _ => "?b",
+        })
+        .collect()
+}
+
+/// Splits the digits and symbols at the start and end off a word that has letters, like "12" and "!" from "12abc!".
+/// Words without letters, or without anything before or after them, have no prefix or suffix.
+///
+/// # Arguments
+///
+/// * `word` - The word to split.
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(affixes(b"12abc!"), (Some(&b"12"[..]), Some(&b"!"[..])));
+/// ```
+pub fn affixes(word: &[u8]) -> (Option<&[u8]>, Option<&[u8]>) {
+    let first = word.iter().position(u8::is_ascii_alphabetic);
+    let last = word.iter().rposition(u8::is_ascii_alphabetic);
+
+    match (first, last) {
+        (Some(first), Some(last)) => (
+            Some(&word[..first]).filter(|prefix| !prefix.is_empty()),
+            Some(&word[last + 1..]).filter(|suffix| !suffix.is_empty()),
+        ),
+        _ => (None, None),
+    }
+}
+
+/// Counts of how often each thing was seen, like each length or mask.
+type Counts<K> = HashMap<K, u64>;
+
+/// Returns the counts from most to least common, with ties in key order.
+fn most_common<K: Clone + Ord + Hash>(counts: &Counts<K>) -> Vec<(K, u64)> {
+    let mut sorted: Vec<(K, u64)> = counts.iter().map(|(key, count)| (key.clone(), *count)).collect();
+    sorted.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    sorted
+}
+
+/// Writes a string as a JSON string, quoted and escaped.
+fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Statistics about a list of words, like PACK's statsgen gives.
+#[derive(Debug, Default)]
+pub struct Stats {
+    words: u64,
+    lengths: BTreeMap<usize, u64>,
+    charsets: Counts<&'static str>,
+    masks: Counts<String>,
+    prefixes: Counts<Word>,
+    suffixes: Counts<Word>,
+}
+
+/// One line of a report: what was counted, how many words had it, and what percentage of all the words that is.
+struct Row {
+    key: String,
+    count: u64,
+    percent: f64,
+}
+
+impl Stats {
+    /// Adds a word to the statistics.
+    ///
+    /// # Arguments
+    ///
+    /// * `word` - The word to add.
+    /// * `count` - How many times the word was seen.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut stats = Stats::default();
+    /// stats.add(&Word::from("hello123"), 2);
+    /// ```
+    pub fn add(&mut self, word: &Word, count: u64) {
+        let bytes = word.as_bytes();
+        self.words += count;
+        *self.lengths.entry(bytes.len()).or_insert(0) += count;
+        *self.charsets.entry(charset(bytes)).or_insert(0) += count;
+        *self.masks.entry(mask(bytes)).or_insert(0) += count;
+
+        let (prefix, suffix) = affixes(bytes);
+        if let Some(prefix) = prefix {
+            *self.prefixes.entry(Word::from(prefix)).or_insert(0) += count;
+        }
+        if let Some(suffix) = suffix {
+            *self.suffixes.entry(Word::from(suffix)).or_insert(0) += count;
+        }
+    }
+
+    fn row(&self, key: String, count: u64) -> Row {
+        let percent = if self.words == 0 {
+            0.0
+        } else {
+            count as f64 * 100.0 / self.words as f64
+        };
+        Row { key, count, percent }
+    }
+
+    /// Every section of the report, as its name and rows. Lengths are in order, and everything else is most common first.
+    fn sections(&self, top: usize) -> Vec<(&'static str, Vec<Row>)> {
+        let lengths = self
+            .lengths
+            .iter()
+            .map(|(length, count)| self.row(length.to_string(), *count))
+            .collect();
+        let charsets = most_common(&self.charsets)
+            .into_iter()
+            .map(|(charset, count)| self.row(charset.to_string(), count))
+            .collect();
+        let masks = most_common(&self.masks)
+            .into_iter()
+            .take(top)
+            .map(|(mask, count)| self.row(mask, count))
+            .collect();
+        let prefixes = most_common(&self.prefixes)
+            .into_iter()
+            .take(top)
+            .map(|(prefix, count)| self.row(prefix.to_string(), count))
+            .collect();
+        let suffixes = most_common(&self.suffixes)
+            .into_iter()
+            .take(top)
+            .map(|(suffix, count)| self.row(suffix.to_string(), count))
+            .collect();
+
+        vec![
+            ("lengths", lengths),
+            ("charsets", charsets),
+            ("masks", masks),
+            ("prefixes", prefixes),
+            ("suffixes", suffixes),
+        ]
+    }
+
+    /// Writes the statistics as a report to read, with the masks' running total of how many words they cover.
+    ///
+    /// # Arguments
+    ///
+    /// * `top` - How many of the most common masks, prefixes and suffixes to list.
+    pub fn to_text(&self, top: usize) -> String {
+        let mut text = String::new();
+        writeln!(text, "Analyzed {} words.", self.words).unwrap();
+
+        for (name, rows) in self.sections(top) {
+            let title = match name {
+                "lengths" => "Lengths".to_string(),
+                "charsets" => "Charsets".to_string(),
+                name => {
+                    let mut title = name.to_string();
+                    title[..1].make_ascii_uppercase();
+                    format!("{} (top {})", title, top)
+                }
+            };
+            writeln!(text, "\n{}:", title).unwrap();
+
+            let width = rows.iter().map(|row| row.key.chars().count()).max().unwrap_or(0);
+            let mut covered = 0.0;
+            for row in rows {
+                write!(
+                    text,
+                    "  {:>width$}: {:6.2}% ({})",
+                    row.key,
+                    row.percent,
+                    row.count,
+                    width = width
+                )
+                .unwrap();
+                if name == "masks" {
+                    covered += row.percent;
+                    write!(text, ", {:.2}% covered", covered).unwrap();
+                }
+                text.push('\n');
+            }
+        }
+
+        text
+    }
+
+    /// Writes the statistics as a JSON object, with a section for each kind of statistic.
+    /// Each section is a list of objects with the key, count and percent, and masks also have how much they cover along with the masks before them.
+    ///
+    /// # Arguments
+    ///
+    /// * `top` - How many of the most common masks, prefixes and suffixes to list.
+    pub fn to_json(&self, top: usize) -> String {
+        let mut json = String::new();
+        write!(json, "{{\n  \"words\": {}", self.words).unwrap();
+
+        for (name, rows) in self.sections(top) {
+            let key = match name {
+                "lengths" => "length",
+                "charsets" => "charset",
+                "masks" => "mask",
+                "prefixes" => "prefix",
+                _ => "suffix",
+            };
+            write!(json, ",\n  \"{}\": [", name).unwrap();
+
+            let mut covered = 0.0;
+            for (i, row) in rows.iter().enumerate() {
+                let value = if name == "lengths" {
+                    row.key.clone()
+                } else {
+                    json_string(&row.key)
+                };
+                if i > 0 {
+                    json.push(',');
+                }
+                write!(
+                    json,
+                    "\n    {{\"{}\": {}, \"count\": {}, \"percent\": {:.2}",
+                    key, value, row.count, row.percent
+                )
+                .unwrap();
+                if name == "masks" {
+                    covered += row.percent;
+                    write!(json, ", \"covered\": {:.2}", covered).unwrap();
+                }
+                json.push('}');
+            }
+            json.push_str(if rows.is_empty() { "]" } else { "\n  ]" });
+        }
+
+        json.push_str("\n}\n");
+        json
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(charset(b"password"), "loweralpha");
+        assert_eq!(charset(b"PASSWORD"), "upperalpha");
+        assert_eq!(charset(b"123456"), "numeric");
+        assert_eq!(charset(b"!@#"), "special");
+        assert_eq!(charset(b"Password"), "mixedalpha");
+        assert_eq!(charset(b"Passw0rd"), "mixedalphanum");
+        assert_eq!(charset(b"p@ss 1"), "loweralphaspecialnum");
+        assert_eq!(charset(b"P@ssw0rd"), "all");
+        assert_eq!(charset("caf\u{e9}".as_bytes()), "loweralphaspecial");
+
+        assert_eq!(mask(b"Hello1!"), "?u?l?l?l?l?d?s");
+        assert_eq!(mask(b"a b\xff"), "?l?s?l?b");
+
+        assert_eq!(affixes(b"12abc!"), (Some(&b"12"[..]), Some(&b"!"[..])));
+        assert_eq!(affixes(b"abc2024"), (None, Some(&b"2024"[..])));
+        assert_eq!(affixes(b"a1b"), (None, None));
+        assert_eq!(affixes(b"123456"), (None, None));
+    }
+
+    #[test]
+    fn test_reports() {
+        let mut stats = Stats::default();
+        for word in &["password1", "monkey1", "dragon", "123456", "Summer2024!"] {
+            stats.add(&Word::from(*word), 1);
+        }
+        stats.add(&Word::from("\"quoted\"1"), 3);
+
+        let text = stats.to_text(2);
+        let expected = "Analyzed 8 words.
+
+Lengths:
+   6:  25.00% (2)
+   7:  12.50% (1)
+   9:  50.00% (4)
+  11:  12.50% (1)
+
+Charsets:
+  loweralphaspecialnum:  37.50% (3)
+         loweralphanum:  25.00% (2)
+                   all:  12.50% (1)
+            loweralpha:  12.50% (1)
+               numeric:  12.50% (1)
+
+Masks (top 2):
+  ?s?l?l?l?l?l?l?s?d:  37.50% (3), 37.50% covered
+        ?d?d?d?d?d?d:  12.50% (1), 50.00% covered
+
+Prefixes (top 2):
+  \":  37.50% (3)
+
+Suffixes (top 2):
+  \"1:  37.50% (3)
+   1:  25.00% (2)
+";
+        assert_eq!(text, expected);
+
+        let json = stats.to_json(1);
+        assert!(json.starts_with("{\n  \"words\": 8,\n  \"lengths\": [\n    {\"length\": 6, \"count\": 2, \"percent\": 25.00},"));
+        assert!(json.contains(
+            "\n  \"masks\": [\n    {\"mask\": \"?s?l?l?l?l?l?l?s?d\", \"count\": 3, \"percent\": 37.50, \"covered\": 37.50}\n  ],"
+        ));
+        assert!(json.ends_with("\n  \"suffixes\": [\n    {\"suffix\": \"\\\"1\", \"count\": 3, \"percent\": 37.50}\n  ]\n}\n"));
+
+        let empty = Stats::default().to_json(10);
+        assert!(empty.contains("\"lengths\": [],"));
+    }
+}
@@ -0,0 +1,360 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::string::String;
+use std::vec::Vec;
+
+use crate::word::Word;
+
+/// Where the template goes relative to the word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateMode {
+    /// The template is the whole word, with {word} where the word goes.
+    Full,
+    /// The template goes before the word.
+    Prefix,
+    /// The template goes after the word.
+    Suffix,
+    /// The template goes both before and after the word.
+    Wrap,
+}
+
+impl TemplateMode {
+    /// Returns the mode with the given name, full, prefix, suffix or wrap.
+    pub fn from_name(name: &str) -> Option<TemplateMode> {
+        match name {
+            "full" => Some(TemplateMode::Full),
+            "prefix" => Some(TemplateMode::Prefix),
+            "suffix" => Some(TemplateMode::Suffix),
+            "wrap" => Some(TemplateMode::Wrap),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TemplateMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TemplateMode::Full => write!(f, "full"),
+            TemplateMode::Prefix => write!(f, "prefix"),
+            TemplateMode::Suffix => write!(f, "suffix"),
+            TemplateMode::Wrap => write!(f, "wrap"),
+        }
+    }
+}
+
+/// A piece of a template.
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Literal(Vec<u8>),
+    Word,
+    /// The length of the word in bytes.
+    Length,
+    /// Where the word is in the stream, starting from 0.
+    Index,
+    /// A value from one of the lists, by its position in the template's lists.
+    List(usize),
+}
+
+/// A template like {word}{year}! that words are written into, with values from lists for placeholders other than
+/// {word}, {length} and {index}.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    /// The template as it was written.
+    pub text: String,
+    pub mode: TemplateMode,
+    /// The name and file of each list, in the order they were given.
+    pub lists: Vec<(String, PathBuf)>,
+    parts: Vec<Part>,
+    /// The values of each list, in the same order as lists.
+    values: Vec<Vec<Word>>,
+}
+
+/// Splits a template into its parts, where {name} is a placeholder and {{ and }} are braces.
+fn parse_parts(text: &str, lists: &[(String, PathBuf)]) -> Result<Vec<Part>, String> {
+    let mut parts = vec![];
+    let mut literal = vec![];
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        None => return Err(format!("template \"{}\" has a {{ without a }}", text)),
+                        Some('{') if name.is_empty() => {
+                            literal.push(b'{');
+                            break;
+                        }
+                        Some('}') => {
+                            if !literal.is_empty() {
+                                parts.push(Part::Literal(literal));
+                                literal = vec![];
+                            }
+                            parts.push(match name.as_str() {
+                                "word" => Part::Word,
+                                "length" => Part::Length,
+                                "index" => Part::Index,
+                                _ => match lists.iter().position(|(list, _)| *list == name) {
+                                    Some(i) => Part::List(i),
+                                    None => {
+                                        return Err(format!(
+                                            "template \"{}\" uses {{{}}}, which isn't word, length, index or a list given to it",
+                                            text, name
+                                        ))
+                                    }
+                                },
+                            });
+                            break;
+                        }
+                        Some(c) => name.push(c),
+                    }
+                }
+            }
+            '}' => {
+                if chars.next() != Some('}') {
+                    return Err(format!("template \"{}\" has a }} without a {{, write }}}} for a brace", text));
+                }
+                literal.push(b'}');
+            }
+            c => literal.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    if !literal.is_empty() {
+        parts.push(Part::Literal(literal));
+    }
+
+    Ok(parts)
+}
+
+/// Reads the values of a list, one per line, skipping empty lines.
+fn read_values(path: &Path) -> Result<Vec<Word>, String> {
+    let contents = fs::read(path).map_err(|why| format!("couldn't read {}: {}", path.display(), why))?;
+
+    Ok(contents
+        .split(|&c| c == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .filter(|line| !line.is_empty())
+        .map(Word::from)
+        .collect())
+}
+
+impl Template {
+    /// Parses a template and reads the lists its placeholders use.
+    /// Returns a human readable message if the template doesn't parse, a list can't be read or isn't used, or a
+    /// full template has nowhere for the word to go.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The template, like {word}{year}!.
+    /// * `mode` - Where the template goes relative to the word.
+    /// * `lists` - The name of each list and the file its values are read from.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// // Using a file with the lines 2023 and 2024.
+    /// let lists = vec![(String::from("year"), PathBuf::from("years.txt"))];
+    /// let template = Template::load("{word}{year}!", TemplateMode::Full, lists).unwrap();
+    ///
+    /// assert!(template.render(&Word::from("summer"), 0) == vec!["summer2023!", "summer2024!"]);
+    /// ```
+    pub fn load(text: &str, mode: TemplateMode, lists: Vec<(String, PathBuf)>) -> Result<Template, String> {
+        for (i, (name, _)) in lists.iter().enumerate() {
+            let mut chars = name.chars();
+            if !chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+            {
+                return Err(format!(
+                    "list name \"{}\" should be letters, digits and underscores, starting with a letter",
+                    name
+                ));
+            }
+            if ["word", "length", "index"].contains(&name.as_str()) {
+                return Err(format!("{} is already a placeholder and can't be the name of a list", name));
+            }
+            if lists[..i].iter().any(|(other, _)| other == name) {
+                return Err(format!("list {} is given more than once", name));
+            }
+        }
+
+        let parts = parse_parts(text, &lists)?;
+        if mode == TemplateMode::Full && !parts.contains(&Part::Word) {
+            return Err(format!(
+                "template \"{}\" has no {{word}}, which full templates need",
+                text
+            ));
+        }
+        for (i, (name, _)) in lists.iter().enumerate() {
+            if !parts.contains(&Part::List(i)) {
+                return Err(format!("list {} isn't used in template \"{}\"", name, text));
+            }
+        }
+
+        let values = lists
+            .iter()
+            .map(|(_, path)| read_values(path))
+            .collect::<Result<Vec<Vec<Word>>, String>>()?;
+
+        Ok(Template {
+            text: String::from(text),
+            mode,
+            lists,
+            parts,
+            values,
+        })
+    }
+
+    /// Returns true if the template uses {index}, so words have to go through it in order.
+    pub fn uses_index(&self) -> bool {
+        self.parts.contains(&Part::Index)
+    }
+
+    /// The most words each word can be turned into, one for every combination of values from the lists.
+    pub fn expansion(&self) -> usize {
+        self.values.iter().map(Vec::len).product()
+    }
+
+    /// Writes the template's parts, with the given value from each list, to the end of the buffer.
+    fn write(&self, buffer: &mut Vec<u8>, word: &Word, index: usize, choices: &[usize]) {
+        for part in &self.parts {
+            match part {
+                Part::Literal(bytes) => buffer.extend_from_slice(bytes),
+                Part::Word => buffer.extend_from_slice(word.as_bytes()),
+                Part::Length => buffer.extend_from_slice(word.len().to_string().as_bytes()),
+                Part::Index => buffer.extend_from_slice(index.to_string().as_bytes()),
+                Part::List(i) => buffer.extend_from_slice(self.values[*i][choices[*i]].as_bytes()),
+            }
+        }
+    }
+
+    /// Returns the word written into the template once for every combination of values from the lists, with the
+    /// last list changing fastest.
+    ///
+    /// # Arguments
+    ///
+    /// * `word` - The word to write into the template.
+    /// * `index` - Where the word is in the stream, for {index}.
+    pub fn render(&self, word: &Word, index: usize) -> Vec<Word> {
+        let mut made = vec![];
+        if self.values.iter().any(Vec::is_empty) {
+            return made;
+        }
+
+        let mut choices = vec![0; self.values.len()];
+        loop {
+            let mut buffer = vec![];
+            match self.mode {
+                TemplateMode::Full => self.write(&mut buffer, word, index, &choices),
+                TemplateMode::Prefix => {
+                    self.write(&mut buffer, word, index, &choices);
+                    buffer.extend_from_slice(word.as_bytes());
+                }
+                TemplateMode::Suffix => {
+                    buffer.extend_from_slice(word.as_bytes());
+                    self.write(&mut buffer, word, index, &choices);
+                }
+                TemplateMode::Wrap => {
+                    self.write(&mut buffer, word, index, &choices);
+                    buffer.extend_from_slice(word.as_bytes());
+                    self.write(&mut buffer, word, index, &choices);
+                }
+            }
+            made.push(Word::new(buffer));
+
+            let mut i = choices.len();
+            loop {
+                if i == 0 {
+                    return made;
+                }
+                i -= 1;
+                choices[i] += 1;
+                if choices[i] < self.values[i].len() {
+                    break;
+                }
+                choices[i] = 0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let years = PathBuf::from("test_template_years.txt");
+        fs::write(&years, "2023\r\n\n2024\n").unwrap();
+        let symbols = PathBuf::from("test_template_symbols.txt");
+        fs::write(&symbols, "!\n?\n").unwrap();
+
+        let lists = vec![
+            (String::from("year"), years.clone()),
+            (String::from("symbol"), symbols.clone()),
+        ];
+        let full = Template::load("{word}{year}{symbol}", TemplateMode::Full, lists.clone());
+        let suffix = Template::load("{year}", TemplateMode::Suffix, lists[..1].to_vec());
+        let unused = Template::load("{word}{year}", TemplateMode::Full, lists.clone());
+
+        for path in &[years, symbols] {
+            if let Err(why) = fs::remove_file(path) {
+                eprintln!("couldn't remove testing file {}: {}", path.display(), why)
+            }
+        }
+
+        let word = Word::from("summer");
+        let full = full.unwrap();
+        assert_eq!(
+            full.render(&word, 0),
+            vec!["summer2023!", "summer2023?", "summer2024!", "summer2024?"]
+        );
+        assert_eq!(full.expansion(), 4);
+        assert!(!full.uses_index());
+        assert_eq!(suffix.unwrap().render(&word, 0), vec!["summer2023", "summer2024"]);
+        assert_eq!(
+            unused.unwrap_err(),
+            "list symbol isn't used in template \"{word}{year}\""
+        );
+
+        let render = |text: &str, mode: TemplateMode| Template::load(text, mode, vec![]).unwrap().render(&word, 7);
+        assert_eq!(render("admin:{word}", TemplateMode::Full), vec!["admin:summer"]);
+        assert_eq!(render("{{{word}}} {length} #{index}", TemplateMode::Full), vec!["{summer} 6 #7"]);
+        assert_eq!(render("root ", TemplateMode::Prefix), vec!["root summer"]);
+        assert_eq!(render("*", TemplateMode::Wrap), vec!["*summer*"]);
+        assert!(Template::load("{index}", TemplateMode::Suffix, vec![]).unwrap().uses_index());
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let error = |text: &str, lists: Vec<(String, PathBuf)>| {
+            Template::load(text, TemplateMode::Full, lists).unwrap_err()
+        };
+
+        assert_eq!(
+            error("{word}{year}", vec![]),
+            "template \"{word}{year}\" uses {year}, which isn't word, length, index or a list given to it"
+        );
+        assert_eq!(error("{word", vec![]), "template \"{word\" has a { without a }");
+        assert_eq!(
+            error("{word}}", vec![]),
+            "template \"{word}}\" has a } without a {, write }} for a brace"
+        );
+        assert_eq!(error("admin", vec![]), "template \"admin\" has no {word}, which full templates need");
+        assert_eq!(
+            error("{word}{length}", vec![(String::from("length"), PathBuf::from("a"))]),
+            "length is already a placeholder and can't be the name of a list"
+        );
+        assert_eq!(
+            error("{word}{a}", vec![(String::from("a"), PathBuf::from("a")), (String::from("a"), PathBuf::from("b"))]),
+            "list a is given more than once"
+        );
+        assert_eq!(
+            error("{word}{a b}", vec![(String::from("a b"), PathBuf::from("a"))]),
+            "list name \"a b\" should be letters, digits and underscores, starting with a letter"
+        );
+        assert!(error("{word}{a}", vec![(String::from("a"), PathBuf::from("test_template_missing.txt"))])
+            .starts_with("couldn't read test_template_missing.txt: "));
+    }
+}
@@ -0,0 +1,88 @@
+use std::fmt;
+use std::str;
+use std::string::String;
+use std::vec::Vec;
+
+/// A word from a list, kept as the exact bytes it was read as.
+/// Most words are UTF-8, but leaked lists are full of Latin-1 and binary junk that are still worth keeping, so
+/// nothing is lost or replaced when a word isn't valid UTF-8. Processors that work on characters say what they do
+/// with words that aren't.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Word(Vec<u8>);
+
+impl Word {
+    /// Creates a word from its bytes.
+    pub fn new(bytes: Vec<u8>) -> Word {
+        Word(bytes)
+    }
+
+    /// The bytes of the word.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Returns the word as text, or None if it isn't valid UTF-8.
+    pub fn to_str(&self) -> Option<&str> {
+        str::from_utf8(&self.0).ok()
+    }
+
+    /// How many bytes long the word is.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if the word has no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl AsRef<[u8]> for Word {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Word {
+    fn from(bytes: Vec<u8>) -> Word {
+        Word(bytes)
+    }
+}
+
+impl From<&[u8]> for Word {
+    fn from(bytes: &[u8]) -> Word {
+        Word(bytes.to_vec())
+    }
+}
+
+impl From<String> for Word {
+    fn from(text: String) -> Word {
+        Word(text.into_bytes())
+    }
+}
+
+impl From<&str> for Word {
+    fn from(text: &str) -> Word {
+        Word(text.as_bytes().to_vec())
+    }
+}
+
+impl PartialEq<str> for Word {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl PartialEq<&str> for Word {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+/// Words display as text, with any bytes that aren't valid UTF-8 shown as the replacement character.
+/// Only use this for messages; files are always written from the bytes.
+impl fmt::Display for Word {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.0))
+    }
+}